@@ -0,0 +1,319 @@
+//! MPEG-4 audio decoder configuration: the `esds` DecoderConfigDescriptor and
+//! the AudioSpecificConfig it carries.
+//!
+//! See ISO/IEC 14496-1 § 7.2.6.6 and ISO/IEC 14496-3 § 1.6.2.1.
+
+use crate::bits::BitReader;
+use crate::boxes::Reader;
+use std::io::Result;
+
+const ES_DESCR_TAG: u8 = 0x03;
+const DECODER_CONFIG_DESCR_TAG: u8 = 0x04;
+
+const SAMPLING_FREQUENCIES: [u32; 13] = [
+    96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350,
+];
+
+/// DecoderConfigDescriptor fields.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DecoderConfig {
+    /// objectTypeIndication
+    pub object_type_indication: u8,
+    /// streamType
+    pub stream_type: u8,
+    /// bufferSizeDB in bytes
+    pub buffer_size_db: u32,
+    /// maxBitrate in bits per second
+    pub max_bitrate: u32,
+    /// avgBitrate in bits per second, 0 for variable bitrate
+    pub avg_bitrate: u32,
+}
+
+/// Decoded AudioSpecificConfig.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AudioSpecificConfig {
+    /// audioObjectType of the core coder
+    pub audio_object_type: u32,
+    /// samplingFrequencyIndex, 15 when the frequency is explicit
+    pub sampling_frequency_index: u32,
+    /// core sampling frequency in Hz
+    pub sampling_frequency: u32,
+    /// channelConfiguration
+    pub channel_configuration: u32,
+    /// true when SBR is signalled, explicitly or by a sync extension
+    pub sbr_present: bool,
+    /// true when parametric stereo is signalled
+    pub ps_present: bool,
+    /// output sampling frequency of the SBR extension
+    pub extension_sampling_frequency: Option<u32>,
+    /// samples per frame at the core sampling frequency
+    pub frame_length: u32,
+}
+
+fn descriptor_header(r: &mut Reader) -> Result<(u8, usize)> {
+    let tag = r.u8()?;
+    let mut size = 0usize;
+    for _ in 0..4 {
+        let b = r.u8()?;
+        size = (size << 7) | usize::from(b & 0x7f);
+        if b & 0x80 == 0 {
+            break;
+        }
+    }
+    Ok((tag, size))
+}
+
+/// Reads the DecoderConfigDescriptor from the descriptors of an `esds` box.
+pub fn read_decoder_config(esds: &[u8]) -> Result<Option<DecoderConfig>> {
+    let mut r = Reader::new(esds);
+    while r.remaining() > 0 {
+        let (tag, size) = descriptor_header(&mut r)?;
+        let mut d = Reader::new(r.bytes(size)?);
+        if tag == ES_DESCR_TAG {
+            d.skip(2)?;
+            let flags = d.u8()?;
+            if flags & 0x80 != 0 {
+                d.skip(2)?;
+            }
+            if flags & 0x40 != 0 {
+                let url_length = usize::from(d.u8()?);
+                d.skip(url_length)?;
+            }
+            if flags & 0x20 != 0 {
+                d.skip(2)?;
+            }
+            r = d;
+        } else if tag == DECODER_CONFIG_DESCR_TAG {
+            let object_type_indication = d.u8()?;
+            let stream_type = d.u8()? >> 2;
+            return Ok(Some(DecoderConfig {
+                object_type_indication,
+                stream_type,
+                buffer_size_db: d.u24()?,
+                max_bitrate: d.u32()?,
+                avg_bitrate: d.u32()?,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+fn audio_object_type(r: &mut BitReader) -> Result<u32> {
+    let aot = r.bits(5)?;
+    if aot == 31 {
+        Ok(32 + r.bits(6)?)
+    } else {
+        Ok(aot)
+    }
+}
+
+fn sampling_frequency(r: &mut BitReader) -> Result<(u32, u32)> {
+    let index = r.bits(4)?;
+    let frequency = match SAMPLING_FREQUENCIES.get(index as usize) {
+        Some(&frequency) => frequency,
+        None if index == 15 => r.bits(24)?,
+        None => 0,
+    };
+    Ok((index, frequency))
+}
+
+/// Decodes an AudioSpecificConfig.
+pub fn read_audio_specific_config(data: &[u8]) -> Result<AudioSpecificConfig> {
+    let mut r = BitReader::new(data);
+    let mut asc = AudioSpecificConfig {
+        audio_object_type: audio_object_type(&mut r)?,
+        frame_length: 1024,
+        ..Default::default()
+    };
+    (asc.sampling_frequency_index, asc.sampling_frequency) = sampling_frequency(&mut r)?;
+    asc.channel_configuration = r.bits(4)?;
+    let explicit = asc.audio_object_type == 5 || asc.audio_object_type == 29;
+    if explicit {
+        asc.sbr_present = true;
+        asc.ps_present = asc.audio_object_type == 29;
+        asc.extension_sampling_frequency = Some(sampling_frequency(&mut r)?.1);
+        asc.audio_object_type = audio_object_type(&mut r)?;
+        if asc.audio_object_type == 22 {
+            r.skip(4)?;
+        }
+    }
+    match asc.audio_object_type {
+        1 | 2 | 3 | 4 | 6 | 7 | 17 | 19 | 20 | 21 | 22 | 23 => {
+            // GASpecificConfig
+            if r.flag()? {
+                asc.frame_length = 960;
+            }
+            if r.flag()? {
+                r.skip(14)?;
+            }
+            let extension_flag = r.flag()?;
+            if asc.channel_configuration == 0 {
+                // program_config_element() is not decoded, so the sync extension cannot be found
+                return Ok(asc);
+            }
+            if asc.audio_object_type == 6 || asc.audio_object_type == 20 {
+                r.skip(3)?;
+            }
+            if extension_flag {
+                match asc.audio_object_type {
+                    22 => r.skip(16)?,
+                    17 | 19 | 20 | 23 => r.skip(3)?,
+                    _ => {}
+                }
+                r.skip(1)?;
+            }
+            if asc.audio_object_type == 23 {
+                asc.frame_length /= 2;
+            }
+        }
+        42 => {
+            // UsacConfig
+            if r.bits(5)? == 0x1f {
+                r.skip(24)?;
+            }
+            let core_sbr_frame_length_index = r.bits(3)?;
+            asc.frame_length = match core_sbr_frame_length_index {
+                0 => 768,
+                1 => 1024,
+                2 | 3 => 2048,
+                _ => 4096,
+            };
+            asc.sbr_present = core_sbr_frame_length_index >= 2;
+            return Ok(asc);
+        }
+        _ => return Ok(asc),
+    }
+    if !explicit && r.remaining() >= 16 && r.bits(11)? == 0x2b7 {
+        // backward compatible signalling of SBR and PS
+        if audio_object_type(&mut r)? == 5 {
+            asc.sbr_present = r.flag()?;
+            if asc.sbr_present {
+                asc.extension_sampling_frequency = Some(sampling_frequency(&mut r)?.1);
+                if r.remaining() >= 12 && r.bits(11)? == 0x548 {
+                    asc.ps_present = r.flag()?;
+                }
+            }
+        }
+    }
+    Ok(asc)
+}
+
+/// Name of the profile an AudioSpecificConfig signals, e.g. `HE-AACv2`.
+pub fn object_type_name(asc: &AudioSpecificConfig) -> &'static str {
+    if asc.audio_object_type == 2 && asc.ps_present {
+        return "HE-AACv2";
+    }
+    if asc.audio_object_type == 2 && asc.sbr_present {
+        return "HE-AAC";
+    }
+    match asc.audio_object_type {
+        1 => "AAC Main",
+        2 => "AAC-LC",
+        3 => "AAC SSR",
+        4 => "AAC LTP",
+        6 => "AAC Scalable",
+        17 => "ER AAC-LC",
+        19 => "ER AAC LTP",
+        20 => "ER AAC Scalable",
+        23 => "AAC-LD",
+        32 => "MPEG-1/2 Layer-1",
+        33 => "MPEG-1/2 Layer-2",
+        34 => "MPEG-1/2 Layer-3",
+        39 => "AAC-ELD",
+        42 => "xHE-AAC",
+        _ => "unknown",
+    }
+}
+
+/// Channel count and layout of a channelConfiguration.
+pub fn channel_layout(channel_configuration: u32) -> (u32, &'static str) {
+    match channel_configuration {
+        0 => (0, "program config element"),
+        1 => (1, "mono"),
+        2 => (2, "stereo"),
+        3 => (3, "3.0"),
+        4 => (4, "4.0"),
+        5 => (5, "5.0"),
+        6 => (6, "5.1"),
+        7 => (8, "7.1"),
+        11 => (7, "6.1"),
+        12 => (8, "7.1 rear surround"),
+        13 => (24, "22.2"),
+        14 => (8, "7.1 top front"),
+        _ => (0, "reserved"),
+    }
+}
+
+/// Prints the `[media.track.audio.esds]` and `[media.track.audio.aac]` sections.
+/// # Arguments
+/// * `esds` - descriptors of the `esds` box
+/// * `decoder_specific_info` - contents of the DecoderSpecificInfo descriptor
+pub fn print_report(esds: &[u8], decoder_specific_info: &[u8]) {
+    println!("[media.track.audio.esds]");
+    match read_decoder_config(esds) {
+        Ok(Some(dc)) => {
+            println!(
+                "object_type_indication = \"0x{:02x}\"",
+                dc.object_type_indication
+            );
+            println!("stream_type = {:?}", dc.stream_type);
+            println!("buffer_size_db = {:?}", dc.buffer_size_db);
+            println!("max_bitrate = {:?}", dc.max_bitrate);
+            println!("avg_bitrate = {:?}", dc.avg_bitrate);
+        }
+        Ok(None) => println!("error = {:?}", "esds has no DecoderConfigDescriptor"),
+        Err(e) => println!("error = \"{}\"", e),
+    }
+    if decoder_specific_info.is_empty() {
+        return;
+    }
+    println!("[media.track.audio.aac]");
+    let asc = match read_audio_specific_config(decoder_specific_info) {
+        Ok(asc) => asc,
+        Err(e) => {
+            println!("error = \"{}\"", e);
+            return;
+        }
+    };
+    let (channels, layout) = channel_layout(asc.channel_configuration);
+    println!("audio_object_type = {:?}", asc.audio_object_type);
+    println!("object_type_name = \"{}\"", object_type_name(&asc));
+    println!(
+        "sampling_frequency_index = {:?}",
+        asc.sampling_frequency_index
+    );
+    println!("sampling_frequency = {:?}", asc.sampling_frequency);
+    println!("channel_configuration = {:?}", asc.channel_configuration);
+    println!("channels = {:?}", channels);
+    println!("channel_layout = \"{}\"", layout);
+    println!("sbr_present = {:?}", asc.sbr_present);
+    println!("ps_present = {:?}", asc.ps_present);
+    if let Some(frequency) = asc.extension_sampling_frequency {
+        println!("extension_sampling_frequency = {:?}", frequency);
+    }
+    println!("frame_length = {:?}", asc.frame_length);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_audio_specific_config() {
+        // AAC-LC 44.1 kHz stereo with an SBR and PS sync extension at 44.1 kHz
+        let asc = read_audio_specific_config(&[0x12, 0x10, 0x56, 0xe5, 0xa5, 0x48, 0x80]).unwrap();
+        assert_eq!(asc.audio_object_type, 2);
+        assert_eq!(asc.sampling_frequency, 44100);
+        assert_eq!(asc.channel_configuration, 2);
+        assert!(asc.sbr_present);
+        assert!(asc.ps_present);
+        assert_eq!(object_type_name(&asc), "HE-AACv2");
+
+        // explicit HE-AAC: AOT 5, 24 kHz core, 48 kHz output, AAC-LC
+        let asc = read_audio_specific_config(&[0x2b, 0x11, 0x88, 0x00]).unwrap();
+        assert_eq!(asc.audio_object_type, 2);
+        assert_eq!(asc.sampling_frequency, 24000);
+        assert_eq!(asc.extension_sampling_frequency, Some(48000));
+        assert_eq!(object_type_name(&asc), "HE-AAC");
+    }
+}
@@ -0,0 +1,119 @@
+//! Apple Lossless codec configuration, the `alac` magic cookie.
+//!
+//! See ALACMagicCookieDescription.txt in the Apple Lossless Audio Codec sources.
+
+use crate::boxes::Reader;
+use std::io::Result;
+
+/// Decoded ALACSpecificConfig.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AlacConfig {
+    /// frameLength in samples per frame
+    pub frame_length: u32,
+    /// compatibleVersion
+    pub compatible_version: u8,
+    /// bitDepth
+    pub bit_depth: u8,
+    /// pb, mb and kb Rice tuning parameters
+    pub rice: (u8, u8, u8),
+    /// numChannels
+    pub channels: u8,
+    /// maxRun
+    pub max_run: u16,
+    /// maxFrameBytes, 0 when unknown
+    pub max_frame_bytes: u32,
+    /// avgBitRate in bits per second, 0 when unknown
+    pub avg_bitrate: u32,
+    /// sampleRate in Hz
+    pub sample_rate: u32,
+}
+
+/// Decodes an ALAC magic cookie.
+pub fn read_config(cookie: &[u8]) -> Result<AlacConfig> {
+    // older cookies are wrapped in 'frma' and 'alac' atoms; a plain cookie may
+    // instead be followed by an ALACChannelLayoutInfo
+    let mut cookie = cookie;
+    if cookie.get(4..8) == Some(b"frma") {
+        cookie = &cookie[12..];
+    }
+    if cookie.get(4..8) == Some(b"alac") {
+        cookie = cookie.get(12..).unwrap_or_default();
+    }
+    let mut r = Reader::new(cookie);
+    Ok(AlacConfig {
+        frame_length: r.u32()?,
+        compatible_version: r.u8()?,
+        bit_depth: r.u8()?,
+        rice: (r.u8()?, r.u8()?, r.u8()?),
+        channels: r.u8()?,
+        max_run: r.u16()?,
+        max_frame_bytes: r.u32()?,
+        avg_bitrate: r.u32()?,
+        sample_rate: r.u32()?,
+    })
+}
+
+/// Prints the `[media.track.audio.alac]` section.
+pub fn print_report(cookie: &[u8]) {
+    println!("[media.track.audio.alac]");
+    match read_config(cookie) {
+        Ok(config) => {
+            println!("frame_length = {:?}", config.frame_length);
+            println!("compatible_version = {:?}", config.compatible_version);
+            println!("bit_depth = {:?}", config.bit_depth);
+            println!("rice_history_mult = {:?}", config.rice.0);
+            println!("rice_initial_history = {:?}", config.rice.1);
+            println!("rice_limit = {:?}", config.rice.2);
+            println!("channels = {:?}", config.channels);
+            println!("max_run = {:?}", config.max_run);
+            println!("max_frame_bytes = {:?}", config.max_frame_bytes);
+            println!("avg_bitrate = {:?}", config.avg_bitrate);
+            println!("sample_rate = {:?}", config.sample_rate);
+        }
+        Err(e) => println!("error = \"{}\"", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn specific_config(channels: u8) -> Vec<u8> {
+        let mut config = 4096u32.to_be_bytes().to_vec();
+        config.extend_from_slice(&[0, 24, 40, 10, 14, channels, 0, 255]);
+        for value in [13_000u32, 2_304_000, 48000] {
+            config.extend_from_slice(&value.to_be_bytes());
+        }
+        config
+    }
+
+    #[test]
+    fn unit_alac_cookie_with_channel_layout() {
+        // 5.1 ALACSpecificConfig followed by a 'chan' ALACChannelLayoutInfo
+        let mut cookie = specific_config(6);
+        cookie.extend_from_slice(&24u32.to_be_bytes());
+        cookie.extend_from_slice(b"chan");
+        cookie.extend_from_slice(&[0; 4]);
+        cookie.extend_from_slice(&[0x00, 0x79, 0x00, 0x06]);
+        cookie.extend_from_slice(&[0; 8]);
+        assert_eq!(cookie.len(), 48);
+        let config = read_config(&cookie).unwrap();
+        assert_eq!(config.frame_length, 4096);
+        assert_eq!(config.bit_depth, 24);
+        assert_eq!(config.channels, 6);
+        assert_eq!(config.sample_rate, 48000);
+    }
+
+    #[test]
+    fn unit_alac_cookie_in_frma_atoms() {
+        let mut cookie = 12u32.to_be_bytes().to_vec();
+        cookie.extend_from_slice(b"frmaalac");
+        cookie.extend_from_slice(&36u32.to_be_bytes());
+        cookie.extend_from_slice(b"alac");
+        cookie.extend_from_slice(&[0; 4]);
+        cookie.extend_from_slice(&specific_config(2));
+        let config = read_config(&cookie).unwrap();
+        assert_eq!(config.channels, 2);
+        assert_eq!(config.sample_rate, 48000);
+    }
+}
@@ -0,0 +1,338 @@
+//! AV1 codec configuration (`av1C`) and sequence header OBU decoding.
+//!
+//! See the AV1 ISOBMFF binding § 2.3 and the AV1 bitstream specification § 5.5.
+
+use crate::bits::BitReader;
+use mp4parse::AV1ConfigBox;
+use std::io::{Error, ErrorKind, Result};
+
+const OBU_SEQUENCE_HEADER: u32 = 1;
+
+/// Decoded sequence header OBU fields.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SequenceHeader {
+    /// seq_profile
+    pub profile: u32,
+    /// still_picture
+    pub still_picture: bool,
+    /// reduced_still_picture_header
+    pub reduced_still_picture_header: bool,
+    /// num_units_in_display_tick and time_scale, when timing info is present
+    pub timing: Option<(u32, u32)>,
+    /// operating_points_cnt_minus_1 + 1
+    pub operating_points: u32,
+    /// seq_level_idx of operating point 0
+    pub level_idx: u32,
+    /// seq_tier of operating point 0
+    pub tier: u32,
+    /// max_frame_width_minus_1 + 1
+    pub max_frame_width: u32,
+    /// max_frame_height_minus_1 + 1
+    pub max_frame_height: u32,
+    /// use_128x128_superblock
+    pub use_128x128_superblock: bool,
+    /// enable_order_hint
+    pub enable_order_hint: bool,
+    /// enable_superres
+    pub enable_superres: bool,
+    /// enable_cdef
+    pub enable_cdef: bool,
+    /// enable_restoration
+    pub enable_restoration: bool,
+    /// bit depth from high_bitdepth and twelve_bit
+    pub bit_depth: u32,
+    /// mono_chrome
+    pub monochrome: bool,
+    /// color_primaries
+    pub colour_primaries: u32,
+    /// transfer_characteristics
+    pub transfer_characteristics: u32,
+    /// matrix_coefficients
+    pub matrix_coefficients: u32,
+    /// color_range, true for full range
+    pub full_range: bool,
+    /// subsampling_x and subsampling_y
+    pub subsampling: (u32, u32),
+    /// chroma_sample_position
+    pub chroma_sample_position: u32,
+    /// film_grain_params_present
+    pub film_grain_params_present: bool,
+}
+
+fn leb128(buf: &[u8], pos: &mut usize) -> Result<usize> {
+    let mut value = 0usize;
+    for i in 0..8 {
+        let byte = *buf
+            .get(*pos)
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "OBU truncated"))?;
+        *pos += 1;
+        value |= usize::from(byte & 0x7f) << (i * 7);
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    Ok(value)
+}
+
+/// Finds the sequence header OBU in the configOBUs of an `av1C` box.
+pub fn find_sequence_header(obus: &[u8]) -> Result<Option<SequenceHeader>> {
+    let mut pos = 0;
+    while pos < obus.len() {
+        let header = obus[pos];
+        pos += 1;
+        let obu_type = u32::from((header >> 3) & 0xf);
+        if header & 0x4 != 0 {
+            pos += 1;
+        }
+        let size = if header & 0x2 != 0 {
+            leb128(obus, &mut pos)?
+        } else {
+            obus.len().saturating_sub(pos)
+        };
+        let payload = obus
+            .get(pos..pos + size)
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "OBU truncated"))?;
+        if obu_type == OBU_SEQUENCE_HEADER {
+            return read_sequence_header(payload).map(Some);
+        }
+        pos += size;
+    }
+    Ok(None)
+}
+
+/// Decodes a sequence header OBU payload.
+pub fn read_sequence_header(payload: &[u8]) -> Result<SequenceHeader> {
+    let mut r = BitReader::new(payload);
+    let mut seq = SequenceHeader {
+        profile: r.bits(3)?,
+        still_picture: r.flag()?,
+        reduced_still_picture_header: r.flag()?,
+        operating_points: 1,
+        ..Default::default()
+    };
+    if seq.reduced_still_picture_header {
+        seq.level_idx = r.bits(5)?;
+    } else {
+        let mut buffer_delay_length = 0;
+        let mut decoder_model_info_present = false;
+        if r.flag()? {
+            seq.timing = Some((r.bits(32)?, r.bits(32)?));
+            if r.flag()? {
+                // num_ticks_per_picture_minus_1 uvlc
+                let mut zeros = 0;
+                while !r.flag()? {
+                    zeros += 1;
+                }
+                if zeros < 32 {
+                    r.skip(zeros)?;
+                }
+            }
+            decoder_model_info_present = r.flag()?;
+            if decoder_model_info_present {
+                buffer_delay_length = r.bits(5)? as usize + 1;
+                r.skip(32 + 5 + 5)?;
+            }
+        }
+        let initial_display_delay_present = r.flag()?;
+        seq.operating_points = r.bits(5)? + 1;
+        for i in 0..seq.operating_points {
+            r.skip(12)?;
+            let level_idx = r.bits(5)?;
+            let tier = if level_idx > 7 { r.bits(1)? } else { 0 };
+            if i == 0 {
+                seq.level_idx = level_idx;
+                seq.tier = tier;
+            }
+            if decoder_model_info_present && r.flag()? {
+                r.skip(2 * buffer_delay_length + 1)?;
+            }
+            if initial_display_delay_present && r.flag()? {
+                r.skip(4)?;
+            }
+        }
+    }
+    let width_bits = r.bits(4)? + 1;
+    let height_bits = r.bits(4)? + 1;
+    seq.max_frame_width = r.bits(width_bits)? + 1;
+    seq.max_frame_height = r.bits(height_bits)? + 1;
+    if !seq.reduced_still_picture_header && r.flag()? {
+        r.skip(7)?;
+    }
+    seq.use_128x128_superblock = r.flag()?;
+    r.skip(2)?;
+    if !seq.reduced_still_picture_header {
+        r.skip(4)?;
+        seq.enable_order_hint = r.flag()?;
+        if seq.enable_order_hint {
+            r.skip(2)?;
+        }
+        let force_screen_content_tools = if r.flag()? { 2 } else { r.bits(1)? };
+        if force_screen_content_tools > 0 && !r.flag()? {
+            r.skip(1)?;
+        }
+        if seq.enable_order_hint {
+            r.skip(3)?;
+        }
+    }
+    seq.enable_superres = r.flag()?;
+    seq.enable_cdef = r.flag()?;
+    seq.enable_restoration = r.flag()?;
+
+    let high_bitdepth = r.flag()?;
+    seq.bit_depth = match (seq.profile, high_bitdepth) {
+        (2, true) => {
+            if r.flag()? {
+                12
+            } else {
+                10
+            }
+        }
+        (_, true) => 10,
+        _ => 8,
+    };
+    seq.monochrome = seq.profile != 1 && r.flag()?;
+    (
+        seq.colour_primaries,
+        seq.transfer_characteristics,
+        seq.matrix_coefficients,
+    ) = if r.flag()? {
+        (r.bits(8)?, r.bits(8)?, r.bits(8)?)
+    } else {
+        (2, 2, 2)
+    };
+    if seq.monochrome {
+        seq.full_range = r.flag()?;
+        seq.subsampling = (1, 1);
+    } else if (
+        seq.colour_primaries,
+        seq.transfer_characteristics,
+        seq.matrix_coefficients,
+    ) == (1, 13, 0)
+    {
+        seq.full_range = true;
+    } else {
+        seq.full_range = r.flag()?;
+        seq.subsampling = match seq.profile {
+            0 => (1, 1),
+            1 => (0, 0),
+            _ if seq.bit_depth == 12 => {
+                let x = r.bits(1)?;
+                (x, if x == 1 { r.bits(1)? } else { 0 })
+            }
+            _ => (1, 0),
+        };
+        if seq.subsampling == (1, 1) {
+            seq.chroma_sample_position = r.bits(2)?;
+        }
+    }
+    if !seq.monochrome {
+        r.skip(1)?;
+    }
+    seq.film_grain_params_present = r.flag()?;
+    Ok(seq)
+}
+
+/// Level name of a seq_level_idx, e.g. `4.0`.
+fn level_name(level_idx: u32) -> String {
+    if level_idx == 31 {
+        return "max".to_string();
+    }
+    format!("{}.{}", 2 + (level_idx >> 2), level_idx & 3)
+}
+
+fn chroma_sample_position_name(position: u32) -> &'static str {
+    match position {
+        1 => "vertical",
+        2 => "colocated",
+        _ => "unknown",
+    }
+}
+
+/// Prints the `[media.track.video.av1]` section and the decoded sequence header.
+pub fn print_report(av1c: &AV1ConfigBox) {
+    let flags = av1c.raw_config.get(2).copied().unwrap_or(0);
+    println!("[media.track.video.av1]");
+    println!("seq_profile = {:?}", av1c.profile);
+    println!("seq_level_idx = {:?}", av1c.level);
+    println!("level = \"{}\"", level_name(u32::from(av1c.level)));
+    println!(
+        "tier = \"{}\"",
+        if av1c.tier == 0 { "Main" } else { "High" }
+    );
+    println!("high_bitdepth = {:?}", flags & 0x40 != 0);
+    println!("twelve_bit = {:?}", flags & 0x20 != 0);
+    println!("bit_depth = {:?}", av1c.bit_depth);
+    println!("monochrome = {:?}", av1c.monochrome);
+    println!("chroma_subsampling_x = {:?}", av1c.chroma_subsampling_x);
+    println!("chroma_subsampling_y = {:?}", av1c.chroma_subsampling_y);
+    println!(
+        "chroma_sample_position = \"{}\"",
+        chroma_sample_position_name(u32::from(av1c.chroma_sample_position))
+    );
+    if av1c.initial_presentation_delay_present {
+        println!(
+            "initial_presentation_delay = {:?}",
+            u32::from(av1c.initial_presentation_delay_minus_one) + 1
+        );
+    }
+    println!("config_obus_bytes = {:?}", av1c.config_obus().len());
+    match find_sequence_header(av1c.config_obus()) {
+        Ok(Some(seq)) => {
+            println!("[media.track.video.av1.sequence_header]");
+            println!("seq_profile = {:?}", seq.profile);
+            println!("still_picture = {:?}", seq.still_picture);
+            println!(
+                "reduced_still_picture_header = {:?}",
+                seq.reduced_still_picture_header
+            );
+            println!("operating_points = {:?}", seq.operating_points);
+            println!("seq_level_idx = {:?}", seq.level_idx);
+            println!("level = \"{}\"", level_name(seq.level_idx));
+            println!("tier = \"{}\"", if seq.tier == 0 { "Main" } else { "High" });
+            if let Some((num_units_in_display_tick, time_scale)) = seq.timing {
+                println!(
+                    "num_units_in_display_tick = {:?}",
+                    num_units_in_display_tick
+                );
+                println!("time_scale = {:?}", time_scale);
+            }
+            println!("max_frame_width = {:?}", seq.max_frame_width);
+            println!("max_frame_height = {:?}", seq.max_frame_height);
+            println!("bit_depth = {:?}", seq.bit_depth);
+            println!("monochrome = {:?}", seq.monochrome);
+            println!("colour_primaries = {:?}", seq.colour_primaries);
+            println!(
+                "transfer_characteristics = {:?}",
+                seq.transfer_characteristics
+            );
+            println!("matrix_coefficients = {:?}", seq.matrix_coefficients);
+            println!("full_range = {:?}", seq.full_range);
+            println!("subsampling_x = {:?}", seq.subsampling.0);
+            println!("subsampling_y = {:?}", seq.subsampling.1);
+            println!(
+                "chroma_sample_position = \"{}\"",
+                chroma_sample_position_name(seq.chroma_sample_position)
+            );
+            println!("use_128x128_superblock = {:?}", seq.use_128x128_superblock);
+            println!("enable_order_hint = {:?}", seq.enable_order_hint);
+            println!("enable_superres = {:?}", seq.enable_superres);
+            println!("enable_cdef = {:?}", seq.enable_cdef);
+            println!("enable_restoration = {:?}", seq.enable_restoration);
+            println!(
+                "film_grain_params_present = {:?}",
+                seq.film_grain_params_present
+            );
+            if seq.profile != u32::from(av1c.profile) || seq.level_idx != u32::from(av1c.level) {
+                println!(
+                    "warning = {:?}",
+                    "sequence header profile or level differs from av1C"
+                );
+            }
+        }
+        Ok(None) => {}
+        Err(e) => {
+            println!("[media.track.video.av1.sequence_header]");
+            println!("error = \"{}\"", e);
+        }
+    }
+}
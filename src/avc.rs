@@ -0,0 +1,507 @@
+//! H.264/AVC decoder configuration record (`avcC`) and parameter set decoding.
+//!
+//! See ISO/IEC 14496-15 § 5.3.3 and ITU-T H.264 § 7.3.2.
+
+use crate::bits::{self, BitReader};
+use crate::boxes::Reader;
+use std::io::{Error, ErrorKind, Result};
+
+/// Video signal description from the VUI.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VideoSignal {
+    /// video_format
+    pub video_format: u32,
+    /// video_full_range_flag
+    pub full_range: bool,
+    /// colour_primaries, when a colour description is present
+    pub colour_primaries: Option<u32>,
+    /// transfer_characteristics, when a colour description is present
+    pub transfer_characteristics: Option<u32>,
+    /// matrix_coefficients, when a colour description is present
+    pub matrix_coefficients: Option<u32>,
+}
+
+/// Decoded sequence parameter set fields.
+#[derive(Debug, Clone, Default)]
+pub struct Sps {
+    /// profile_idc
+    pub profile_idc: u8,
+    /// constraint_set0_flag to constraint_set5_flag, in the high six bits
+    pub constraint_flags: u8,
+    /// level_idc
+    pub level_idc: u8,
+    /// seq_parameter_set_id
+    pub id: u32,
+    /// chroma_format_idc
+    pub chroma_format_idc: u32,
+    /// luma bit depth
+    pub bit_depth_luma: u32,
+    /// chroma bit depth
+    pub bit_depth_chroma: u32,
+    /// max_num_ref_frames
+    pub max_num_ref_frames: u32,
+    /// frame_mbs_only_flag
+    pub frame_mbs_only: bool,
+    /// coded width in luma samples
+    pub coded_width: u32,
+    /// coded height in luma samples
+    pub coded_height: u32,
+    /// frame cropping offsets left, right, top, bottom in luma samples
+    pub crop: (u32, u32, u32, u32),
+    /// sample aspect ratio
+    pub sample_aspect_ratio: Option<(u32, u32)>,
+    /// video signal description
+    pub video_signal: Option<VideoSignal>,
+    /// num_units_in_tick and time_scale
+    pub timing: Option<(u32, u32)>,
+    /// fixed_frame_rate_flag
+    pub fixed_frame_rate: bool,
+    /// max_num_reorder_frames
+    pub max_num_reorder_frames: Option<u32>,
+    /// max_dec_frame_buffering
+    pub max_dec_frame_buffering: Option<u32>,
+}
+
+impl Sps {
+    /// Displayed width after cropping.
+    pub fn width(&self) -> u32 {
+        self.coded_width
+            .saturating_sub(self.crop.0.saturating_add(self.crop.1))
+    }
+
+    /// Displayed height after cropping.
+    pub fn height(&self) -> u32 {
+        self.coded_height
+            .saturating_sub(self.crop.2.saturating_add(self.crop.3))
+    }
+}
+
+/// Decoded picture parameter set fields.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Pps {
+    /// pic_parameter_set_id
+    pub id: u32,
+    /// seq_parameter_set_id
+    pub sps_id: u32,
+    /// entropy_coding_mode_flag, true for CABAC
+    pub cabac: bool,
+}
+
+/// AVCDecoderConfigurationRecord.
+#[derive(Debug, Clone, Default)]
+pub struct AvcConfig {
+    /// configurationVersion
+    pub version: u8,
+    /// AVCProfileIndication
+    pub profile: u8,
+    /// profile_compatibility
+    pub compatibility: u8,
+    /// AVCLevelIndication
+    pub level: u8,
+    /// NAL unit length field size in bytes
+    pub nal_length_size: u8,
+    /// sequence parameter set NAL units
+    pub sps: Vec<Vec<u8>>,
+    /// picture parameter set NAL units
+    pub pps: Vec<Vec<u8>>,
+}
+
+/// Parses an `avcC` payload.
+pub fn read_config(avcc: &[u8]) -> Result<AvcConfig> {
+    let mut r = Reader::new(avcc);
+    let mut config = AvcConfig {
+        version: r.u8()?,
+        profile: r.u8()?,
+        compatibility: r.u8()?,
+        level: r.u8()?,
+        nal_length_size: (r.u8()? & 0x3) + 1,
+        ..Default::default()
+    };
+    let sps_count = r.u8()? & 0x1f;
+    for _ in 0..sps_count {
+        let length = usize::from(r.u16()?);
+        config.sps.push(r.bytes(length)?.to_vec());
+    }
+    let pps_count = r.u8()?;
+    for _ in 0..pps_count {
+        let length = usize::from(r.u16()?);
+        config.pps.push(r.bytes(length)?.to_vec());
+    }
+    Ok(config)
+}
+
+fn scaling_list(r: &mut BitReader, size: usize) -> Result<()> {
+    let mut last = 8i32;
+    let mut next = 8i32;
+    for _ in 0..size {
+        if next != 0 {
+            let delta_scale = r.se()?;
+            if !(-128..=127).contains(&delta_scale) {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "delta_scale out of range",
+                ));
+            }
+            next = (last + delta_scale + 256) % 256;
+        }
+        if next != 0 {
+            last = next;
+        }
+    }
+    Ok(())
+}
+
+fn hrd_parameters(r: &mut BitReader) -> Result<()> {
+    let cpb_count = r.ue_plus(1)?;
+    r.skip(8)?;
+    for _ in 0..cpb_count {
+        r.ue()?;
+        r.ue()?;
+        r.skip(1)?;
+    }
+    r.skip(20)
+}
+
+/// Aspect ratio and video signal type at the start of the VUI, which H.264
+/// and H.265 code identically.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VuiHeader {
+    /// sample aspect ratio
+    pub sample_aspect_ratio: Option<(u32, u32)>,
+    /// video signal description
+    pub video_signal: Option<VideoSignal>,
+}
+
+/// Reads the VUI fields up to and including the video signal type.
+pub fn read_vui_header(r: &mut BitReader) -> Result<VuiHeader> {
+    let mut sample_aspect_ratio = None;
+    if r.flag()? {
+        let idc = r.bits(8)?;
+        sample_aspect_ratio = match idc {
+            255 => Some((r.bits(16)?, r.bits(16)?)),
+            // Table E-1
+            1..=16 => Some(
+                [
+                    (1, 1),
+                    (12, 11),
+                    (10, 11),
+                    (16, 11),
+                    (40, 33),
+                    (24, 11),
+                    (20, 11),
+                    (32, 11),
+                    (80, 33),
+                    (18, 11),
+                    (15, 11),
+                    (64, 33),
+                    (160, 99),
+                    (4, 3),
+                    (3, 2),
+                    (2, 1),
+                ][idc as usize - 1],
+            ),
+            _ => None,
+        };
+    }
+    if r.flag()? {
+        r.skip(1)?;
+    }
+    let mut video_signal = None;
+    if r.flag()? {
+        let mut signal = VideoSignal {
+            video_format: r.bits(3)?,
+            full_range: r.flag()?,
+            ..Default::default()
+        };
+        if r.flag()? {
+            signal.colour_primaries = Some(r.bits(8)?);
+            signal.transfer_characteristics = Some(r.bits(8)?);
+            signal.matrix_coefficients = Some(r.bits(8)?);
+        }
+        video_signal = Some(signal);
+    }
+    Ok(VuiHeader {
+        sample_aspect_ratio,
+        video_signal,
+    })
+}
+
+/// Decodes a sequence parameter set NAL unit, including its header byte.
+pub fn read_sps(nal: &[u8]) -> Result<Sps> {
+    let data = bits::rbsp(nal.get(1..).unwrap_or(&[]));
+    let mut r = BitReader::new(&data);
+    let mut sps = Sps {
+        profile_idc: r.bits(8)? as u8,
+        constraint_flags: r.bits(8)? as u8,
+        level_idc: r.bits(8)? as u8,
+        id: r.ue()?,
+        chroma_format_idc: 1,
+        bit_depth_luma: 8,
+        bit_depth_chroma: 8,
+        ..Default::default()
+    };
+    let mut separate_colour_plane = false;
+    if matches!(
+        sps.profile_idc,
+        100 | 110 | 122 | 244 | 44 | 83 | 86 | 118 | 128 | 138 | 139 | 134 | 135
+    ) {
+        sps.chroma_format_idc = r.ue()?;
+        if sps.chroma_format_idc == 3 {
+            separate_colour_plane = r.flag()?;
+        }
+        sps.bit_depth_luma = r.ue_plus(8)?;
+        sps.bit_depth_chroma = r.ue_plus(8)?;
+        r.skip(1)?;
+        if r.flag()? {
+            let lists = if sps.chroma_format_idc == 3 { 12 } else { 8 };
+            for i in 0..lists {
+                if r.flag()? {
+                    scaling_list(&mut r, if i < 6 { 16 } else { 64 })?;
+                }
+            }
+        }
+    }
+    r.ue()?;
+    match r.ue()? {
+        0 => {
+            r.ue()?;
+        }
+        1 => {
+            r.skip(1)?;
+            r.se()?;
+            r.se()?;
+            for _ in 0..r.ue()? {
+                r.se()?;
+            }
+        }
+        _ => {}
+    }
+    sps.max_num_ref_frames = r.ue()?;
+    r.skip(1)?;
+    let width_mbs = r.ue_plus(1)?;
+    let height_map_units = r.ue_plus(1)?;
+    sps.frame_mbs_only = r.flag()?;
+    if !sps.frame_mbs_only {
+        r.skip(1)?;
+    }
+    r.skip(1)?;
+    let field_factor = if sps.frame_mbs_only { 1 } else { 2 };
+    sps.coded_width = bits::mul(width_mbs, 16)?;
+    sps.coded_height = bits::mul(height_map_units, 16 * field_factor)?;
+    if r.flag()? {
+        let chroma_array_type = if separate_colour_plane {
+            0
+        } else {
+            sps.chroma_format_idc
+        };
+        let (unit_x, unit_y) = match chroma_array_type {
+            1 => (2, 2 * field_factor),
+            2 => (2, field_factor),
+            _ => (1, field_factor),
+        };
+        sps.crop = (
+            bits::mul(r.ue()?, unit_x)?,
+            bits::mul(r.ue()?, unit_x)?,
+            bits::mul(r.ue()?, unit_y)?,
+            bits::mul(r.ue()?, unit_y)?,
+        );
+        let (left, right, top, bottom) = sps.crop;
+        if u64::from(left) + u64::from(right) > u64::from(sps.coded_width)
+            || u64::from(top) + u64::from(bottom) > u64::from(sps.coded_height)
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "frame crop larger than the picture",
+            ));
+        }
+    }
+    if r.flag()? {
+        let header = read_vui_header(&mut r)?;
+        sps.sample_aspect_ratio = header.sample_aspect_ratio;
+        sps.video_signal = header.video_signal;
+        if r.flag()? {
+            r.ue()?;
+            r.ue()?;
+        }
+        if r.flag()? {
+            sps.timing = Some((r.bits(32)?, r.bits(32)?));
+            sps.fixed_frame_rate = r.flag()?;
+        }
+        let nal_hrd = r.flag()?;
+        if nal_hrd {
+            hrd_parameters(&mut r)?;
+        }
+        let vcl_hrd = r.flag()?;
+        if vcl_hrd {
+            hrd_parameters(&mut r)?;
+        }
+        if nal_hrd || vcl_hrd {
+            r.skip(1)?;
+        }
+        r.skip(1)?;
+        if r.flag()? {
+            r.skip(1)?;
+            for _ in 0..4 {
+                r.ue()?;
+            }
+            sps.max_num_reorder_frames = Some(r.ue()?);
+            sps.max_dec_frame_buffering = Some(r.ue()?);
+        }
+    }
+    Ok(sps)
+}
+
+/// Decodes a picture parameter set NAL unit, including its header byte.
+pub fn read_pps(nal: &[u8]) -> Result<Pps> {
+    let data = bits::rbsp(nal.get(1..).unwrap_or(&[]));
+    let mut r = BitReader::new(&data);
+    Ok(Pps {
+        id: r.ue()?,
+        sps_id: r.ue()?,
+        cabac: r.flag()?,
+    })
+}
+
+/// Profile name of a profile_idc, per H.264 Annex A.
+pub fn profile_name(profile_idc: u8, constraint_flags: u8) -> &'static str {
+    match profile_idc {
+        66 if constraint_flags & 0x40 != 0 => "Constrained Baseline",
+        66 => "Baseline",
+        77 => "Main",
+        88 => "Extended",
+        100 => "High",
+        110 => "High 10",
+        122 => "High 4:2:2",
+        244 => "High 4:4:4 Predictive",
+        44 => "CAVLC 4:4:4 Intra",
+        83 => "Scalable Baseline",
+        86 => "Scalable High",
+        118 => "Multiview High",
+        128 => "Stereo High",
+        _ => "Unknown",
+    }
+}
+
+/// Level name of a level_idc, e.g. `3.1`. Level 1b is signalled by level_idc 11
+/// with constraint_set3_flag in Baseline, Main and Extended profiles.
+pub fn level_name(profile_idc: u8, constraint_flags: u8, level_idc: u8) -> String {
+    if level_idc == 9
+        || (level_idc == 11 && constraint_flags & 0x10 != 0 && matches!(profile_idc, 66 | 77 | 88))
+    {
+        return "1b".to_string();
+    }
+    format!("{}.{}", level_idc / 10, level_idc % 10)
+}
+
+/// Prints the `[media.track.video.avc]` section and one subsection per parameter set.
+pub fn print_report(config: &AvcConfig) {
+    println!("[media.track.video.avc]");
+    println!("configuration_version = {:?}", config.version);
+    println!(
+        "profile = \"{}\"",
+        profile_name(config.profile, config.compatibility)
+    );
+    println!("profile_idc = {:?}", config.profile);
+    println!("constraint_flags = \"{:06b}\"", config.compatibility >> 2);
+    println!(
+        "level = \"{}\"",
+        level_name(config.profile, config.compatibility, config.level)
+    );
+    println!("level_idc = {:?}", config.level);
+    println!("nal_length_size = {:?}", config.nal_length_size);
+    println!("sps_count = {:?}", config.sps.len());
+    println!("pps_count = {:?}", config.pps.len());
+    for nal in &config.sps {
+        println!("[media.track.video.avc.sps]");
+        println!("bytes = {:?}", nal.len());
+        let sps = match read_sps(nal) {
+            Ok(sps) => sps,
+            Err(e) => {
+                println!("error = \"{}\"", e);
+                continue;
+            }
+        };
+        println!("id = {:?}", sps.id);
+        println!(
+            "profile = \"{}\"",
+            profile_name(sps.profile_idc, sps.constraint_flags)
+        );
+        println!(
+            "level = \"{}\"",
+            level_name(sps.profile_idc, sps.constraint_flags, sps.level_idc)
+        );
+        println!("chroma_format_idc = {:?}", sps.chroma_format_idc);
+        println!("bit_depth_luma = {:?}", sps.bit_depth_luma);
+        println!("bit_depth_chroma = {:?}", sps.bit_depth_chroma);
+        println!("coded_width = {:?}", sps.coded_width);
+        println!("coded_height = {:?}", sps.coded_height);
+        println!("width = {:?}", sps.width());
+        println!("height = {:?}", sps.height());
+        println!("frame_mbs_only = {:?}", sps.frame_mbs_only);
+        println!("max_num_ref_frames = {:?}", sps.max_num_ref_frames);
+        if let Some((width, height)) = sps.sample_aspect_ratio {
+            println!("sample_aspect_ratio = \"{}:{}\"", width, height);
+        }
+        if let Some(signal) = sps.video_signal {
+            println!("video_format = {:?}", signal.video_format);
+            println!("full_range = {:?}", signal.full_range);
+            if let Some(v) = signal.colour_primaries {
+                println!("colour_primaries = {:?}", v);
+            }
+            if let Some(v) = signal.transfer_characteristics {
+                println!("transfer_characteristics = {:?}", v);
+            }
+            if let Some(v) = signal.matrix_coefficients {
+                println!("matrix_coefficients = {:?}", v);
+            }
+        }
+        if let Some((num_units_in_tick, time_scale)) = sps.timing {
+            println!("num_units_in_tick = {:?}", num_units_in_tick);
+            println!("time_scale = {:?}", time_scale);
+            println!("fixed_frame_rate = {:?}", sps.fixed_frame_rate);
+            if num_units_in_tick > 0 {
+                println!(
+                    "frame_rate = {:.3}",
+                    f64::from(time_scale) / (2.0 * f64::from(num_units_in_tick))
+                );
+            }
+        }
+        if let Some(v) = sps.max_num_reorder_frames {
+            println!("max_num_reorder_frames = {:?}", v);
+        }
+        if let Some(v) = sps.max_dec_frame_buffering {
+            println!("max_dec_frame_buffering = {:?}", v);
+        }
+    }
+    for nal in &config.pps {
+        println!("[media.track.video.avc.pps]");
+        println!("bytes = {:?}", nal.len());
+        match read_pps(nal) {
+            Ok(pps) => {
+                println!("id = {:?}", pps.id);
+                println!("sps_id = {:?}", pps.sps_id);
+                println!(
+                    "entropy_coding = \"{}\"",
+                    if pps.cabac { "CABAC" } else { "CAVLC" }
+                );
+            }
+            Err(e) => println!("error = \"{}\"", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_scaling_list_delta_scale() {
+        // se(v) 200, outside -128..=127
+        let mut r = BitReader::new(&[0x00, 0xc8, 0x00]);
+        let err = scaling_list(&mut r, 16).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        // se(v) -1 then zero deltas
+        let mut r = BitReader::new(&[0b0111_1111, 0xff, 0xc0]);
+        scaling_list(&mut r, 16).unwrap();
+    }
+}
@@ -0,0 +1,144 @@
+//! Bit level reading of codec parameter sets.
+
+use std::io::{Error, ErrorKind, Result};
+
+/// Removes emulation prevention bytes (`00 00 03`) from a NAL unit payload,
+/// giving the raw byte sequence payload that parameter sets are coded in.
+pub fn rbsp(nal: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(nal.len());
+    let mut zeros = 0;
+    for &b in nal {
+        if zeros >= 2 && b == 3 {
+            zeros = 0;
+            continue;
+        }
+        zeros = if b == 0 { zeros + 1 } else { 0 };
+        out.push(b);
+    }
+    out
+}
+
+/// Big-endian bit reader with Exp-Golomb support.
+#[derive(Debug)]
+pub struct BitReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    /// constructor
+    pub fn new(buf: &'a [u8]) -> BitReader<'a> {
+        BitReader { buf, pos: 0 }
+    }
+
+    /// Reads one bit.
+    pub fn bit(&mut self) -> Result<u32> {
+        let byte = self
+            .buf
+            .get(self.pos / 8)
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "bitstream truncated"))?;
+        let bit = (byte >> (7 - self.pos % 8)) & 1;
+        self.pos += 1;
+        Ok(u32::from(bit))
+    }
+
+    /// Reads one bit as a flag.
+    pub fn flag(&mut self) -> Result<bool> {
+        Ok(self.bit()? == 1)
+    }
+
+    /// Reads up to 32 bits as an unsigned integer.
+    pub fn bits(&mut self, n: u32) -> Result<u32> {
+        let mut value = 0u32;
+        for _ in 0..n {
+            value = (value << 1) | self.bit()?;
+        }
+        Ok(value)
+    }
+
+    /// Number of bits left to read.
+    pub fn remaining(&self) -> usize {
+        (self.buf.len() * 8).saturating_sub(self.pos)
+    }
+
+    /// Skips `n` bits.
+    pub fn skip(&mut self, n: usize) -> Result<()> {
+        if self.pos + n > self.buf.len() * 8 {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "bitstream truncated"));
+        }
+        self.pos += n;
+        Ok(())
+    }
+
+    /// Reads an unsigned Exp-Golomb code, `ue(v)`.
+    pub fn ue(&mut self) -> Result<u32> {
+        let mut zeros = 0;
+        while self.bit()? == 0 {
+            zeros += 1;
+            if zeros > 31 {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "invalid Exp-Golomb code",
+                ));
+            }
+        }
+        Ok(((1u64 << zeros) - 1 + u64::from(self.bits(zeros)?)) as u32)
+    }
+
+    /// Reads a signed Exp-Golomb code, `se(v)`.
+    pub fn se(&mut self) -> Result<i32> {
+        let k = self.ue()?;
+        Ok(if k % 2 == 1 {
+            k.div_ceil(2) as i32
+        } else {
+            -((k / 2) as i32)
+        })
+    }
+
+    /// Reads `ue(v)` plus `offset`, for syntax elements coded as `_minus1`
+    /// or `_minus8`.
+    pub fn ue_plus(&mut self, offset: u32) -> Result<u32> {
+        self.ue()?.checked_add(offset).ok_or_else(overflow)
+    }
+}
+
+fn overflow() -> Error {
+    Error::new(ErrorKind::InvalidData, "parameter set value out of range")
+}
+
+/// Multiplies parameter set values, failing with `InvalidData` on overflow.
+pub fn mul(a: u32, b: u32) -> Result<u32> {
+    a.checked_mul(b).ok_or_else(overflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_exp_golomb() {
+        // 1 | 010 | 011 | 00100 | 00100
+        let buf = [0b1010_0110, 0b0100_0010, 0b0000_0000];
+        let mut r = BitReader::new(&buf);
+        assert_eq!(r.ue().unwrap(), 0);
+        assert_eq!(r.ue().unwrap(), 1);
+        assert_eq!(r.se().unwrap(), -1);
+        assert_eq!(r.ue().unwrap(), 3);
+        assert_eq!(r.se().unwrap(), 2);
+        assert_eq!(rbsp(&[0, 0, 3, 1, 0, 0, 3]), vec![0, 0, 1, 0, 0]);
+    }
+
+    #[test]
+    fn unit_exp_golomb_overflow() {
+        // 31 zeros, a one and 31 ones: the largest ue(v), 2^32 - 2
+        let buf = [0, 0, 0, 1, 0xff, 0xff, 0xff, 0xfe];
+        assert_eq!(BitReader::new(&buf).ue().unwrap(), u32::MAX - 1);
+        let err = BitReader::new(&buf).ue_plus(8).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(
+            mul(u32::MAX - 1, 16).unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+        assert_eq!(mul(120, 16).unwrap(), 1920);
+    }
+}
@@ -0,0 +1,362 @@
+//! Minimal ISO BMFF box walker.
+//!
+//! mp4parse only exposes the parsed `moov` contents, so anything that needs
+//! byte offsets or boxes mp4parse skips walks the file buffer with this module.
+
+use std::io::{Error, ErrorKind, Result};
+
+/// A box located within the file buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mp4Box {
+    /// four character code
+    pub name: [u8; 4],
+    /// offset of the box header from the start of the buffer
+    pub offset: usize,
+    /// size of the box header, including any largesize field
+    pub header_size: usize,
+    /// total size of the box, including the header
+    pub size: usize,
+}
+
+impl Mp4Box {
+    /// Returns true when the box has the given four character code.
+    pub fn is(&self, name: &[u8; 4]) -> bool {
+        &self.name == name
+    }
+
+    /// Offset of the first byte after the box header.
+    pub fn body_offset(&self) -> usize {
+        self.offset + self.header_size
+    }
+
+    /// Offset of the first byte after the box.
+    pub fn end(&self) -> usize {
+        self.offset + self.size
+    }
+
+    /// Box payload, excluding the header.
+    pub fn body<'a>(&self, buf: &'a [u8]) -> &'a [u8] {
+        &buf[self.body_offset()..self.end()]
+    }
+}
+
+/// Reads consecutive box headers between `start` and `end`.
+/// Walking stops at the first header that is truncated or overruns `end`.
+pub fn parse_boxes(buf: &[u8], start: usize, end: usize) -> Vec<Mp4Box> {
+    let end = end.min(buf.len());
+    let mut boxes = Vec::new();
+    let mut offset = start;
+    while offset + 8 <= end {
+        let mut r = Reader::new(&buf[offset..end]);
+        let (size32, name) = match (r.u32(), r.bytes(4)) {
+            (Ok(size), Ok(name)) => (size, [name[0], name[1], name[2], name[3]]),
+            _ => break,
+        };
+        let (header_size, size) = match size32 {
+            0 => (8, end - offset),
+            1 => match r.u64() {
+                Ok(size) => (16, size as usize),
+                Err(_) => break,
+            },
+            size => (8, size as usize),
+        };
+        if size < header_size || offset.checked_add(size).is_none_or(|e| e > end) {
+            break;
+        }
+        boxes.push(Mp4Box {
+            name,
+            offset,
+            header_size,
+            size,
+        });
+        offset += size;
+    }
+    boxes
+}
+
+/// Top level boxes of the file.
+pub fn top_level(buf: &[u8]) -> Vec<Mp4Box> {
+    parse_boxes(buf, 0, buf.len())
+}
+
+/// Child boxes of a container box.
+/// Skips the fields that precede the children of `meta`, `stsd` and `dref`.
+pub fn children(buf: &[u8], parent: &Mp4Box) -> Vec<Mp4Box> {
+    let skip = match &parent.name {
+        // ISO meta is a full box, QuickTime meta is a plain container
+        b"meta" => match buf.get(parent.body_offset() + 4..parent.body_offset() + 8) {
+            Some(b"hdlr") => 0,
+            _ => 4,
+        },
+        b"stsd" | b"dref" => 8,
+        _ => 0,
+    };
+    parse_boxes(buf, parent.body_offset() + skip, parent.end())
+}
+
+/// First child box of `parent` with the given name.
+pub fn child(buf: &[u8], parent: &Mp4Box, name: &[u8; 4]) -> Option<Mp4Box> {
+    children(buf, parent).into_iter().find(|b| b.is(name))
+}
+
+/// Follows `path` from `parent`, taking the first match at each level.
+pub fn find_path(buf: &[u8], parent: &Mp4Box, path: &[&[u8; 4]]) -> Option<Mp4Box> {
+    let mut current = *parent;
+    for name in path {
+        current = child(buf, &current, name)?;
+    }
+    Some(current)
+}
+
+/// First top level box with the given name.
+pub fn find_top(buf: &[u8], name: &[u8; 4]) -> Option<Mp4Box> {
+    top_level(buf).into_iter().find(|b| b.is(name))
+}
+
+/// `trak` boxes of the movie, in file order.
+/// This is the same order mp4parse uses for `MediaContext::tracks`.
+pub fn traks(buf: &[u8]) -> Vec<Mp4Box> {
+    match find_top(buf, b"moov") {
+        Some(moov) => children(buf, &moov)
+            .into_iter()
+            .filter(|b| b.is(b"trak"))
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Sample entries of a `trak`, in sample description index order.
+pub fn sample_entries(buf: &[u8], trak: &Mp4Box) -> Vec<Mp4Box> {
+    find_path(buf, trak, &[b"mdia", b"minf", b"stbl", b"stsd"])
+        .map_or_else(Vec::new, |stsd| children(buf, &stsd))
+}
+
+/// data_reference_index of a sample entry, the `dref` entry holding its samples.
+pub fn data_reference_index(buf: &[u8], entry: &Mp4Box) -> Option<u16> {
+    let mut r = Reader::new(entry.body(buf));
+    r.skip(6).ok()?;
+    r.u16().ok()
+}
+
+/// Child boxes of a visual sample entry, such as `avcC` or `colr`.
+/// They follow the 78 bytes of fixed `VisualSampleEntry` fields.
+pub fn visual_entry_children(buf: &[u8], entry: &Mp4Box) -> Vec<Mp4Box> {
+    parse_boxes(buf, entry.body_offset() + 78, entry.end())
+}
+
+/// Width and height fields of a visual sample entry.
+pub fn visual_entry_size(buf: &[u8], entry: &Mp4Box) -> Option<(u16, u16)> {
+    let mut r = Reader::new(entry.body(buf));
+    r.skip(24).ok()?;
+    Some((r.u16().ok()?, r.u16().ok()?))
+}
+
+/// Child boxes of an audio sample entry, such as `dac3`. They follow the 28
+/// bytes of `AudioSampleEntry` fields, plus 16 or 36 more for the QuickTime
+/// version 1 and 2 layouts.
+pub fn audio_entry_children(buf: &[u8], entry: &Mp4Box) -> Vec<Mp4Box> {
+    let mut r = Reader::new(entry.body(buf));
+    let version = r.skip(8).and_then(|_| r.u16()).unwrap_or(0);
+    let fields = match version {
+        1 => 44,
+        2 => 64,
+        _ => 28,
+    };
+    parse_boxes(buf, entry.body_offset() + fields, entry.end())
+}
+
+/// Channel count, sample size and integer sample rate of an audio sample entry.
+pub fn audio_entry_fields(buf: &[u8], entry: &Mp4Box) -> Option<(u16, u16, u32)> {
+    let mut r = Reader::new(entry.body(buf));
+    r.skip(16).ok()?;
+    let channelcount = r.u16().ok()?;
+    let samplesize = r.u16().ok()?;
+    r.skip(4).ok()?;
+    Some((channelcount, samplesize, r.u32().ok()? >> 16))
+}
+
+/// Major and compatible brands of an `ftyp` or `styp` box.
+pub fn brands(buf: &[u8], b: &Mp4Box) -> Vec<[u8; 4]> {
+    let body = b.body(buf);
+    let mut brands = Vec::new();
+    if body.len() >= 4 {
+        brands.push([body[0], body[1], body[2], body[3]]);
+    }
+    for c in body.get(8..).unwrap_or(&[]).chunks_exact(4) {
+        brands.push([c[0], c[1], c[2], c[3]]);
+    }
+    brands
+}
+
+/// Big-endian field reader over a byte slice.
+#[derive(Debug, Clone, Copy)]
+pub struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// constructor
+    pub fn new(buf: &'a [u8]) -> Reader<'a> {
+        Reader { buf, pos: 0 }
+    }
+
+    /// Bytes left to read.
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    /// Reads `n` bytes.
+    pub fn bytes(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.remaining() < n {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "truncated box"));
+        }
+        let bytes = &self.buf[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    /// Skips `n` bytes.
+    pub fn skip(&mut self, n: usize) -> Result<()> {
+        self.bytes(n).map(|_| ())
+    }
+
+    /// Reads a u8.
+    pub fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    /// Reads a big-endian u16.
+    pub fn u16(&mut self) -> Result<u16> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    /// Reads a big-endian 24 bit unsigned integer.
+    pub fn u24(&mut self) -> Result<u32> {
+        let b = self.bytes(3)?;
+        Ok(u32::from_be_bytes([0, b[0], b[1], b[2]]))
+    }
+
+    /// Reads a big-endian u32.
+    pub fn u32(&mut self) -> Result<u32> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// Reads a big-endian u64.
+    pub fn u64(&mut self) -> Result<u64> {
+        let b = self.bytes(8)?;
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(b);
+        Ok(u64::from_be_bytes(bytes))
+    }
+
+    /// Reads a big-endian i32.
+    pub fn i32(&mut self) -> Result<i32> {
+        Ok(self.u32()? as i32)
+    }
+
+    /// Reads a big-endian i64.
+    pub fn i64(&mut self) -> Result<i64> {
+        Ok(self.u64()? as i64)
+    }
+
+    /// Reads a full box version and flags.
+    pub fn full_box(&mut self) -> Result<(u8, u32)> {
+        let version = self.u8()?;
+        let flags = self.u24()?;
+        Ok((version, flags))
+    }
+}
+
+/// Movie header fields mp4parse keeps private.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MovieHeader {
+    /// movie timescale
+    pub timescale: u32,
+    /// movie duration in movie timescale units
+    pub duration: u64,
+}
+
+/// Reads `moov/mvhd`.
+pub fn read_mvhd(buf: &[u8]) -> Result<MovieHeader> {
+    let moov = find_top(buf, b"moov")
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "moov box not found"))?;
+    let mvhd = child(buf, &moov, b"mvhd")
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "mvhd box not found"))?;
+    let mut r = Reader::new(mvhd.body(buf));
+    let (version, _) = r.full_box()?;
+    let (timescale, duration) = if version == 1 {
+        r.skip(16)?;
+        (r.u32()?, r.u64()?)
+    } else {
+        r.skip(8)?;
+        (r.u32()?, u64::from(r.u32()?))
+    };
+    Ok(MovieHeader {
+        timescale,
+        duration,
+    })
+}
+
+/// A single edit list entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edit {
+    /// segment duration in movie timescale units
+    pub segment_duration: u64,
+    /// media start time in track timescale units, -1 for an empty edit
+    pub media_time: i64,
+    /// media rate as 16.16 fixed point
+    pub media_rate: i32,
+}
+
+/// Reads every entry of `trak/edts/elst`; mp4parse only keeps the first two.
+pub fn read_elst(buf: &[u8], trak: &Mp4Box) -> Result<Vec<Edit>> {
+    let elst = match find_path(buf, trak, &[b"edts", b"elst"]) {
+        Some(elst) => elst,
+        None => return Ok(Vec::new()),
+    };
+    let mut r = Reader::new(elst.body(buf));
+    let (version, _) = r.full_box()?;
+    let count = r.u32()?;
+    let mut edits = Vec::new();
+    for _ in 0..count {
+        let (segment_duration, media_time) = if version == 1 {
+            (r.u64()?, r.i64()?)
+        } else {
+            (u64::from(r.u32()?), i64::from(r.i32()?))
+        };
+        edits.push(Edit {
+            segment_duration,
+            media_time,
+            media_rate: r.i32()?,
+        });
+    }
+    Ok(edits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_parse_boxes_stops_at_truncated_box() {
+        let mut buf = vec![0, 0, 0, 8, b'f', b'r', b'e', b'e'];
+        buf.extend_from_slice(&[0, 0, 0, 16, b'm', b'd', b'a', b't', 0]);
+        let boxes = parse_boxes(&buf, 0, buf.len());
+        assert_eq!(boxes.len(), 1);
+        assert!(boxes[0].is(b"free"));
+        assert_eq!(boxes[0].end(), 8);
+    }
+
+    #[test]
+    fn unit_parse_boxes_stops_at_oversized_largesize() {
+        let mut buf = vec![0, 0, 0, 8, b'f', b'r', b'e', b'e'];
+        buf.extend_from_slice(&[0, 0, 0, 1, b'm', b'd', b'a', b't']);
+        buf.extend_from_slice(&u64::MAX.to_be_bytes());
+        let boxes = parse_boxes(&buf, 0, buf.len());
+        assert_eq!(boxes.len(), 1);
+        assert!(boxes[0].is(b"free"));
+    }
+}
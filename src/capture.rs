@@ -0,0 +1,348 @@
+//! Capture device and location metadata of phone and camera recordings:
+//! QuickTime `©xyz`, `©mak`, `©mod` and `©swr` user data, the 3GPP `loci`
+//! box, Apple `mdta` metadata keys and GoPro `udta` boxes.
+
+use crate::boxes::{self, Mp4Box, Reader};
+use crate::tags::{self, Tag, TagValue};
+use std::io::Result;
+
+/// A location with decimal degrees and metres.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Location {
+    /// latitude in degrees, north positive
+    pub latitude: f64,
+    /// longitude in degrees, east positive
+    pub longitude: f64,
+    /// altitude in metres
+    pub altitude: Option<f64>,
+}
+
+/// Device and location metadata of a recording.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CaptureInfo {
+    /// device manufacturer
+    pub make: Option<String>,
+    /// device model
+    pub model: Option<String>,
+    /// recording software
+    pub software: Option<String>,
+    /// camera firmware version
+    pub firmware: Option<String>,
+    /// camera serial number
+    pub serial_number: Option<String>,
+    /// capture date as written by the device
+    pub creation_date: Option<String>,
+    /// ISO 6709 location string
+    pub iso6709: Option<String>,
+    /// decoded location
+    pub location: Option<Location>,
+    /// horizontal location accuracy in metres
+    pub horizontal_accuracy: Option<f64>,
+    /// `loci` place name
+    pub location_name: Option<String>,
+    /// box the location was read from
+    pub location_source: Option<&'static str>,
+}
+
+impl CaptureInfo {
+    fn is_empty(&self) -> bool {
+        *self == CaptureInfo::default()
+    }
+
+    fn set_location(&mut self, source: &'static str, location: Location) {
+        if self.location.is_none() {
+            self.location = Some(location);
+            self.location_source = Some(source);
+        }
+    }
+}
+
+/// Parses one ISO 6709 component with `degree_digits` integer digits of
+/// degrees, optionally followed by two of minutes and two of seconds.
+fn iso6709_component(value: &str, degree_digits: usize) -> Option<f64> {
+    let (sign, digits) = match value.split_at(1) {
+        ("+", digits) => (1.0, digits),
+        ("-", digits) => (-1.0, digits),
+        _ => return None,
+    };
+    let integer_digits = digits.find('.').unwrap_or(digits.len());
+    let number: f64 = digits.parse().ok()?;
+    let degrees = match integer_digits.checked_sub(degree_digits)? {
+        0 => number,
+        2 => (number / 100.0).trunc() + number % 100.0 / 60.0,
+        4 => {
+            (number / 10000.0).trunc()
+                + (number % 10000.0 / 100.0).trunc() / 60.0
+                + number % 100.0 / 3600.0
+        }
+        _ => return None,
+    };
+    Some(sign * degrees)
+}
+
+/// Parses an ISO 6709 point such as `+37.3318-122.0312+010.000/`.
+pub fn parse_iso6709(value: &str) -> Option<Location> {
+    let value = value.trim().split('/').next()?;
+    let starts: Vec<usize> = value
+        .char_indices()
+        .filter(|(_, c)| *c == '+' || *c == '-')
+        .map(|(i, _)| i)
+        .collect();
+    let component = |n: usize| -> Option<&str> {
+        let start = *starts.get(n)?;
+        let end = starts.get(n + 1).copied().unwrap_or(value.len());
+        Some(&value[start..end])
+    };
+    // altitude may be followed by a CRS identifier
+    let altitude = component(2).and_then(|a| a.split("CRS").next()?.parse::<f64>().ok());
+    Some(Location {
+        latitude: iso6709_component(component(0)?, 2)?,
+        longitude: iso6709_component(component(1)?, 3)?,
+        altitude,
+    })
+}
+
+fn text_value(tag: &Tag) -> Option<String> {
+    tag.values.iter().find_map(|v| match v {
+        TagValue::Text(text) if !text.is_empty() => Some(text.clone()),
+        _ => None,
+    })
+}
+
+fn read_string(r: &mut Reader) -> Result<String> {
+    let mut bytes = Vec::new();
+    loop {
+        match r.u8()? {
+            0 => break,
+            b => bytes.push(b),
+        }
+    }
+    Ok(String::from_utf8_lossy(&bytes).to_string())
+}
+
+/// Reads a 3GPP `loci` location box.
+fn read_loci(body: &[u8], info: &mut CaptureInfo) -> Result<()> {
+    let mut r = Reader::new(body);
+    r.full_box()?;
+    r.skip(2)?;
+    let name = read_string(&mut r)?;
+    r.skip(1)?;
+    let fixed = |value: i32| f64::from(value) / 65536.0;
+    let longitude = fixed(r.i32()?);
+    let latitude = fixed(r.i32()?);
+    let altitude = fixed(r.i32()?);
+    if !name.is_empty() {
+        info.location_name = Some(name);
+    }
+    info.set_location(
+        "loci",
+        Location {
+            latitude,
+            longitude,
+            altitude: Some(altitude),
+        },
+    );
+    Ok(())
+}
+
+/// Reads the Apple `mdta` keys and values of a `meta` box.
+fn read_mdta(buf: &[u8], meta: &Mp4Box, info: &mut CaptureInfo) -> Result<()> {
+    let (keys, ilst) = match (
+        boxes::child(buf, meta, b"keys"),
+        boxes::child(buf, meta, b"ilst"),
+    ) {
+        (Some(keys), Some(ilst)) => (keys, ilst),
+        _ => return Ok(()),
+    };
+    let mut r = Reader::new(keys.body(buf));
+    r.full_box()?;
+    let mut names = Vec::new();
+    for _ in 0..r.u32()? {
+        let size = r.u32()? as usize;
+        r.skip(4)?;
+        names.push(String::from_utf8_lossy(r.bytes(size.saturating_sub(8))?).to_string());
+    }
+    for item in tags::read_ilst(buf, &ilst)? {
+        let index = u32::from_be_bytes(item.atom) as usize;
+        let name = match index.checked_sub(1).and_then(|i| names.get(i)) {
+            Some(name) => name.as_str(),
+            None => continue,
+        };
+        match name {
+            "com.apple.quicktime.location.ISO6709" => {
+                if let Some(value) = text_value(&item) {
+                    if let Some(location) = parse_iso6709(&value) {
+                        info.set_location("mdta", location);
+                    }
+                    info.iso6709.get_or_insert(value);
+                }
+            }
+            "com.apple.quicktime.location.accuracy.horizontal" => {
+                info.horizontal_accuracy = item.values.iter().find_map(|v| match v {
+                    TagValue::Float(value) => Some(*value),
+                    TagValue::Integer(value) => Some(*value as f64),
+                    TagValue::Text(text) => text.parse().ok(),
+                    _ => None,
+                });
+            }
+            "com.apple.quicktime.make" => info.make = text_value(&item),
+            "com.apple.quicktime.model" => info.model = text_value(&item),
+            "com.apple.quicktime.software" => info.software = text_value(&item),
+            "com.apple.quicktime.creationdate" => info.creation_date = text_value(&item),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Collects the GoPro `MINF`, `CASN` and `FMWR` strings of a GPMF payload.
+fn read_gpmf(body: &[u8], info: &mut CaptureInfo) -> Result<()> {
+    let mut r = Reader::new(body);
+    while r.remaining() >= 8 {
+        let key = r.bytes(4)?;
+        let value_type = r.u8()?;
+        let size = usize::from(r.u8()?);
+        let repeat = usize::from(r.u16()?);
+        let length = size * repeat;
+        let data = r.bytes(length.min(r.remaining()))?;
+        r.skip(
+            ((length + 3) & !3)
+                .saturating_sub(length)
+                .min(r.remaining()),
+        )?;
+        if value_type == 0 {
+            read_gpmf(data, info)?;
+            continue;
+        }
+        if value_type != b'c' {
+            continue;
+        }
+        let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+        let value = String::from_utf8_lossy(&data[..end]).trim().to_string();
+        match key {
+            b"MINF" => {
+                info.model.get_or_insert(value);
+            }
+            b"CASN" => {
+                info.serial_number.get_or_insert(value);
+            }
+            b"FMWR" => {
+                info.firmware.get_or_insert(value);
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Reads capture metadata from `moov`, taking user data text atoms from `tags`.
+/// Apple `mdta` values take precedence over QuickTime user data, which takes
+/// precedence over `loci` and GoPro boxes.
+pub fn read_capture(buf: &[u8], tags: &[Tag]) -> Result<CaptureInfo> {
+    let mut info = CaptureInfo::default();
+    let moov = match boxes::find_top(buf, b"moov") {
+        Some(moov) => moov,
+        None => return Ok(info),
+    };
+    if let Some(meta) = boxes::child(buf, &moov, b"meta") {
+        read_mdta(buf, &meta, &mut info)?;
+    }
+    let text = |atom: &[u8; 4]| tags.iter().find(|t| t.is(atom)).and_then(text_value);
+    if let Some(xyz) = text(b"\xa9xyz") {
+        if let Some(location) = parse_iso6709(&xyz) {
+            info.set_location("\u{a9}xyz", location);
+        }
+        info.iso6709.get_or_insert(xyz);
+    }
+    info.make = info.make.take().or_else(|| text(b"\xa9mak"));
+    info.model = info.model.take().or_else(|| text(b"\xa9mod"));
+    info.software = info.software.take().or_else(|| text(b"\xa9swr"));
+    if let Some(udta) = boxes::child(buf, &moov, b"udta") {
+        for b in boxes::children(buf, &udta) {
+            match &b.name {
+                b"loci" => read_loci(b.body(buf), &mut info)?,
+                b"FIRM" => {
+                    let firmware = String::from_utf8_lossy(b.body(buf));
+                    let firmware = firmware.trim_end_matches('\0').trim().to_string();
+                    info.firmware.get_or_insert(firmware);
+                }
+                b"GPMF" => {
+                    read_gpmf(b.body(buf), &mut info)?;
+                    // GPMF is GoPro's own format and carries no make
+                    info.make.get_or_insert_with(|| "GoPro".to_string());
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(info)
+}
+
+/// Prints the `[media.capture]` section when the file carries any device or
+/// location metadata.
+pub fn print_report(buf: &[u8], tags: &[Tag]) {
+    let info = match read_capture(buf, tags) {
+        Ok(info) if info.is_empty() => return,
+        Ok(info) => info,
+        Err(e) => {
+            println!("[media.capture]");
+            println!("error = \"{}\"", e);
+            return;
+        }
+    };
+    println!("[media.capture]");
+    let strings = [
+        ("make", &info.make),
+        ("model", &info.model),
+        ("software", &info.software),
+        ("firmware", &info.firmware),
+        ("serial_number", &info.serial_number),
+        ("creation_date", &info.creation_date),
+        ("location_iso6709", &info.iso6709),
+        ("location_name", &info.location_name),
+    ];
+    for (key, value) in strings {
+        if let Some(value) = value {
+            println!("{} = {:?}", key, value);
+        }
+    }
+    if let Some(location) = info.location {
+        println!("latitude = {:.6}", location.latitude);
+        println!("longitude = {:.6}", location.longitude);
+        if let Some(altitude) = location.altitude {
+            println!("altitude = {:.3}", altitude);
+        }
+    }
+    if let Some(accuracy) = info.horizontal_accuracy {
+        println!("horizontal_accuracy = {:.3}", accuracy);
+    }
+    if let Some(source) = info.location_source {
+        println!("location_source = {:?}", source);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_parse_iso6709() {
+        assert_eq!(
+            parse_iso6709("+37.3318-122.0312+010.000/"),
+            Some(Location {
+                latitude: 37.3318,
+                longitude: -122.0312,
+                altitude: Some(10.0),
+            })
+        );
+        let location = parse_iso6709("+4852.25+00220.80/").unwrap();
+        assert!((location.latitude - 48.870833).abs() < 1e-6);
+        assert!((location.longitude - 2.346667).abs() < 1e-6);
+        assert_eq!(location.altitude, None);
+        let location = parse_iso6709("-335200-15112.5+5CRSWGS_84/").unwrap();
+        assert!((location.latitude + 33.866667).abs() < 1e-6);
+        assert!((location.longitude + 151.208333).abs() < 1e-6);
+        assert_eq!(location.altitude, Some(5.0));
+        assert_eq!(parse_iso6709("nowhere"), None);
+    }
+}
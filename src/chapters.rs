@@ -0,0 +1,352 @@
+//! Chapters from the Nero `chpl` box and QuickTime chapter text tracks
+//! referenced by `tref/chap`, with FFMETADATA and WebVTT export.
+
+use crate::boxes::{self, Mp4Box, Reader};
+use crate::sample_table;
+use mp4parse::MediaContext;
+use std::error::Error;
+use std::fmt::Write;
+use std::io::Result;
+
+/// `chpl` start times are in 100 nanosecond units.
+const CHPL_TIMESCALE: u64 = 10_000_000;
+
+/// Where a chapter list was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChapterSource {
+    /// QuickTime text track referenced by `tref/chap`, with its track ID
+    Track(u32),
+    /// Nero `moov/udta/chpl`
+    Nero,
+}
+
+/// A chapter, with times in the timescale of its `ChapterList`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chapter {
+    /// start time
+    pub start: u64,
+    /// end time
+    pub end: u64,
+    /// chapter title
+    pub title: String,
+}
+
+/// Chapters read from one source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChapterList {
+    /// source of the chapters
+    pub source: ChapterSource,
+    /// units per second of the chapter times
+    pub timescale: u64,
+    /// chapters in start time order
+    pub chapters: Vec<Chapter>,
+}
+
+impl ChapterList {
+    fn seconds(&self, time: u64) -> f64 {
+        time as f64 / self.timescale as f64
+    }
+}
+
+/// Movie duration converted to `timescale`, used to close the last `chpl` chapter.
+fn movie_duration(buf: &[u8], timescale: u64) -> u64 {
+    match boxes::read_mvhd(buf) {
+        Ok(mvhd) if mvhd.timescale > 0 => {
+            (u128::from(mvhd.duration) * u128::from(timescale) / u128::from(mvhd.timescale)) as u64
+        }
+        _ => 0,
+    }
+}
+
+/// Reads the Nero `chpl` box of `moov/udta`.
+pub fn read_chpl(buf: &[u8]) -> Result<Option<ChapterList>> {
+    let chpl = match boxes::find_top(buf, b"moov")
+        .and_then(|moov| boxes::find_path(buf, &moov, &[b"udta", b"chpl"]))
+    {
+        Some(chpl) => chpl,
+        None => return Ok(None),
+    };
+    let mut r = Reader::new(chpl.body(buf));
+    let (version, _) = r.full_box()?;
+    if version > 0 {
+        r.skip(4)?;
+    }
+    let count = r.u8()?;
+    let mut starts = Vec::new();
+    for _ in 0..count {
+        let start = r.u64()?;
+        let length = usize::from(r.u8()?);
+        let title = String::from_utf8_lossy(r.bytes(length)?).to_string();
+        starts.push((start, title));
+    }
+    let duration = movie_duration(buf, CHPL_TIMESCALE);
+    let chapters = (0..starts.len())
+        .map(|i| Chapter {
+            start: starts[i].0,
+            end: starts.get(i + 1).map_or(duration, |next| next.0),
+            title: starts[i].1.clone(),
+        })
+        .collect();
+    Ok(Some(ChapterList {
+        source: ChapterSource::Nero,
+        timescale: CHPL_TIMESCALE,
+        chapters,
+    }))
+}
+
+/// Decodes a text sample: a 16-bit length followed by UTF-8, or UTF-16 with a byte order mark.
+fn read_text_sample(sample: &[u8]) -> Result<String> {
+    let mut r = Reader::new(sample);
+    let length = usize::from(r.u16()?);
+    let text = r.bytes(length)?;
+    Ok(match text {
+        [0xfe, 0xff, rest @ ..] => {
+            let units: Vec<u16> = rest
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        [0xff, 0xfe, rest @ ..] => {
+            let units: Vec<u16> = rest
+                .chunks_exact(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::from_utf8_lossy(text).to_string(),
+    })
+}
+
+/// Track ID of the first chapter track referenced by a `tref/chap` box.
+fn chapter_track_id(buf: &[u8], traks: &[Mp4Box]) -> Option<u32> {
+    traks.iter().find_map(|trak| {
+        let chap = boxes::find_path(buf, trak, &[b"tref", b"chap"])?;
+        Reader::new(chap.body(buf)).u32().ok()
+    })
+}
+
+/// Reads the QuickTime chapter track referenced by `tref/chap`, one chapter per text sample.
+pub fn read_chapter_track(buf: &[u8], context: &MediaContext) -> Result<Option<ChapterList>> {
+    let track_id = match chapter_track_id(buf, &boxes::traks(buf)) {
+        Some(track_id) => track_id,
+        None => return Ok(None),
+    };
+    let track = match context.tracks.iter().find(|t| t.track_id == Some(track_id)) {
+        Some(track) => track,
+        None => return Ok(None),
+    };
+    let timescale = track.timescale.map_or(0, |t| t.0);
+    if timescale == 0 {
+        return Ok(None);
+    }
+    let times = sample_table::decode_times(track);
+    let sizes = sample_table::sample_sizes(track);
+    let durations = track.stts.as_ref().map_or_else(Vec::new, |stts| {
+        stts.samples
+            .iter()
+            .flat_map(|s| std::iter::repeat_n(u64::from(s.sample_delta), s.sample_count as usize))
+            .collect()
+    });
+    let mut chapters = Vec::new();
+    for chunk in sample_table::chunks(track) {
+        let mut offset = chunk.offset as usize;
+        for sample in chunk.first_sample..chunk.first_sample + chunk.samples {
+            let size = sizes.get(sample).copied().unwrap_or(0) as usize;
+            let bytes = buf.get(offset..offset + size).unwrap_or_default();
+            let start = times.get(sample).copied().unwrap_or(0);
+            chapters.push(Chapter {
+                start,
+                end: start + durations.get(sample).copied().unwrap_or(0),
+                title: read_text_sample(bytes)?,
+            });
+            offset += size;
+        }
+    }
+    Ok(Some(ChapterList {
+        source: ChapterSource::Track(track_id),
+        timescale,
+        chapters,
+    }))
+}
+
+/// Reads every chapter source, the QuickTime chapter track first.
+pub fn read_chapters(buf: &[u8], context: &MediaContext) -> Result<Vec<ChapterList>> {
+    Ok([read_chapter_track(buf, context)?, read_chpl(buf)?]
+        .into_iter()
+        .flatten()
+        .collect())
+}
+
+/// Describes the first difference between two chapter lists, if any.
+fn compare(a: &ChapterList, b: &ChapterList) -> Option<String> {
+    if a.chapters.len() != b.chapters.len() {
+        return Some(format!(
+            "chapter track has {} chapters but chpl has {}",
+            a.chapters.len(),
+            b.chapters.len()
+        ));
+    }
+    for (i, (x, y)) in a.chapters.iter().zip(b.chapters.iter()).enumerate() {
+        if (a.seconds(x.start) - b.seconds(y.start)).abs() > 0.001 {
+            return Some(format!(
+                "chapter {} starts at {:.3} s in the chapter track but {:.3} s in chpl",
+                i + 1,
+                a.seconds(x.start),
+                b.seconds(y.start)
+            ));
+        }
+        if x.title != y.title {
+            return Some(format!(
+                "chapter {} is titled {:?} in the chapter track but {:?} in chpl",
+                i + 1,
+                x.title,
+                y.title
+            ));
+        }
+    }
+    None
+}
+
+/// Prints `[media.chapters]` for each chapter source followed by a
+/// `[media.chapters.chapter]` section per chapter. A warning is printed when
+/// the chapter track and `chpl` disagree.
+pub fn print_report(lists: &[ChapterList]) {
+    for list in lists {
+        println!("[media.chapters]");
+        match list.source {
+            ChapterSource::Track(track_id) => {
+                println!("source = \"chap\"");
+                println!("track_id = {:?}", track_id);
+            }
+            ChapterSource::Nero => println!("source = \"chpl\""),
+        }
+        println!("count = {:?}", list.chapters.len());
+        if let (ChapterSource::Track(_), Some(chpl)) = (list.source, lists.get(1)) {
+            if let Some(warning) = compare(list, chpl) {
+                println!("warning = {:?}", warning);
+            }
+        }
+        for (index, chapter) in list.chapters.iter().enumerate() {
+            println!("[media.chapters.chapter]");
+            println!("index = {:?}", index + 1);
+            println!("start = {:.3}", list.seconds(chapter.start));
+            println!("end = {:.3}", list.seconds(chapter.end));
+            println!("title = {:?}", chapter.title);
+        }
+    }
+}
+
+/// Formats seconds as a WebVTT `hh:mm:ss.ttt` timestamp.
+fn vtt_timestamp(seconds: f64) -> String {
+    let millis = (seconds * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// WebVTT chapters file, one cue per chapter.
+pub fn webvtt(list: &ChapterList) -> String {
+    let mut out = String::from("WEBVTT\n");
+    for (index, chapter) in list.chapters.iter().enumerate() {
+        let _ = write!(
+            out,
+            "\n{}\n{} --> {}\n{}\n",
+            index + 1,
+            vtt_timestamp(list.seconds(chapter.start)),
+            vtt_timestamp(list.seconds(chapter.end)),
+            chapter.title
+        );
+    }
+    out
+}
+
+/// Escapes the FFMETADATA special characters `=`, `;`, `#`, `\` and newline.
+fn ffmetadata_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// FFMETADATA file with one `[CHAPTER]` per chapter, in the chapter timescale.
+pub fn ffmetadata(list: &ChapterList) -> String {
+    let mut out = String::from(";FFMETADATA1\n");
+    for chapter in &list.chapters {
+        let _ = write!(
+            out,
+            "\n[CHAPTER]\nTIMEBASE=1/{}\nSTART={}\nEND={}\ntitle={}\n",
+            list.timescale,
+            chapter.start,
+            chapter.end,
+            ffmetadata_escape(&chapter.title)
+        );
+    }
+    out
+}
+
+/// Writes the first chapter list to `path`, as WebVTT for a `.vtt` extension
+/// and FFMETADATA otherwise.
+pub fn export(lists: &[ChapterList], path: &str) -> std::result::Result<(), Box<dyn Error>> {
+    let list = match lists.first() {
+        Some(list) if !list.chapters.is_empty() => list,
+        _ => return Err("no chapters to export".into()),
+    };
+    let contents = if path.to_lowercase().ends_with(".vtt") {
+        webvtt(list)
+    } else {
+        ffmetadata(list)
+    };
+    std::fs::write(path, contents)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapters() -> ChapterList {
+        ChapterList {
+            source: ChapterSource::Track(2),
+            timescale: 1000,
+            chapters: vec![
+                Chapter {
+                    start: 0,
+                    end: 60_000,
+                    title: "Intro; part 1".to_string(),
+                },
+                Chapter {
+                    start: 60_000,
+                    end: 3_690_500,
+                    title: "Chapter 2".to_string(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn unit_chapters_webvtt() {
+        assert_eq!(
+            webvtt(&chapters()),
+            "WEBVTT\n\n1\n00:00:00.000 --> 00:01:00.000\nIntro; part 1\n\n\
+             2\n00:01:00.000 --> 01:01:30.500\nChapter 2\n"
+        );
+    }
+
+    #[test]
+    fn unit_chapters_ffmetadata() {
+        assert_eq!(
+            ffmetadata(&chapters()),
+            ";FFMETADATA1\n\n[CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=60000\ntitle=Intro\\; part 1\n\n\
+             [CHAPTER]\nTIMEBASE=1/1000\nSTART=60000\nEND=3690500\ntitle=Chapter 2\n"
+        );
+    }
+}
@@ -0,0 +1,35 @@
+//! Shared reporting for conformance and consistency checks.
+
+/// A failed constraint and the box it was found in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// box path, e.g. `moof[2]/traf[1]/tfdt`
+    pub path: String,
+    /// description of the failed constraint
+    pub message: String,
+}
+
+impl Violation {
+    /// constructor
+    pub fn new(path: String, message: &str) -> Violation {
+        Violation {
+            path,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Prints a check section with one `violation` subsection per failure.
+/// # Arguments
+/// * `section` - Section name, e.g. `media.check.cmaf`
+/// * `violations` - Failures found by the check
+pub fn print_report(section: &str, violations: &[Violation]) {
+    println!("[{}]", section);
+    println!("conformant = {:?}", violations.is_empty());
+    println!("violations = {:?}", violations.len());
+    for v in violations {
+        println!("[{}.violation]", section);
+        println!("path = {:?}", v.path);
+        println!("message = {:?}", v.message);
+    }
+}
@@ -0,0 +1,179 @@
+//! CMAF (ISO/IEC 23000-19) conformance checks for fragmented files.
+//!
+//! Only the structural constraints visible in the box layout are checked;
+//! codec specific CMAF media profiles are not validated.
+
+use crate::boxes::{self, Reader};
+use crate::check::Violation;
+use crate::fragment::{MovieFragment, TrackExtends};
+use mp4parse::MediaContext;
+
+/// Checks the CMAF header (`ftyp` + `moov`) and every fragment and segment.
+/// # Arguments
+/// * `buf` - Whole file contents
+/// * `context` - mp4parse context read from `buf`
+/// * `trexs` - Track defaults from `mvex`
+/// * `fragments` - Movie fragments of `buf`
+pub fn check(
+    buf: &[u8],
+    context: &MediaContext,
+    trexs: &[TrackExtends],
+    fragments: &[MovieFragment],
+) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let top = boxes::top_level(buf);
+
+    match top.iter().find(|b| b.is(b"ftyp")) {
+        Some(ftyp) => {
+            let brands = boxes::brands(buf, ftyp);
+            if !brands.iter().any(|b| b == b"cmfc" || b == b"cmf2") {
+                violations.push(Violation::new(
+                    "ftyp".to_string(),
+                    "neither cmfc nor cmf2 is a compatible brand",
+                ));
+            }
+        }
+        None => violations.push(Violation::new(
+            "ftyp".to_string(),
+            "CMAF header has no ftyp box",
+        )),
+    }
+
+    let styps = top.iter().enumerate().filter(|(_, b)| b.is(b"styp"));
+    for (n, (i, styp)) in styps.enumerate() {
+        let path = format!("styp[{}]", n + 1);
+        if !boxes::brands(buf, styp).iter().any(|b| b == b"cmfs") {
+            violations.push(Violation::new(
+                path.clone(),
+                "segment type box does not list the cmfs brand",
+            ));
+        }
+        if !top[i + 1..].iter().any(|b| b.is(b"moof")) {
+            violations.push(Violation::new(
+                path,
+                "segment type box is not followed by a movie fragment",
+            ));
+        }
+    }
+
+    if let Some(moov) = top.iter().find(|b| b.is(b"moov")) {
+        let traks = boxes::traks(buf);
+        if traks.len() != 1 {
+            violations.push(Violation::new(
+                "moov".to_string(),
+                "CMAF header must contain exactly one track",
+            ));
+        }
+        if boxes::child(buf, moov, b"mvex").is_none() {
+            violations.push(Violation::new(
+                "moov/mvex".to_string(),
+                "CMAF header has no movie extends box",
+            ));
+        }
+        for (i, track) in context.tracks.iter().enumerate() {
+            let track_id = track.track_id.unwrap_or(0);
+            if !trexs.iter().any(|t| t.track_id == track_id) {
+                violations.push(Violation::new(
+                    "moov/mvex/trex".to_string(),
+                    &format!("no trex for track {}", track_id),
+                ));
+            }
+            let stbl = format!("moov/trak[{}]/mdia/minf/stbl", i + 1);
+            // mp4parse drops sample_count when stsz has a constant sample_size
+            let sample_count = traks
+                .get(i)
+                .and_then(|trak| boxes::find_path(buf, trak, &[b"mdia", b"minf", b"stbl", b"stsz"]))
+                .and_then(|stsz| {
+                    let mut r = Reader::new(stsz.body(buf));
+                    r.full_box().ok()?;
+                    r.u32().ok()?;
+                    r.u32().ok()
+                })
+                .unwrap_or(0);
+            if sample_count != 0 {
+                violations.push(Violation::new(
+                    format!("{}/stsz", stbl),
+                    "CMAF header sample tables must be empty",
+                ));
+            }
+            if track.stco.as_ref().is_some_and(|s| !s.offsets.is_empty()) {
+                violations.push(Violation::new(
+                    format!("{}/stco", stbl),
+                    "CMAF header sample tables must be empty",
+                ));
+            }
+            if track.stts.as_ref().is_some_and(|s| !s.samples.is_empty()) {
+                violations.push(Violation::new(
+                    format!("{}/stts", stbl),
+                    "CMAF header sample tables must be empty",
+                ));
+            }
+        }
+    } else {
+        violations.push(Violation::new(
+            "moov".to_string(),
+            "CMAF header has no movie box",
+        ));
+    }
+
+    if fragments.is_empty() {
+        violations.push(Violation::new(
+            "moof".to_string(),
+            "no movie fragments found",
+        ));
+    }
+    let mut default_durations: Vec<(u32, u32)> = Vec::new();
+    for (i, fragment) in fragments.iter().enumerate() {
+        let moof = format!("moof[{}]", i + 1);
+        if fragment.trafs.len() != 1 {
+            violations.push(Violation::new(
+                moof.clone(),
+                &format!(
+                    "CMAF fragments carry one track, found {} track fragments",
+                    fragment.trafs.len()
+                ),
+            ));
+        }
+        if fragment.sequence_number.is_none() {
+            violations.push(Violation::new(
+                format!("{}/mfhd", moof),
+                "movie fragment header is missing",
+            ));
+        }
+        for (j, traf) in fragment.trafs.iter().enumerate() {
+            let path = format!("{}/traf[{}]", moof, j + 1);
+            if traf.base_media_decode_time.is_none() {
+                violations.push(Violation::new(
+                    format!("{}/tfdt", path),
+                    "track fragment decode time box is missing",
+                ));
+            }
+            if traf.samples.first().is_some_and(|s| !s.is_sync()) {
+                violations.push(Violation::new(
+                    format!("{}/trun", path),
+                    "fragment does not start with a sync sample",
+                ));
+            }
+            let default_duration = traf.default_sample_duration.unwrap_or_else(|| {
+                trexs
+                    .iter()
+                    .find(|t| t.track_id == traf.track_id)
+                    .map_or(0, |t| t.default_sample_duration)
+            });
+            match default_durations.iter().find(|d| d.0 == traf.track_id) {
+                Some(&(_, first)) if first != default_duration => {
+                    violations.push(Violation::new(
+                        format!("{}/tfhd", path),
+                        &format!(
+                            "default sample duration {} differs from {} used by earlier fragments",
+                            default_duration, first
+                        ),
+                    ));
+                }
+                Some(_) => {}
+                None => default_durations.push((traf.track_id, default_duration)),
+            }
+        }
+    }
+    violations
+}
@@ -0,0 +1,173 @@
+//! RFC 6381 `codecs` parameter strings, as used by HLS, DASH and MSE.
+//!
+//! AVC, AV1 and audio configurations come from mp4parse. HEVC, VP9, Dolby
+//! Vision and AC-4 are read from the raw `hvcC`/`vpcC`/`dvcC`/`dac4` boxes
+//! because mp4parse does not expose the profile and level fields needed.
+
+use crate::aac;
+use crate::boxes::{self, Mp4Box};
+use crate::dolby;
+use crate::dovi;
+use crate::vpx;
+use mp4parse::{AudioCodecSpecific, SampleEntry, VideoCodecSpecific};
+
+/// Returns the RFC 6381 codecs string of the first sample entry of a track, or
+/// `None` when the configuration does not carry enough information to build one.
+/// # Arguments
+/// * `buf` - Whole file contents
+/// * `trak` - `trak` box of the track
+/// * `entry` - mp4parse sample entry of the track, if it was recognised
+pub fn codecs(buf: &[u8], trak: &Mp4Box, entry: Option<&SampleEntry>) -> Option<String> {
+    let raw = boxes::sample_entries(buf, trak).into_iter().next()?;
+    entry_codecs(buf, &raw, entry)
+}
+
+/// Returns the RFC 6381 codecs string of one sample entry.
+/// # Arguments
+/// * `buf` - Whole file contents
+/// * `raw` - raw sample entry box
+/// * `entry` - mp4parse sample entry, if it was recognised
+pub fn entry_codecs(buf: &[u8], raw: &Mp4Box, entry: Option<&SampleEntry>) -> Option<String> {
+    let fourcc = String::from_utf8_lossy(&raw.name).to_string();
+    let config = |name: &[u8; 4]| {
+        boxes::visual_entry_children(buf, raw)
+            .into_iter()
+            .find(|b| b.is(name))
+            .map(|b| b.body(buf))
+    };
+    match &raw.name {
+        b"hvc1" | b"hev1" => return config(b"hvcC").and_then(|c| hevc(&fourcc, c)),
+        b"vp08" | b"vp09" => return config(b"vpcC").and_then(|c| vpx(&fourcc, c)),
+        b"dvh1" | b"dvhe" | b"dav1" | b"dva1" | b"dvav" => {
+            let config = dovi::find_config(buf, raw)?;
+            let config = dovi::read_config(config.name, config.body(buf)).ok()?;
+            return Some(dovi::codecs(&fourcc, &config));
+        }
+        b"ac-3" | b"ec-3" => return Some(fourcc),
+        b"ac-4" => {
+            let dac4 = boxes::audio_entry_children(buf, raw)
+                .into_iter()
+                .find(|b| b.is(b"dac4"))?;
+            return dolby::ac4_codecs(&dolby::read_ac4(dac4.body(buf)).ok()?);
+        }
+        _ => {}
+    }
+    match entry? {
+        SampleEntry::Video(v) => match &v.codec_specific {
+            VideoCodecSpecific::AVCConfig(avcc) if avcc.len() >= 4 => Some(format!(
+                "{}.{:02X}{:02X}{:02X}",
+                if raw.is(b"avc3") { "avc3" } else { "avc1" },
+                avcc[1],
+                avcc[2],
+                avcc[3]
+            )),
+            VideoCodecSpecific::AV1Config(av1c) => Some(format!(
+                "av01.{}.{:02}{}.{:02}",
+                av1c.profile,
+                av1c.level,
+                if av1c.tier == 0 { 'M' } else { 'H' },
+                av1c.bit_depth
+            )),
+            _ => None,
+        },
+        SampleEntry::Audio(a) => match &a.codec_specific {
+            AudioCodecSpecific::ES_Descriptor(esds) => {
+                mp4a(&esds.codec_esds, esds.audio_object_type)
+            }
+            AudioCodecSpecific::FLACSpecificBox(_) => Some("fLaC".to_string()),
+            AudioCodecSpecific::OpusSpecificBox(_) => Some("Opus".to_string()),
+            AudioCodecSpecific::ALACSpecificBox(_) => Some("alac".to_string()),
+            AudioCodecSpecific::MP3 => Some("mp4a.6B".to_string()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// RFC 6381 form of an `mp4a` entry: `mp4a.40.<audio object type>` for
+/// MPEG-4 audio, otherwise the objectTypeIndication in hex, e.g. `mp4a.6B` for MP3.
+fn mp4a(codec_esds: &[u8], audio_object_type: Option<u16>) -> Option<String> {
+    let oti = aac::read_decoder_config(codec_esds)
+        .ok()
+        .flatten()
+        .map(|config| config.object_type_indication);
+    match (oti, audio_object_type) {
+        (Some(0x40) | None, Some(aot)) => Some(format!("mp4a.40.{}", aot)),
+        (Some(0x40) | None, None) => None,
+        (Some(oti), _) => Some(format!("mp4a.{:02X}", oti)),
+    }
+}
+
+/// ISO/IEC 14496-15 Annex E form, e.g. `hvc1.1.6.L93.B0`.
+fn hevc(fourcc: &str, hvcc: &[u8]) -> Option<String> {
+    let c = hvcc.get(..13)?;
+    let profile_space = ["", "A", "B", "C"][usize::from(c[1] >> 6)];
+    let tier = if c[1] & 0x20 == 0 { 'L' } else { 'H' };
+    let compatibility = u32::from_be_bytes([c[2], c[3], c[4], c[5]]).reverse_bits();
+    let mut out = format!(
+        "{}.{}{}.{:X}.{}{}",
+        fourcc,
+        profile_space,
+        c[1] & 0x1f,
+        compatibility,
+        tier,
+        c[12]
+    );
+    let constraints = &c[6..12];
+    let used = constraints
+        .iter()
+        .rposition(|&b| b != 0)
+        .map_or(0, |i| i + 1);
+    for b in &constraints[..used] {
+        out.push_str(&format!(".{:X}", b));
+    }
+    Some(out)
+}
+
+/// VP codec ISO media binding short form, e.g. `vp09.00.10.08`.
+fn vpx(fourcc: &str, vpcc: &[u8]) -> Option<String> {
+    let c = vpx::read_config(vpcc).ok()?;
+    Some(format!(
+        "{}.{:02}.{:02}.{:02}",
+        fourcc, c.profile, c.level, c.bit_depth
+    ))
+}
+
+/// MIME type with a `codecs` parameter covering every track.
+/// # Arguments
+/// * `has_video` - true when any track is video
+/// * `codecs` - RFC 6381 codecs of every track
+pub fn mime(has_video: bool, codecs: &[String]) -> String {
+    format!(
+        "{}/mp4; codecs=\"{}\"",
+        if has_video { "video" } else { "audio" },
+        codecs.join(",")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_hevc_codecs_string() {
+        // Main profile, main tier, level 3.1, progressive source flag set
+        let hvcc = [1, 0x01, 0x60, 0, 0, 0, 0xb0, 0, 0, 0, 0, 0, 93];
+        assert_eq!(hevc("hvc1", &hvcc).unwrap(), "hvc1.1.6.L93.B0");
+    }
+
+    #[test]
+    fn unit_mp4a_codecs_string() {
+        // ES_Descriptor with a DecoderConfigDescriptor for the given objectTypeIndication
+        let esds = |oti: u8| {
+            [
+                0x03, 0x12, 0x00, 0x01, 0x00, 0x04, 0x0d, oti, 0x15, 0x00, 0x00, 0x00, 0x00, 0x01,
+                0xf4, 0x00, 0x00, 0x01, 0xf4, 0x00,
+            ]
+        };
+        assert_eq!(mp4a(&esds(0x40), Some(2)).unwrap(), "mp4a.40.2");
+        assert_eq!(mp4a(&esds(0x6b), None).unwrap(), "mp4a.6B");
+        assert_eq!(mp4a(&esds(0x69), None).unwrap(), "mp4a.69");
+        assert_eq!(mp4a(&esds(0x40), None), None);
+    }
+}
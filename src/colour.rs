@@ -0,0 +1,286 @@
+//! Names of the coding-independent code points of ISO/IEC 23091-2 (ITU-T H.273)
+//! and the colour, HDR and display boxes of visual sample entries (ISO/IEC
+//! 14496-12 § 12.1).
+
+use crate::boxes::{self, Mp4Box, Reader};
+use std::io::Result;
+
+/// Name of a colour_primaries value.
+pub fn primaries_name(value: u32) -> &'static str {
+    match value {
+        1 => "BT.709",
+        2 => "unspecified",
+        4 => "BT.470M",
+        5 => "BT.470BG",
+        6 => "SMPTE 170M",
+        7 => "SMPTE 240M",
+        8 => "film",
+        9 => "BT.2020",
+        10 => "SMPTE ST 428-1",
+        11 => "SMPTE RP 431-2 (DCI-P3)",
+        12 => "SMPTE EG 432-1 (Display P3)",
+        22 => "EBU Tech 3213-E",
+        _ => "reserved",
+    }
+}
+
+/// Name of a transfer_characteristics value.
+pub fn transfer_name(value: u32) -> &'static str {
+    match value {
+        1 => "BT.709",
+        2 => "unspecified",
+        4 => "gamma 2.2",
+        5 => "gamma 2.8",
+        6 => "SMPTE 170M",
+        7 => "SMPTE 240M",
+        8 => "linear",
+        9 => "log 100:1",
+        10 => "log 316:1",
+        11 => "IEC 61966-2-4",
+        12 => "BT.1361",
+        13 => "sRGB",
+        14 => "BT.2020 10-bit",
+        15 => "BT.2020 12-bit",
+        16 => "SMPTE ST 2084 (PQ)",
+        17 => "SMPTE ST 428-1",
+        18 => "ARIB STD-B67 (HLG)",
+        _ => "reserved",
+    }
+}
+
+/// Name of a matrix_coefficients value.
+pub fn matrix_name(value: u32) -> &'static str {
+    match value {
+        0 => "identity",
+        1 => "BT.709",
+        2 => "unspecified",
+        4 => "FCC",
+        5 => "BT.470BG",
+        6 => "SMPTE 170M",
+        7 => "SMPTE 240M",
+        8 => "YCgCo",
+        9 => "BT.2020 non-constant luminance",
+        10 => "BT.2020 constant luminance",
+        11 => "SMPTE ST 2085",
+        12 => "chromaticity-derived non-constant luminance",
+        13 => "chromaticity-derived constant luminance",
+        14 => "ICtCp",
+        _ => "reserved",
+    }
+}
+
+/// Coded colour parameters of an `nclx` or `nclc` `colr` box.
+#[derive(Debug, Clone, Copy)]
+pub struct ColourParameters {
+    /// colour_type, `nclx` or the QuickTime `nclc`
+    pub colour_type: [u8; 4],
+    /// colour_primaries
+    pub primaries: u16,
+    /// transfer_characteristics
+    pub transfer: u16,
+    /// matrix_coefficients
+    pub matrix: u16,
+    /// full_range_flag, `nclx` only
+    pub full_range: Option<bool>,
+}
+
+/// Colour and display boxes of a visual sample entry.
+#[derive(Debug, Clone, Default)]
+pub struct ColourInfo {
+    /// nclx or nclc colour parameters
+    pub colr: Option<ColourParameters>,
+    /// size, profile class and colour space of an embedded ICC profile
+    pub icc: Option<(usize, String, String)>,
+    /// mdcv green, blue and red primaries then white point, in 0.00002 units
+    pub mastering_primaries: Option<[(u16, u16); 4]>,
+    /// mdcv maximum and minimum luminance, in 0.0001 cd/m2 units
+    pub mastering_luminance: Option<(u32, u32)>,
+    /// clli MaxCLL and MaxFALL in cd/m2
+    pub content_light_level: Option<(u16, u16)>,
+    /// pasp hSpacing and vSpacing
+    pub pixel_aspect_ratio: Option<(u32, u32)>,
+    /// clap width, height, horizontal and vertical offset as fractions
+    pub clean_aperture: Option<[(i32, u32); 4]>,
+    /// btrt bufferSizeDB, maxBitrate and avgBitrate
+    pub bitrate: Option<(u32, u32, u32)>,
+}
+
+impl ColourInfo {
+    fn is_empty(&self) -> bool {
+        self.colr.is_none()
+            && self.icc.is_none()
+            && self.mastering_primaries.is_none()
+            && self.content_light_level.is_none()
+            && self.pixel_aspect_ratio.is_none()
+            && self.clean_aperture.is_none()
+            && self.bitrate.is_none()
+    }
+}
+
+fn fourcc(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).trim_end().to_string()
+}
+
+/// Reads the `colr`, `mdcv`, `clli`, `pasp`, `clap` and `btrt` children of a visual sample entry.
+pub fn read_colour_info(buf: &[u8], entry: &Mp4Box) -> Result<ColourInfo> {
+    let mut info = ColourInfo::default();
+    for b in boxes::visual_entry_children(buf, entry) {
+        let mut r = Reader::new(b.body(buf));
+        match &b.name {
+            b"colr" => {
+                let colour_type: [u8; 4] = r.bytes(4)?.try_into().unwrap_or_default();
+                match &colour_type {
+                    b"nclx" | b"nclc" => {
+                        let (primaries, transfer, matrix) = (r.u16()?, r.u16()?, r.u16()?);
+                        let full_range = if &colour_type == b"nclx" {
+                            Some(r.u8()? & 0x80 != 0)
+                        } else {
+                            None
+                        };
+                        info.colr = Some(ColourParameters {
+                            colour_type,
+                            primaries,
+                            transfer,
+                            matrix,
+                            full_range,
+                        });
+                    }
+                    b"rICC" | b"prof" => {
+                        let profile = r.bytes(r.remaining())?;
+                        let class = profile.get(12..16).map(fourcc).unwrap_or_default();
+                        let space = profile.get(16..20).map(fourcc).unwrap_or_default();
+                        info.icc = Some((profile.len(), class, space));
+                    }
+                    _ => {}
+                }
+            }
+            b"mdcv" => {
+                let mut primaries = [(0, 0); 4];
+                for p in primaries.iter_mut() {
+                    *p = (r.u16()?, r.u16()?);
+                }
+                info.mastering_primaries = Some(primaries);
+                info.mastering_luminance = Some((r.u32()?, r.u32()?));
+            }
+            b"clli" => info.content_light_level = Some((r.u16()?, r.u16()?)),
+            b"pasp" => info.pixel_aspect_ratio = Some((r.u32()?, r.u32()?)),
+            b"clap" => {
+                let mut aperture = [(0, 0); 4];
+                for a in aperture.iter_mut() {
+                    *a = (r.i32()?, r.u32()?);
+                }
+                info.clean_aperture = Some(aperture);
+            }
+            b"btrt" => info.bitrate = Some((r.u32()?, r.u32()?, r.u32()?)),
+            _ => {}
+        }
+    }
+    Ok(info)
+}
+
+fn fraction((numerator, denominator): (i32, u32)) -> f64 {
+    if denominator == 0 {
+        0.0
+    } else {
+        f64::from(numerator) / f64::from(denominator)
+    }
+}
+
+/// Prints the `[media.track.video.colour]` section when the sample entry has any colour boxes.
+pub fn print_report(buf: &[u8], entry: &Mp4Box) {
+    let info = match read_colour_info(buf, entry) {
+        Ok(info) if info.is_empty() => return,
+        Ok(info) => info,
+        Err(e) => {
+            println!("[media.track.video.colour]");
+            println!("error = \"{}\"", e);
+            return;
+        }
+    };
+    println!("[media.track.video.colour]");
+    if let Some(ColourParameters {
+        colour_type,
+        primaries,
+        transfer,
+        matrix,
+        full_range,
+    }) = info.colr
+    {
+        println!("colour_type = \"{}\"", fourcc(&colour_type));
+        println!("colour_primaries = {:?}", primaries);
+        println!(
+            "colour_primaries_name = \"{}\"",
+            primaries_name(u32::from(primaries))
+        );
+        println!("transfer_characteristics = {:?}", transfer);
+        println!(
+            "transfer_characteristics_name = \"{}\"",
+            transfer_name(u32::from(transfer))
+        );
+        println!("matrix_coefficients = {:?}", matrix);
+        println!(
+            "matrix_coefficients_name = \"{}\"",
+            matrix_name(u32::from(matrix))
+        );
+        if let Some(full_range) = full_range {
+            println!("full_range = {:?}", full_range);
+        }
+        let hdr = match transfer {
+            16 => Some("PQ"),
+            18 => Some("HLG"),
+            _ => None,
+        };
+        if let Some(hdr) = hdr {
+            println!("hdr_transfer = \"{}\"", hdr);
+        }
+    }
+    if let Some((size, class, space)) = &info.icc {
+        println!("icc_profile_size = {:?}", size);
+        println!("icc_profile_class = \"{}\"", class);
+        println!("icc_colour_space = \"{}\"", space);
+    }
+    if let Some(primaries) = info.mastering_primaries {
+        let xy = |(x, y): (u16, u16)| {
+            format!(
+                "[{:.5}, {:.5}]",
+                f64::from(x) * 0.00002,
+                f64::from(y) * 0.00002
+            )
+        };
+        println!("mastering_display_green = {}", xy(primaries[0]));
+        println!("mastering_display_blue = {}", xy(primaries[1]));
+        println!("mastering_display_red = {}", xy(primaries[2]));
+        println!("mastering_display_white_point = {}", xy(primaries[3]));
+    }
+    if let Some((max, min)) = info.mastering_luminance {
+        println!(
+            "mastering_display_max_luminance = {:.4}",
+            f64::from(max) * 0.0001
+        );
+        println!(
+            "mastering_display_min_luminance = {:.4}",
+            f64::from(min) * 0.0001
+        );
+    }
+    if let Some((max_cll, max_fall)) = info.content_light_level {
+        println!("max_cll = {:?}", max_cll);
+        println!("max_fall = {:?}", max_fall);
+    }
+    if let Some((h_spacing, v_spacing)) = info.pixel_aspect_ratio {
+        println!("pixel_aspect_ratio = \"{}:{}\"", h_spacing, v_spacing);
+    }
+    if let Some([width, height, horizontal, vertical]) = info.clean_aperture {
+        println!("clean_aperture_width = {:.3}", fraction(width));
+        println!("clean_aperture_height = {:.3}", fraction(height));
+        println!(
+            "clean_aperture_horizontal_offset = {:.3}",
+            fraction(horizontal)
+        );
+        println!("clean_aperture_vertical_offset = {:.3}", fraction(vertical));
+    }
+    if let Some((buffer_size_db, max_bitrate, avg_bitrate)) = info.bitrate {
+        println!("buffer_size_db = {:?}", buffer_size_db);
+        println!("max_bitrate = {:?}", max_bitrate);
+        println!("avg_bitrate = {:?}", avg_bitrate);
+    }
+}
@@ -0,0 +1,104 @@
+//! Cover art extraction and image header dimensions.
+
+use crate::boxes::Reader;
+use crate::tags::Image;
+use std::fs;
+use std::io::Result;
+use std::path::{Path, PathBuf};
+
+/// Width and height from a JPEG start of frame segment.
+fn jpeg_dimensions(bytes: &[u8]) -> Result<Option<(u32, u32)>> {
+    let mut r = Reader::new(bytes);
+    r.skip(2)?;
+    while r.remaining() >= 4 {
+        if r.u8()? != 0xff {
+            return Ok(None);
+        }
+        let mut marker = r.u8()?;
+        while marker == 0xff {
+            marker = r.u8()?;
+        }
+        match marker {
+            // standalone markers carry no length
+            0x01 | 0xd0..=0xd7 => continue,
+            0xd9 | 0xda => return Ok(None),
+            _ => {}
+        }
+        let length = usize::from(r.u16()?);
+        // SOF0 to SOF15, except DHT, JPG and DAC
+        if (0xc0..=0xcf).contains(&marker) && ![0xc4, 0xc8, 0xcc].contains(&marker) {
+            r.skip(1)?;
+            let height = u32::from(r.u16()?);
+            let width = u32::from(r.u16()?);
+            return Ok(Some((width, height)));
+        }
+        r.skip(length.saturating_sub(2))?;
+    }
+    Ok(None)
+}
+
+/// Width and height from a PNG IHDR chunk.
+fn png_dimensions(bytes: &[u8]) -> Result<Option<(u32, u32)>> {
+    let mut r = Reader::new(bytes);
+    r.skip(12)?;
+    if r.bytes(4)? != b"IHDR" {
+        return Ok(None);
+    }
+    Ok(Some((r.u32()?, r.u32()?)))
+}
+
+/// Width and height from a BMP BITMAPINFOHEADER, which stores them little endian.
+fn bmp_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let field = |offset: usize| {
+        bytes
+            .get(offset..offset + 4)
+            .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]).unsigned_abs())
+    };
+    Some((field(18)?, field(22)?))
+}
+
+/// Image width and height read from the JPEG, PNG or BMP header.
+pub fn dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    if bytes.starts_with(&[0xff, 0xd8]) {
+        jpeg_dimensions(bytes).ok().flatten()
+    } else if bytes.starts_with(b"\x89PNG") {
+        png_dimensions(bytes).ok().flatten()
+    } else if bytes.starts_with(b"BM") {
+        bmp_dimensions(bytes)
+    } else {
+        None
+    }
+}
+
+/// File extension of an image format name.
+fn extension(format: &str) -> &'static str {
+    match format {
+        "jpeg" => "jpg",
+        "png" => "png",
+        "bmp" => "bmp",
+        _ => "bin",
+    }
+}
+
+/// Writes each cover image to `dir` as `cover-<n>.<ext>`, creating `dir` if needed.
+/// # Arguments
+/// * `buf` - Whole file contents
+/// * `images` - Cover images from `tags::covers`
+/// * `dir` - Output directory
+pub fn extract(buf: &[u8], images: &[Image], dir: &Path) -> Result<Vec<PathBuf>> {
+    if images.is_empty() {
+        return Ok(Vec::new());
+    }
+    fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+    for (index, image) in images.iter().enumerate() {
+        let path = dir.join(format!(
+            "cover-{}.{}",
+            index + 1,
+            extension(image.format(buf))
+        ));
+        fs::write(&path, image.bytes(buf))?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
@@ -0,0 +1,351 @@
+//! Dolby audio sample entries: AC-3 (`dac3`), E-AC-3 (`dec3`) and AC-4 (`dac4`).
+//!
+//! mp4parse does not model these entries, so they are read from the raw boxes.
+//! See ETSI TS 102 366 Annex F and ETSI TS 103 190-2 Annex E.
+
+use crate::bits::BitReader;
+use crate::boxes::{self, Mp4Box};
+use std::io::{Error, ErrorKind, Result};
+
+const AC3_BITRATES: [u32; 19] = [
+    32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384, 448, 512, 576, 640,
+];
+
+/// AC3SpecificBox fields.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ac3Config {
+    /// fscod
+    pub fscod: u32,
+    /// bsid
+    pub bsid: u32,
+    /// bsmod
+    pub bsmod: u32,
+    /// acmod
+    pub acmod: u32,
+    /// lfeon
+    pub lfeon: bool,
+    /// bit_rate_code
+    pub bit_rate_code: u32,
+}
+
+/// One independent substream of an EC3SpecificBox.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Eac3Substream {
+    /// fscod
+    pub fscod: u32,
+    /// bsid
+    pub bsid: u32,
+    /// asvc
+    pub asvc: bool,
+    /// bsmod
+    pub bsmod: u32,
+    /// acmod
+    pub acmod: u32,
+    /// lfeon
+    pub lfeon: bool,
+    /// num_dep_sub
+    pub num_dep_sub: u32,
+    /// chan_loc of the dependent substreams
+    pub chan_loc: Option<u32>,
+}
+
+/// EC3SpecificBox fields.
+#[derive(Debug, Clone, Default)]
+pub struct Eac3Config {
+    /// data_rate in kbit/s
+    pub data_rate: u32,
+    /// independent substreams, num_ind_sub + 1
+    pub substreams: Vec<Eac3Substream>,
+    /// complexity_index_type_a when flag_ec3_extension_type_a (Atmos JOC) is set
+    pub complexity_index: Option<u32>,
+}
+
+/// One presentation of an AC4SpecificBox.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ac4Presentation {
+    /// presentation_version
+    pub version: u32,
+    /// mdcompat, for version 1 and 2 presentations that carry it
+    pub mdcompat: Option<u32>,
+}
+
+/// AC4SpecificBox fields.
+#[derive(Debug, Clone, Default)]
+pub struct Ac4Config {
+    /// ac4_dsi_version
+    pub dsi_version: u32,
+    /// bitstream_version
+    pub bitstream_version: u32,
+    /// fs_index
+    pub fs_index: u32,
+    /// frame_rate_index
+    pub frame_rate_index: u32,
+    /// n_presentations
+    pub n_presentations: u32,
+    /// short_program_id
+    pub program_id: Option<u32>,
+    /// bit_rate_mode, bit_rate and bit_rate_precision
+    pub bitrate: Option<(u32, u32, u32)>,
+    /// presentations, when the DSI version describes them
+    pub presentations: Vec<Ac4Presentation>,
+}
+
+/// Parses a `dac3` payload.
+pub fn read_ac3(dac3: &[u8]) -> Result<Ac3Config> {
+    let mut r = BitReader::new(dac3);
+    Ok(Ac3Config {
+        fscod: r.bits(2)?,
+        bsid: r.bits(5)?,
+        bsmod: r.bits(3)?,
+        acmod: r.bits(3)?,
+        lfeon: r.flag()?,
+        bit_rate_code: r.bits(5)?,
+    })
+}
+
+/// Parses a `dec3` payload.
+pub fn read_eac3(dec3: &[u8]) -> Result<Eac3Config> {
+    let mut r = BitReader::new(dec3);
+    let mut config = Eac3Config {
+        data_rate: r.bits(13)?,
+        ..Default::default()
+    };
+    let num_ind_sub = r.bits(3)? + 1;
+    for _ in 0..num_ind_sub {
+        let mut sub = Eac3Substream {
+            fscod: r.bits(2)?,
+            bsid: r.bits(5)?,
+            ..Default::default()
+        };
+        r.skip(1)?;
+        sub.asvc = r.flag()?;
+        sub.bsmod = r.bits(3)?;
+        sub.acmod = r.bits(3)?;
+        sub.lfeon = r.flag()?;
+        r.skip(3)?;
+        sub.num_dep_sub = r.bits(4)?;
+        if sub.num_dep_sub > 0 {
+            sub.chan_loc = Some(r.bits(9)?);
+        } else {
+            r.skip(1)?;
+        }
+        config.substreams.push(sub);
+    }
+    if r.remaining() >= 16 {
+        r.skip(7)?;
+        if r.flag()? {
+            config.complexity_index = Some(r.bits(8)?);
+        }
+    }
+    Ok(config)
+}
+
+/// Parses a `dac4` payload.
+pub fn read_ac4(dac4: &[u8]) -> Result<Ac4Config> {
+    let mut r = BitReader::new(dac4);
+    let mut config = Ac4Config {
+        dsi_version: r.bits(3)?,
+        bitstream_version: r.bits(7)?,
+        fs_index: r.bits(1)?,
+        frame_rate_index: r.bits(4)?,
+        n_presentations: r.bits(9)?,
+        ..Default::default()
+    };
+    if config.dsi_version != 1 {
+        return Ok(config);
+    }
+    if config.bitstream_version > 1 && r.flag()? {
+        config.program_id = Some(r.bits(16)?);
+        if r.flag()? {
+            r.skip(128)?;
+        }
+    }
+    config.bitrate = Some((r.bits(2)?, r.bits(32)?, r.bits(32)?));
+    r.skip(r.remaining() % 8)?;
+    for _ in 0..config.n_presentations {
+        let version = r.bits(8)?;
+        let mut pres_bytes = r.bits(8)? as usize;
+        if pres_bytes == 255 {
+            pres_bytes += r.bits(16)? as usize;
+        }
+        let start = dac4.len() - r.remaining() / 8;
+        let body = dac4
+            .get(start..start + pres_bytes)
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "dac4 presentation truncated"))?;
+        r.skip(pres_bytes * 8)?;
+        let mut p = BitReader::new(body);
+        let mdcompat = match version {
+            1 | 2 if p.bits(5)? != 6 => Some(p.bits(3)?),
+            _ => None,
+        };
+        config
+            .presentations
+            .push(Ac4Presentation { version, mdcompat });
+    }
+    Ok(config)
+}
+
+/// RFC 6381 codecs string of an AC-4 configuration, e.g. `ac-4.02.01.03`.
+pub fn ac4_codecs(config: &Ac4Config) -> Option<String> {
+    let presentation = config.presentations.first()?;
+    Some(format!(
+        "ac-4.{:02x}.{:02x}.{:02x}",
+        config.bitstream_version, presentation.version, presentation.mdcompat?
+    ))
+}
+
+fn sample_rate(fscod: u32) -> u32 {
+    match fscod {
+        0 => 48000,
+        1 => 44100,
+        2 => 32000,
+        _ => 0,
+    }
+}
+
+/// Channel count without LFE and layout name of an acmod.
+fn audio_coding_mode(acmod: u32) -> (u32, &'static str) {
+    match acmod {
+        0 => (2, "1+1"),
+        1 => (1, "1/0"),
+        2 => (2, "2/0"),
+        3 => (3, "3/0"),
+        4 => (3, "2/1"),
+        5 => (4, "3/1"),
+        6 => (4, "2/2"),
+        _ => (5, "3/2"),
+    }
+}
+
+fn frame_rate_name(frame_rate_index: u32) -> &'static str {
+    match frame_rate_index {
+        0 => "23.976",
+        1 => "24",
+        2 => "25",
+        3 => "29.97",
+        4 => "30",
+        5 => "47.95",
+        6 => "48",
+        7 => "50",
+        8 => "59.94",
+        9 => "60",
+        10 => "100",
+        11 => "119.88",
+        12 => "120",
+        13 => "23.44",
+        _ => "reserved",
+    }
+}
+
+fn print_coding_mode(acmod: u32, lfeon: bool) {
+    let (channels, layout) = audio_coding_mode(acmod);
+    println!("acmod = {:?}", acmod);
+    println!("audio_coding_mode = \"{}\"", layout);
+    println!("lfeon = {:?}", lfeon);
+    println!("channels = {:?}", channels + u32::from(lfeon));
+}
+
+/// Prints the sample entry, codec and Dolby configuration sections of an
+/// `ac-3`, `ec-3` or `ac-4` sample entry.
+/// # Arguments
+/// * `buf` - Whole file contents
+/// * `entry` - raw sample entry box
+/// * `codecs` - RFC 6381 codecs string of the track, if known
+pub fn print_report(buf: &[u8], entry: &Mp4Box, codecs: Option<&str>) {
+    if let Some((channelcount, samplesize, samplerate)) = boxes::audio_entry_fields(buf, entry) {
+        println!("[media.track.audio.sample.entry]");
+        println!("channelcount = {:?}", channelcount);
+        println!("samplesize = {:?}", samplesize);
+        println!("samplerate = {:?}", f64::from(samplerate));
+    }
+    let (codec_name, config_name): (&str, &[u8; 4]) = match &entry.name {
+        b"ac-3" => ("AC-3", b"dac3"),
+        b"ec-3" => ("E-AC-3", b"dec3"),
+        _ => ("AC-4", b"dac4"),
+    };
+    println!("[media.track.audio.codec]");
+    println!("codec_name = \"{}\"", codec_name);
+    println!("fourcc = \"{}\"", String::from_utf8_lossy(&entry.name));
+    if let Some(codecs) = codecs {
+        println!("codecs = \"{}\"", codecs);
+    }
+    let config = boxes::audio_entry_children(buf, entry)
+        .into_iter()
+        .find(|b| b.is(config_name))
+        .map(|b| b.body(buf));
+    let section = match &entry.name {
+        b"ac-3" => "ac3",
+        b"ec-3" => "eac3",
+        _ => "ac4",
+    };
+    println!("[media.track.audio.{}]", section);
+    let config = match config {
+        Some(config) => config,
+        None => {
+            println!(
+                "error = \"{} box not found\"",
+                String::from_utf8_lossy(config_name)
+            );
+            return;
+        }
+    };
+    let result = match &entry.name {
+        b"ac-3" => read_ac3(config).map(|ac3| {
+            println!("bsid = {:?}", ac3.bsid);
+            println!("bsmod = {:?}", ac3.bsmod);
+            println!("sample_rate = {:?}", sample_rate(ac3.fscod));
+            print_coding_mode(ac3.acmod, ac3.lfeon);
+            println!("bit_rate_code = {:?}", ac3.bit_rate_code);
+            if let Some(kbps) = AC3_BITRATES.get(ac3.bit_rate_code as usize) {
+                println!("bitrate = {:?}", kbps * 1000);
+            }
+        }),
+        b"ec-3" => read_eac3(config).map(|eac3| {
+            println!("data_rate = {:?}", eac3.data_rate * 1000);
+            println!("independent_substreams = {:?}", eac3.substreams.len());
+            println!(
+                "dependent_substreams = {:?}",
+                eac3.substreams.iter().map(|s| s.num_dep_sub).sum::<u32>()
+            );
+            println!("joc = {:?}", eac3.complexity_index.is_some());
+            if let Some(complexity_index) = eac3.complexity_index {
+                println!("complexity_index = {:?}", complexity_index);
+            }
+            for sub in &eac3.substreams {
+                println!("[media.track.audio.eac3.substream]");
+                println!("bsid = {:?}", sub.bsid);
+                println!("bsmod = {:?}", sub.bsmod);
+                println!("asvc = {:?}", sub.asvc);
+                println!("sample_rate = {:?}", sample_rate(sub.fscod));
+                print_coding_mode(sub.acmod, sub.lfeon);
+                println!("num_dep_sub = {:?}", sub.num_dep_sub);
+                if let Some(chan_loc) = sub.chan_loc {
+                    println!("chan_loc = \"0x{:03x}\"", chan_loc);
+                }
+            }
+        }),
+        _ => read_ac4(config).map(|ac4| {
+            println!("dsi_version = {:?}", ac4.dsi_version);
+            println!("bitstream_version = {:?}", ac4.bitstream_version);
+            println!(
+                "sample_rate = {:?}",
+                if ac4.fs_index == 0 { 44100 } else { 48000 }
+            );
+            println!("frame_rate = \"{}\"", frame_rate_name(ac4.frame_rate_index));
+            println!("n_presentations = {:?}", ac4.n_presentations);
+            if let Some(program_id) = ac4.program_id {
+                println!("program_id = {:?}", program_id);
+            }
+            if let Some((mode, bit_rate, precision)) = ac4.bitrate {
+                println!("bit_rate_mode = {:?}", mode);
+                println!("bit_rate = {:?}", bit_rate);
+                println!("bit_rate_precision = {:?}", precision);
+            }
+            let versions: Vec<u32> = ac4.presentations.iter().map(|p| p.version).collect();
+            println!("presentation_versions = {:?}", versions);
+        }),
+    };
+    if let Err(e) = result {
+        println!("error = \"{}\"", e);
+    }
+}
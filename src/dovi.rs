@@ -0,0 +1,185 @@
+//! Dolby Vision configuration (`dvcC`, `dvvC`, `dvwC`).
+//!
+//! See "Dolby Vision Streams Within the ISO Base Media File Format" § 3.2.
+
+use crate::av1;
+use crate::avc;
+use crate::boxes::{self, Mp4Box, Reader};
+use crate::colour;
+use crate::hevc;
+use std::io::Result;
+
+/// DOVIDecoderConfigurationRecord fields.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DoviConfig {
+    /// box type, `dvcC` for profiles up to 7, `dvvC` for 8 to 10, `dvwC` above
+    pub box_type: [u8; 4],
+    /// dv_version_major
+    pub version_major: u8,
+    /// dv_version_minor
+    pub version_minor: u8,
+    /// dv_profile
+    pub profile: u8,
+    /// dv_level
+    pub level: u8,
+    /// rpu_present_flag
+    pub rpu_present: bool,
+    /// el_present_flag
+    pub el_present: bool,
+    /// bl_present_flag
+    pub bl_present: bool,
+    /// dv_bl_signal_compatibility_id
+    pub bl_signal_compatibility_id: u8,
+}
+
+/// Parses a `dvcC`, `dvvC` or `dvwC` payload.
+pub fn read_config(box_type: [u8; 4], body: &[u8]) -> Result<DoviConfig> {
+    let mut r = Reader::new(body);
+    let version_major = r.u8()?;
+    let version_minor = r.u8()?;
+    let flags = r.u16()?;
+    Ok(DoviConfig {
+        box_type,
+        version_major,
+        version_minor,
+        profile: (flags >> 9) as u8,
+        level: ((flags >> 3) & 0x3f) as u8,
+        rpu_present: flags & 0x4 != 0,
+        el_present: flags & 0x2 != 0,
+        bl_present: flags & 0x1 != 0,
+        bl_signal_compatibility_id: r.u8()? >> 4,
+    })
+}
+
+/// Finds the Dolby Vision configuration box among the children of a visual sample entry.
+pub fn find_config(buf: &[u8], entry: &Mp4Box) -> Option<Mp4Box> {
+    boxes::visual_entry_children(buf, entry)
+        .into_iter()
+        .find(|b| b.is(b"dvcC") || b.is(b"dvvC") || b.is(b"dvwC"))
+}
+
+/// RFC 6381 codecs string of a Dolby Vision sample entry, e.g. `dvh1.08.06`.
+pub fn codecs(fourcc: &str, config: &DoviConfig) -> String {
+    format!("{}.{:02}.{:02}", fourcc, config.profile, config.level)
+}
+
+fn compatibility_name(id: u8) -> &'static str {
+    match id {
+        0 => "none",
+        1 => "HDR10",
+        2 => "SDR",
+        4 => "HLG",
+        6 => "Ultra HD Blu-ray",
+        _ => "reserved",
+    }
+}
+
+/// Colour primaries and transfer characteristics signalled for the base layer,
+/// from `colr` or else from the codec configuration.
+fn base_layer_colour(buf: &[u8], entry: &Mp4Box) -> Option<(u32, u32)> {
+    if let Some(colr) = colour::read_colour_info(buf, entry)
+        .ok()
+        .and_then(|info| info.colr)
+    {
+        return Some((u32::from(colr.primaries), u32::from(colr.transfer)));
+    }
+    let children = boxes::visual_entry_children(buf, entry);
+    let config = |name: &[u8; 4]| children.iter().find(|b| b.is(name)).map(|b| b.body(buf));
+    let signal = if let Some(hvcc) = config(b"hvcC") {
+        let config = hevc::read_config(hvcc).ok()?;
+        let nal = config
+            .arrays
+            .iter()
+            .filter(|a| a.nal_unit_type == 33)
+            .flat_map(|a| a.units.first())
+            .next()?;
+        hevc::read_sps(nal).ok()?.vui?.video_signal?
+    } else if let Some(avcc) = config(b"avcC") {
+        let config = avc::read_config(avcc).ok()?;
+        avc::read_sps(config.sps.first()?).ok()?.video_signal?
+    } else if let Some(av1c) = config(b"av1C") {
+        let seq = av1::find_sequence_header(av1c.get(4..)?).ok()??;
+        return Some((seq.colour_primaries, seq.transfer_characteristics));
+    } else {
+        return None;
+    };
+    Some((signal.colour_primaries?, signal.transfer_characteristics?))
+}
+
+/// Checks a profile 8 base layer against its bl_signal_compatibility_id.
+fn check_profile_8(config: &DoviConfig, base: Option<(u32, u32)>) -> Option<String> {
+    let expected: &[(u32, u32)] = match config.bl_signal_compatibility_id {
+        1 | 6 => &[(9, 16)],
+        2 => &[(1, 1), (1, 6), (1, 14), (1, 15)],
+        4 => &[(9, 18), (9, 14)],
+        id => {
+            return Some(format!(
+                "profile 8 requires bl_signal_compatibility_id 1, 2, 4 or 6, found {}",
+                id
+            ))
+        }
+    };
+    let name = compatibility_name(config.bl_signal_compatibility_id);
+    match base {
+        None => Some(format!(
+            "profile 8.{} base layer has no colour signalling, expected {}",
+            config.bl_signal_compatibility_id, name
+        )),
+        Some((primaries, transfer)) if !expected.contains(&(primaries, transfer)) => Some(format!(
+            "profile 8.{} base layer signals {} primaries with {} transfer, expected {}",
+            config.bl_signal_compatibility_id,
+            colour::primaries_name(primaries),
+            colour::transfer_name(transfer),
+            name
+        )),
+        Some(_) => None,
+    }
+}
+
+/// Prints the `[media.track.video.dolby_vision]` section when the sample entry
+/// carries a Dolby Vision configuration box.
+pub fn print_report(buf: &[u8], entry: &Mp4Box) {
+    let config_box = match find_config(buf, entry) {
+        Some(config_box) => config_box,
+        None => return,
+    };
+    println!("[media.track.video.dolby_vision]");
+    let config = match read_config(config_box.name, config_box.body(buf)) {
+        Ok(config) => config,
+        Err(e) => {
+            println!("error = \"{}\"", e);
+            return;
+        }
+    };
+    println!("box = \"{}\"", String::from_utf8_lossy(&config.box_type));
+    println!(
+        "dv_version = \"{}.{}\"",
+        config.version_major, config.version_minor
+    );
+    println!("profile = {:?}", config.profile);
+    if config.bl_signal_compatibility_id > 0 {
+        println!(
+            "profile_name = \"{}.{}\"",
+            config.profile, config.bl_signal_compatibility_id
+        );
+    } else {
+        println!("profile_name = \"{}\"", config.profile);
+    }
+    println!("level = {:?}", config.level);
+    println!("rpu_present = {:?}", config.rpu_present);
+    println!("el_present = {:?}", config.el_present);
+    println!("bl_present = {:?}", config.bl_present);
+    println!(
+        "bl_signal_compatibility_id = {:?}",
+        config.bl_signal_compatibility_id
+    );
+    println!(
+        "bl_signal_compatibility = \"{}\"",
+        compatibility_name(config.bl_signal_compatibility_id)
+    );
+    if config.profile == 8 {
+        if let Some(warning) = check_profile_8(&config, base_layer_colour(buf, entry)) {
+            println!("warning = {:?}", warning);
+        }
+    }
+}
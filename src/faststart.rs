@@ -0,0 +1,336 @@
+//! Faststart (web optimised) detection and rewrite.
+//!
+//! A file is faststart when `moov` precedes the first `mdat`, so playback can
+//! begin before the whole file has been downloaded.
+
+use crate::boxes::{self, Mp4Box, Reader};
+use crate::sample_table;
+use mp4parse::{read_mp4, MediaContext};
+use std::collections::HashSet;
+use std::error::Error;
+use std::io::Cursor;
+
+/// Position of the top level boxes that matter for progressive download.
+#[derive(Debug, Clone)]
+pub struct Faststart {
+    /// `moov` box, if present
+    pub moov: Option<Mp4Box>,
+    /// first `mdat` box, if present
+    pub mdat: Option<Mp4Box>,
+    /// top level `free` and `skip` boxes
+    pub free: Vec<Mp4Box>,
+    /// bytes read from the start of the file before every track's first chunk and `moov` are available
+    pub bytes_before_playback: u64,
+}
+
+impl Faststart {
+    /// Returns true when `moov` precedes the first `mdat`.
+    pub fn is_faststart(&self) -> bool {
+        match (self.moov, self.mdat) {
+            (Some(moov), Some(mdat)) => moov.offset < mdat.offset,
+            (Some(_), None) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Locates `moov`, `mdat` and free space and works out the playback start point.
+/// # Arguments
+/// * `buf` - Whole file contents
+/// * `context` - mp4parse context read from `buf`
+pub fn detect(buf: &[u8], context: &MediaContext) -> Faststart {
+    let top = boxes::top_level(buf);
+    let moov = top.iter().find(|b| b.is(b"moov")).copied();
+    let mdat = top.iter().find(|b| b.is(b"mdat")).copied();
+    let free = top
+        .iter()
+        .filter(|b| b.is(b"free") || b.is(b"skip"))
+        .copied()
+        .collect();
+
+    let mut bytes_before_playback = moov.map_or(0, |m| m.end() as u64);
+    for track in context.tracks.iter() {
+        let sizes = sample_table::sample_sizes(track);
+        if let Some(chunk) = sample_table::chunks(track).first() {
+            let size: u64 = sizes
+                .iter()
+                .skip(chunk.first_sample)
+                .take(chunk.samples)
+                .map(|&s| u64::from(s))
+                .sum();
+            bytes_before_playback = bytes_before_playback.max(chunk.offset + size);
+        }
+    }
+    Faststart {
+        moov,
+        mdat,
+        free,
+        bytes_before_playback: bytes_before_playback.min(buf.len() as u64),
+    }
+}
+
+/// Prints the faststart fields of the `[media]` section.
+pub fn print_fields(faststart: &Faststart) {
+    println!("faststart = {:?}", faststart.is_faststart());
+    if let Some(moov) = faststart.moov {
+        println!("moov_offset = {:?}", moov.offset);
+        println!("moov_size = {:?}", moov.size);
+    }
+    if let Some(mdat) = faststart.mdat {
+        println!("mdat_offset = {:?}", mdat.offset);
+        println!("mdat_size = {:?}", mdat.size);
+    }
+    let free: Vec<String> = faststart
+        .free
+        .iter()
+        .map(|b| {
+            format!(
+                "{{ type = \"{}\", offset = {}, size = {} }}",
+                String::from_utf8_lossy(&b.name),
+                b.offset,
+                b.size
+            )
+        })
+        .collect();
+    println!("free = [{}]", free.join(", "));
+    println!(
+        "bytes_before_playback = {:?}",
+        faststart.bytes_before_playback
+    );
+}
+
+/// Result of moving `moov` in front of `mdat`.
+#[derive(Debug, Clone)]
+pub struct Rewrite {
+    /// rewritten file contents
+    pub data: Vec<u8>,
+    /// true when the input was already faststart and was copied unchanged
+    pub already_faststart: bool,
+    /// offset of `moov` in the output
+    pub moov_offset: usize,
+    /// size of `moov` in the output
+    pub moov_size: usize,
+    /// number of `stco` boxes upgraded to `co64`
+    pub co64_upgrades: usize,
+    /// number of chunks whose data was checked at the new offsets
+    pub verified_chunks: usize,
+}
+
+/// Rewrites `buf` with `moov` relocated before the first `mdat`, adjusting every
+/// `stco`/`co64` chunk offset. `stco` boxes are upgraded to `co64` when a moved
+/// offset no longer fits in 32 bits. Sample data is copied unchanged.
+pub fn rewrite(buf: &[u8]) -> Result<Rewrite, Box<dyn Error>> {
+    let top = boxes::top_level(buf);
+    let moov = *top
+        .iter()
+        .find(|b| b.is(b"moov"))
+        .ok_or("moov box not found")?;
+    let mdat = *top
+        .iter()
+        .find(|b| b.is(b"mdat"))
+        .ok_or("mdat box not found")?;
+    if top.iter().any(|b| b.is(b"moof")) {
+        return Err("fragmented files are not supported by faststart".into());
+    }
+    if moov.offset < mdat.offset {
+        return Ok(Rewrite {
+            data: buf.to_vec(),
+            already_faststart: true,
+            moov_offset: moov.offset,
+            moov_size: moov.size,
+            co64_upgrades: 0,
+            verified_chunks: 0,
+        });
+    }
+
+    // Offsets between mdat and moov move forward by the new moov size, offsets
+    // after moov only move by the growth of moov.
+    let mut upgraded = HashSet::new();
+    let mut moov_size = moov.size;
+    let new_moov = loop {
+        let shift = |offset: u64| -> u64 {
+            let offset_usize = offset as usize;
+            if offset_usize < mdat.offset {
+                offset
+            } else if offset_usize < moov.offset {
+                offset + moov_size as u64
+            } else {
+                offset + moov_size as u64 - moov.size as u64
+            }
+        };
+        let mut overflowed = Vec::new();
+        let bytes = rebuild(buf, &moov, &shift, &upgraded, &mut overflowed)?;
+        if overflowed.is_empty() && bytes.len() == moov_size {
+            break bytes;
+        }
+        upgraded.extend(overflowed);
+        moov_size = bytes.len();
+    };
+
+    let mut data = Vec::with_capacity(buf.len() + new_moov.len() - moov.size);
+    for b in &top {
+        if b.offset == mdat.offset {
+            data.extend_from_slice(&new_moov);
+        }
+        if !b.is(b"moov") {
+            data.extend_from_slice(&buf[b.offset..b.end()]);
+        }
+    }
+    // bytes after the last complete box, such as a truncated trailing box
+    let parsed_end = top.last().map_or(0, |b| b.end());
+    data.extend_from_slice(&buf[parsed_end..]);
+    let verified_chunks = verify(buf, &data)?;
+    Ok(Rewrite {
+        data,
+        already_faststart: false,
+        moov_offset: mdat.offset,
+        moov_size,
+        co64_upgrades: upgraded.len(),
+        verified_chunks,
+    })
+}
+
+fn write_box(out: &mut Vec<u8>, name: &[u8; 4], body: &[u8]) {
+    let size = body.len() + 8;
+    if size > u32::MAX as usize {
+        out.extend_from_slice(&1u32.to_be_bytes());
+        out.extend_from_slice(name);
+        out.extend_from_slice(&(size as u64 + 8).to_be_bytes());
+    } else {
+        out.extend_from_slice(&(size as u32).to_be_bytes());
+        out.extend_from_slice(name);
+    }
+    out.extend_from_slice(body);
+}
+
+/// Serialises `b` with shifted chunk offsets, recursing through the containers
+/// on the path to `stco`/`co64`. Offsets of `stco` boxes whose shifted values
+/// overflow 32 bits are pushed to `overflowed`.
+fn rebuild(
+    buf: &[u8],
+    b: &Mp4Box,
+    shift: &dyn Fn(u64) -> u64,
+    upgraded: &HashSet<usize>,
+    overflowed: &mut Vec<usize>,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut out = Vec::new();
+    match &b.name {
+        b"moov" | b"trak" | b"mdia" | b"minf" | b"stbl" => {
+            let mut body = Vec::new();
+            for c in boxes::children(buf, b) {
+                body.extend(rebuild(buf, &c, shift, upgraded, overflowed)?);
+            }
+            write_box(&mut out, &b.name, &body);
+        }
+        b"stco" | b"co64" => {
+            let mut r = Reader::new(b.body(buf));
+            r.full_box()?;
+            let count = r.u32()?;
+            let entry_size = if b.is(b"co64") { 8 } else { 4 };
+            if count as usize > r.remaining() / entry_size {
+                return Err(format!(
+                    "{} entry count {} exceeds the box size",
+                    String::from_utf8_lossy(&b.name),
+                    count
+                )
+                .into());
+            }
+            let mut offsets = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                let offset = if b.is(b"co64") {
+                    r.u64()?
+                } else {
+                    u64::from(r.u32()?)
+                };
+                offsets.push(shift(offset));
+            }
+            let large = offsets.iter().any(|&o| o > u64::from(u32::MAX));
+            let co64 = b.is(b"co64") || upgraded.contains(&b.offset);
+            if large && !co64 {
+                overflowed.push(b.offset);
+            }
+            let mut body = vec![0, 0, 0, 0];
+            body.extend_from_slice(&count.to_be_bytes());
+            for o in offsets {
+                if co64 {
+                    body.extend_from_slice(&o.to_be_bytes());
+                } else {
+                    body.extend_from_slice(&(o as u32).to_be_bytes());
+                }
+            }
+            write_box(&mut out, if co64 { b"co64" } else { b"stco" }, &body);
+        }
+        _ => out.extend_from_slice(&buf[b.offset..b.end()]),
+    }
+    Ok(out)
+}
+
+/// Re-reads the rewritten file and checks every chunk holds the same bytes as before.
+fn verify(before: &[u8], after: &[u8]) -> Result<usize, Box<dyn Error>> {
+    let old = read_mp4(&mut Cursor::new(before))?;
+    let new = read_mp4(&mut Cursor::new(after))?;
+    let mut verified = 0;
+    for (old_track, new_track) in old.tracks.iter().zip(new.tracks.iter()) {
+        let sizes = sample_table::sample_sizes(old_track);
+        let old_chunks = sample_table::chunks(old_track);
+        let new_chunks = sample_table::chunks(new_track);
+        if old_chunks.len() != new_chunks.len() {
+            return Err("faststart verification failed: chunk count changed".into());
+        }
+        for (o, n) in old_chunks.iter().zip(new_chunks.iter()) {
+            let size: u64 = sizes
+                .iter()
+                .skip(o.first_sample)
+                .take(o.samples)
+                .map(|&s| u64::from(s))
+                .sum();
+            let old_data = before.get(o.offset as usize..(o.offset + size) as usize);
+            let new_data = after.get(n.offset as usize..(n.offset + size) as usize);
+            if old_data.is_none() || old_data != new_data {
+                return Err(format!(
+                    "faststart verification failed: chunk at offset {} moved incorrectly",
+                    o.offset
+                )
+                .into());
+            }
+            verified += 1;
+        }
+    }
+    Ok(verified)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_rebuild_upgrades_stco_to_co64() {
+        let buf = [
+            0, 0, 0, 20, b's', b't', b'c', b'o', 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 100,
+        ];
+        let stco = boxes::top_level(&buf)[0];
+        let shift = |offset: u64| offset + u64::from(u32::MAX);
+        let mut upgraded = HashSet::new();
+        let mut overflowed = Vec::new();
+        rebuild(&buf, &stco, &shift, &upgraded, &mut overflowed).unwrap();
+        assert_eq!(overflowed, vec![0]);
+
+        upgraded.insert(0);
+        overflowed.clear();
+        let out = rebuild(&buf, &stco, &shift, &upgraded, &mut overflowed).unwrap();
+        assert!(overflowed.is_empty());
+        assert_eq!(&out[4..8], b"co64");
+        assert_eq!(out.len(), 24);
+        assert_eq!(&out[16..24], &(u64::from(u32::MAX) + 100).to_be_bytes());
+    }
+
+    #[test]
+    fn unit_rebuild_rejects_stco_count() {
+        let buf = [
+            0, 0, 0, 20, b's', b't', b'c', b'o', 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 100,
+        ];
+        let stco = boxes::top_level(&buf)[0];
+        let err = rebuild(&buf, &stco, &|o| o, &HashSet::new(), &mut Vec::new()).unwrap_err();
+        assert!(err.to_string().contains("exceeds the box size"));
+    }
+}
@@ -0,0 +1,113 @@
+//! FLAC codec configuration (`dfLa`) and STREAMINFO decoding.
+//!
+//! See "Encapsulation of FLAC in ISO Base Media File Format" § 3.3.2 and the
+//! FLAC format specification § 8.2.
+
+use crate::bits::BitReader;
+use mp4parse::FLACSpecificBox;
+use std::io::{Error, ErrorKind, Result};
+
+const STREAMINFO: u8 = 0;
+
+/// Decoded METADATA_BLOCK_STREAMINFO.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StreamInfo {
+    /// minimum block size in samples
+    pub min_block_size: u32,
+    /// maximum block size in samples
+    pub max_block_size: u32,
+    /// minimum frame size in bytes, 0 when unknown
+    pub min_frame_size: u32,
+    /// maximum frame size in bytes, 0 when unknown
+    pub max_frame_size: u32,
+    /// sample rate in Hz
+    pub sample_rate: u32,
+    /// number of channels
+    pub channels: u32,
+    /// bits per sample
+    pub bits_per_sample: u32,
+    /// total samples per channel, 0 when unknown
+    pub total_samples: u64,
+    /// MD5 signature of the unencoded audio
+    pub md5: [u8; 16],
+}
+
+/// Decodes a STREAMINFO metadata block.
+pub fn read_stream_info(data: &[u8]) -> Result<StreamInfo> {
+    if data.len() < 34 {
+        return Err(Error::new(
+            ErrorKind::UnexpectedEof,
+            "STREAMINFO shorter than 34 bytes",
+        ));
+    }
+    let mut r = BitReader::new(data);
+    let mut info = StreamInfo {
+        min_block_size: r.bits(16)?,
+        max_block_size: r.bits(16)?,
+        min_frame_size: r.bits(24)?,
+        max_frame_size: r.bits(24)?,
+        sample_rate: r.bits(20)?,
+        channels: r.bits(3)? + 1,
+        bits_per_sample: r.bits(5)? + 1,
+        total_samples: u64::from(r.bits(4)?) << 32,
+        ..Default::default()
+    };
+    info.total_samples |= u64::from(r.bits(32)?);
+    info.md5.copy_from_slice(&data[18..34]);
+    Ok(info)
+}
+
+fn block_type_name(block_type: u8) -> &'static str {
+    match block_type {
+        0 => "STREAMINFO",
+        1 => "PADDING",
+        2 => "APPLICATION",
+        3 => "SEEKTABLE",
+        4 => "VORBIS_COMMENT",
+        5 => "CUESHEET",
+        6 => "PICTURE",
+        _ => "reserved",
+    }
+}
+
+/// Prints the `[media.track.audio.flac]` section.
+pub fn print_report(dfla: &FLACSpecificBox) {
+    println!("[media.track.audio.flac]");
+    let names: Vec<&str> = dfla
+        .blocks
+        .iter()
+        .map(|b| block_type_name(b.block_type))
+        .collect();
+    println!("metadata_blocks = {:?}", names);
+    let streaminfo = match dfla.blocks.first() {
+        Some(block) if block.block_type == STREAMINFO => block,
+        _ => {
+            println!(
+                "error = {:?}",
+                "dfLa does not start with a STREAMINFO block"
+            );
+            return;
+        }
+    };
+    match read_stream_info(&streaminfo.data) {
+        Ok(info) => {
+            println!("min_block_size = {:?}", info.min_block_size);
+            println!("max_block_size = {:?}", info.max_block_size);
+            println!("min_frame_size = {:?}", info.min_frame_size);
+            println!("max_frame_size = {:?}", info.max_frame_size);
+            println!("sample_rate = {:?}", info.sample_rate);
+            println!("channels = {:?}", info.channels);
+            println!("bits_per_sample = {:?}", info.bits_per_sample);
+            println!("total_samples = {:?}", info.total_samples);
+            if info.sample_rate > 0 && info.total_samples > 0 {
+                println!(
+                    "duration = {:.6}",
+                    info.total_samples as f64 / f64::from(info.sample_rate)
+                );
+            }
+            let md5: String = info.md5.iter().map(|b| format!("{:02x}", b)).collect();
+            println!("md5 = \"{}\"", md5);
+        }
+        Err(e) => println!("error = \"{}\"", e),
+    }
+}
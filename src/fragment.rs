@@ -0,0 +1,436 @@
+//! Fragmented MP4 parsing: `mvex/trex` and `moof/mfhd/traf/tfhd/tfdt/trun`.
+//!
+//! mp4parse stops at `moov`, so for fragmented files the sample tables are
+//! empty and track statistics have to be computed from the movie fragments.
+
+use crate::boxes::{self, Mp4Box, Reader};
+use mp4parse::MediaContext;
+use std::io::{Error, ErrorKind, Result};
+
+/// Track defaults from `mvex/trex`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TrackExtends {
+    /// track id
+    pub track_id: u32,
+    /// default sample description index
+    pub default_sample_description_index: u32,
+    /// default sample duration in track timescale units
+    pub default_sample_duration: u32,
+    /// default sample size in bytes
+    pub default_sample_size: u32,
+    /// default sample flags
+    pub default_sample_flags: u32,
+}
+
+/// A resolved fragment sample.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FragmentSample {
+    /// duration in track timescale units
+    pub duration: u32,
+    /// size in bytes
+    pub size: u32,
+    /// sample flags
+    pub flags: u32,
+    /// composition time offset
+    pub composition_offset: i64,
+}
+
+impl FragmentSample {
+    /// Returns true when `sample_is_non_sync_sample` is clear.
+    pub fn is_sync(&self) -> bool {
+        self.flags & 0x0001_0000 == 0
+    }
+}
+
+/// Per sample fields of a `trun`; unset fields come from the tfhd or trex defaults.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RunSample {
+    /// sample duration
+    pub duration: Option<u32>,
+    /// sample size
+    pub size: Option<u32>,
+    /// sample flags
+    pub flags: Option<u32>,
+    /// composition time offset
+    pub composition_offset: Option<i64>,
+}
+
+/// Track run `trun`.
+#[derive(Debug, Clone, Default)]
+pub struct TrackRun {
+    /// data offset relative to the base data offset
+    pub data_offset: Option<i32>,
+    /// flags overriding those of the first sample
+    pub first_sample_flags: Option<u32>,
+    /// per sample fields present in the run
+    pub samples: Vec<RunSample>,
+}
+
+/// Track fragment `traf`.
+#[derive(Debug, Clone, Default)]
+pub struct TrackFragment {
+    /// track id from tfhd
+    pub track_id: u32,
+    /// tfhd flags
+    pub tfhd_flags: u32,
+    /// explicit base data offset from tfhd
+    pub base_data_offset: Option<u64>,
+    /// sample description index from tfhd
+    pub sample_description_index: Option<u32>,
+    /// default sample duration from tfhd
+    pub default_sample_duration: Option<u32>,
+    /// default sample size from tfhd
+    pub default_sample_size: Option<u32>,
+    /// default sample flags from tfhd
+    pub default_sample_flags: Option<u32>,
+    /// base media decode time from tfdt
+    pub base_media_decode_time: Option<u64>,
+    /// track runs
+    pub runs: Vec<TrackRun>,
+    /// resolved samples of all runs
+    pub samples: Vec<FragmentSample>,
+}
+
+impl TrackFragment {
+    /// Summed sample durations in track timescale units.
+    pub fn duration(&self) -> u64 {
+        self.samples.iter().map(|s| u64::from(s.duration)).sum()
+    }
+
+    /// Summed sample sizes in bytes.
+    pub fn size(&self) -> u64 {
+        self.samples.iter().map(|s| u64::from(s.size)).sum()
+    }
+}
+
+/// Movie fragment `moof` and the `mdat` that follows it.
+#[derive(Debug, Clone)]
+pub struct MovieFragment {
+    /// the `moof` box
+    pub moof: Mp4Box,
+    /// the first `mdat` following the `moof`
+    pub mdat: Option<Mp4Box>,
+    /// sequence number from mfhd
+    pub sequence_number: Option<u32>,
+    /// track fragments
+    pub trafs: Vec<TrackFragment>,
+}
+
+/// Reads `moov/mvex/trex` for every track.
+pub fn read_trex(buf: &[u8]) -> Result<Vec<TrackExtends>> {
+    let mut trexs = Vec::new();
+    let mvex = boxes::find_top(buf, b"moov").and_then(|moov| boxes::child(buf, &moov, b"mvex"));
+    if let Some(mvex) = mvex {
+        for trex in boxes::children(buf, &mvex).iter().filter(|b| b.is(b"trex")) {
+            let mut r = Reader::new(trex.body(buf));
+            r.full_box()?;
+            trexs.push(TrackExtends {
+                track_id: r.u32()?,
+                default_sample_description_index: r.u32()?,
+                default_sample_duration: r.u32()?,
+                default_sample_size: r.u32()?,
+                default_sample_flags: r.u32()?,
+            });
+        }
+    }
+    Ok(trexs)
+}
+
+/// Reads every top level `moof` of `buf`, resolving sample fields against `trexs`.
+pub fn read_fragments(buf: &[u8], trexs: &[TrackExtends]) -> Result<Vec<MovieFragment>> {
+    let top = boxes::top_level(buf);
+    let mut fragments = Vec::new();
+    for (i, moof) in top.iter().enumerate().filter(|(_, b)| b.is(b"moof")) {
+        let mdat = top[i + 1..]
+            .iter()
+            .take_while(|b| !b.is(b"moof"))
+            .find(|b| b.is(b"mdat"))
+            .copied();
+        fragments.push(read_moof(buf, moof, mdat, trexs)?);
+    }
+    Ok(fragments)
+}
+
+/// Reads a single `moof`.
+pub fn read_moof(
+    buf: &[u8],
+    moof: &Mp4Box,
+    mdat: Option<Mp4Box>,
+    trexs: &[TrackExtends],
+) -> Result<MovieFragment> {
+    let mut fragment = MovieFragment {
+        moof: *moof,
+        mdat,
+        sequence_number: None,
+        trafs: Vec::new(),
+    };
+    // runs without per sample fields are bounded by the fragment size
+    let mut sample_budget = moof.size + mdat.map_or(0, |m| m.size);
+    for b in boxes::children(buf, moof) {
+        match &b.name {
+            b"mfhd" => {
+                let mut r = Reader::new(b.body(buf));
+                r.full_box()?;
+                fragment.sequence_number = Some(r.u32()?);
+            }
+            b"traf" => {
+                let traf = read_traf(buf, &b, trexs, &mut sample_budget)?;
+                fragment.trafs.push(traf);
+            }
+            _ => {}
+        }
+    }
+    Ok(fragment)
+}
+
+fn read_traf(
+    buf: &[u8],
+    traf: &Mp4Box,
+    trexs: &[TrackExtends],
+    sample_budget: &mut usize,
+) -> Result<TrackFragment> {
+    let mut t = TrackFragment::default();
+    for b in boxes::children(buf, traf) {
+        let mut r = Reader::new(b.body(buf));
+        match &b.name {
+            b"tfhd" => {
+                let (_, flags) = r.full_box()?;
+                t.tfhd_flags = flags;
+                t.track_id = r.u32()?;
+                if flags & 0x01 != 0 {
+                    t.base_data_offset = Some(r.u64()?);
+                }
+                if flags & 0x02 != 0 {
+                    t.sample_description_index = Some(r.u32()?);
+                }
+                if flags & 0x08 != 0 {
+                    t.default_sample_duration = Some(r.u32()?);
+                }
+                if flags & 0x10 != 0 {
+                    t.default_sample_size = Some(r.u32()?);
+                }
+                if flags & 0x20 != 0 {
+                    t.default_sample_flags = Some(r.u32()?);
+                }
+            }
+            b"tfdt" => {
+                let (version, _) = r.full_box()?;
+                t.base_media_decode_time = Some(if version == 1 {
+                    r.u64()?
+                } else {
+                    u64::from(r.u32()?)
+                });
+            }
+            b"trun" => {
+                let (version, flags) = r.full_box()?;
+                let count = r.u32()? as usize;
+                let mut run = TrackRun::default();
+                if flags & 0x01 != 0 {
+                    run.data_offset = Some(r.i32()?);
+                }
+                if flags & 0x04 != 0 {
+                    run.first_sample_flags = Some(r.u32()?);
+                }
+                let sample_bytes = 4 * (flags & 0xf00).count_ones() as usize;
+                let fits = if sample_bytes > 0 {
+                    count
+                        .checked_mul(sample_bytes)
+                        .is_some_and(|n| n <= r.remaining())
+                } else {
+                    count <= *sample_budget
+                };
+                if !fits {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("trun sample_count {} exceeds the fragment size", count),
+                    ));
+                }
+                *sample_budget = sample_budget.saturating_sub(count);
+                run.samples.reserve(count);
+                for _ in 0..count {
+                    let mut sample = RunSample::default();
+                    if flags & 0x100 != 0 {
+                        sample.duration = Some(r.u32()?);
+                    }
+                    if flags & 0x200 != 0 {
+                        sample.size = Some(r.u32()?);
+                    }
+                    if flags & 0x400 != 0 {
+                        sample.flags = Some(r.u32()?);
+                    }
+                    if flags & 0x800 != 0 {
+                        let raw = r.u32()?;
+                        sample.composition_offset = Some(if version == 0 {
+                            i64::from(raw)
+                        } else {
+                            i64::from(raw as i32)
+                        });
+                    }
+                    run.samples.push(sample);
+                }
+                t.runs.push(run);
+            }
+            _ => {}
+        }
+    }
+
+    let trex = trexs
+        .iter()
+        .find(|x| x.track_id == t.track_id)
+        .copied()
+        .unwrap_or_default();
+    for run in &t.runs {
+        for (i, sample) in run.samples.iter().enumerate() {
+            let flags = match (i, run.first_sample_flags) {
+                (0, Some(first)) => first,
+                _ => sample
+                    .flags
+                    .or(t.default_sample_flags)
+                    .unwrap_or(trex.default_sample_flags),
+            };
+            t.samples.push(FragmentSample {
+                duration: sample
+                    .duration
+                    .or(t.default_sample_duration)
+                    .unwrap_or(trex.default_sample_duration),
+                size: sample
+                    .size
+                    .or(t.default_sample_size)
+                    .unwrap_or(trex.default_sample_size),
+                flags,
+                composition_offset: sample.composition_offset.unwrap_or(0),
+            });
+        }
+    }
+    Ok(t)
+}
+
+/// Formats sample flags with their ISO 14496-12 § 8.8.3.1 fields.
+pub fn describe_sample_flags(flags: u32) -> String {
+    format!(
+        "{{ value = \"0x{:08x}\", is_leading = {}, depends_on = {}, is_depended_on = {}, has_redundancy = {}, padding = {}, non_sync = {}, degradation_priority = {} }}",
+        flags,
+        (flags >> 26) & 0x3,
+        (flags >> 24) & 0x3,
+        (flags >> 22) & 0x3,
+        (flags >> 20) & 0x3,
+        (flags >> 17) & 0x7,
+        (flags >> 16) & 0x1 == 1,
+        flags & 0xffff
+    )
+}
+
+/// Aggregate statistics of one track across all fragments.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FragmentedTrack {
+    /// track id
+    pub track_id: u32,
+    /// track timescale
+    pub timescale: u64,
+    /// number of fragments carrying the track
+    pub fragments: usize,
+    /// number of samples
+    pub samples: usize,
+    /// number of sync samples
+    pub sync_samples: usize,
+    /// summed duration in track timescale units
+    pub duration: u64,
+    /// summed sample sizes in bytes
+    pub bytes: u64,
+}
+
+/// Aggregates fragment samples per track, in `moov` track order.
+pub fn track_statistics(
+    context: &MediaContext,
+    fragments: &[MovieFragment],
+) -> Vec<FragmentedTrack> {
+    let mut stats: Vec<FragmentedTrack> = context
+        .tracks
+        .iter()
+        .map(|t| FragmentedTrack {
+            track_id: t.track_id.unwrap_or(0),
+            timescale: t.timescale.map_or(1, |ts| ts.0.max(1)),
+            ..Default::default()
+        })
+        .collect();
+    for traf in fragments.iter().flat_map(|f| f.trafs.iter()) {
+        if let Some(s) = stats.iter_mut().find(|s| s.track_id == traf.track_id) {
+            s.fragments += 1;
+            s.samples += traf.samples.len();
+            s.sync_samples += traf.samples.iter().filter(|x| x.is_sync()).count();
+            s.duration += traf.duration();
+            s.bytes += traf.size();
+        }
+    }
+    stats
+}
+
+/// Prints the `[media.fragments]` section.
+pub fn print_report(context: &MediaContext, trexs: &[TrackExtends], fragments: &[MovieFragment]) {
+    let stats = track_statistics(context, fragments);
+    let timescale = |track_id: u32| {
+        stats
+            .iter()
+            .find(|s| s.track_id == track_id)
+            .map_or(1, |s| s.timescale) as f64
+    };
+    println!("[media.fragments]");
+    println!("fragment_count = {:?}", fragments.len());
+    for trex in trexs {
+        println!("[media.fragments.trex]");
+        println!("track_id = {:?}", trex.track_id);
+        println!(
+            "default_sample_description_index = {:?}",
+            trex.default_sample_description_index
+        );
+        println!(
+            "default_sample_duration = {:?}",
+            trex.default_sample_duration
+        );
+        println!("default_sample_size = {:?}", trex.default_sample_size);
+        println!(
+            "default_sample_flags = {}",
+            describe_sample_flags(trex.default_sample_flags)
+        );
+    }
+    for s in &stats {
+        let duration = s.duration as f64 / s.timescale as f64;
+        println!("[media.fragments.track]");
+        println!("track_id = {:?}", s.track_id);
+        println!("fragments = {:?}", s.fragments);
+        println!("sample_count = {:?}", s.samples);
+        println!("sync_sample_count = {:?}", s.sync_samples);
+        println!("duration = {:.6}", duration);
+        println!("bytes = {:?}", s.bytes);
+        if duration > 0.0 {
+            println!("bitrate = {:.0}", s.bytes as f64 * 8.0 / duration);
+        }
+    }
+    for f in fragments {
+        println!("[media.fragments.fragment]");
+        if let Some(sequence_number) = f.sequence_number {
+            println!("sequence_number = {:?}", sequence_number);
+        }
+        println!("moof_offset = {:?}", f.moof.offset);
+        println!("moof_size = {:?}", f.moof.size);
+        if let Some(mdat) = f.mdat {
+            println!("mdat_size = {:?}", mdat.size);
+        }
+        for t in &f.trafs {
+            println!("[media.fragments.fragment.traf]");
+            println!("track_id = {:?}", t.track_id);
+            if let Some(time) = t.base_media_decode_time {
+                println!("base_media_decode_time = {:?}", time);
+            }
+            println!("sample_count = {:?}", t.samples.len());
+            println!(
+                "duration = {:.6}",
+                t.duration() as f64 / timescale(t.track_id)
+            );
+            println!("bytes = {:?}", t.size());
+            if let Some(flags) = t.default_sample_flags {
+                println!("default_sample_flags = {}", describe_sample_flags(flags));
+            }
+        }
+    }
+}
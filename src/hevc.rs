@@ -0,0 +1,612 @@
+//! H.265/HEVC decoder configuration record (`hvcC`) and SPS decoding.
+//!
+//! mp4parse does not recognise `hvc1`/`hev1` sample entries, so the record is
+//! read from the raw sample entry. See ISO/IEC 14496-15 § 8.3.3 and ITU-T
+//! H.265 § 7.3.2.2.
+
+use crate::avc::{self, VuiHeader};
+use crate::bits::{self, BitReader};
+use crate::boxes::{self, Mp4Box, Reader};
+use std::io::{Error, ErrorKind, Result};
+
+/// An array of NAL units of one type.
+#[derive(Debug, Clone)]
+pub struct NalArray {
+    /// array_completeness
+    pub complete: bool,
+    /// NAL_unit_type
+    pub nal_unit_type: u8,
+    /// NAL units, including their two byte headers
+    pub units: Vec<Vec<u8>>,
+}
+
+/// HEVCDecoderConfigurationRecord.
+#[derive(Debug, Clone, Default)]
+pub struct HevcConfig {
+    /// configurationVersion
+    pub version: u8,
+    /// general_profile_space
+    pub profile_space: u8,
+    /// general_tier_flag
+    pub tier: u8,
+    /// general_profile_idc
+    pub profile_idc: u8,
+    /// general_profile_compatibility_flags
+    pub compatibility_flags: u32,
+    /// general_constraint_indicator_flags, 48 bits
+    pub constraint_flags: u64,
+    /// general_level_idc
+    pub level_idc: u8,
+    /// min_spatial_segmentation_idc
+    pub min_spatial_segmentation: u16,
+    /// parallelismType
+    pub parallelism_type: u8,
+    /// chroma_format_idc
+    pub chroma_format_idc: u8,
+    /// luma bit depth
+    pub bit_depth_luma: u8,
+    /// chroma bit depth
+    pub bit_depth_chroma: u8,
+    /// avgFrameRate in frames per 256 seconds
+    pub avg_frame_rate: u16,
+    /// constantFrameRate
+    pub constant_frame_rate: u8,
+    /// numTemporalLayers
+    pub temporal_layers: u8,
+    /// temporalIdNested
+    pub temporal_id_nested: bool,
+    /// NAL unit length field size in bytes
+    pub nal_length_size: u8,
+    /// parameter set and SEI arrays
+    pub arrays: Vec<NalArray>,
+}
+
+/// Decoded sequence parameter set fields.
+#[derive(Debug, Clone, Default)]
+pub struct Sps {
+    /// sps_seq_parameter_set_id
+    pub id: u32,
+    /// sps_max_sub_layers_minus1 + 1
+    pub max_sub_layers: u32,
+    /// general_profile_idc
+    pub profile_idc: u32,
+    /// general_level_idc
+    pub level_idc: u32,
+    /// chroma_format_idc
+    pub chroma_format_idc: u32,
+    /// pic_width_in_luma_samples
+    pub coded_width: u32,
+    /// pic_height_in_luma_samples
+    pub coded_height: u32,
+    /// conformance window offsets left, right, top, bottom in luma samples
+    pub conformance_window: (u32, u32, u32, u32),
+    /// luma bit depth
+    pub bit_depth_luma: u32,
+    /// chroma bit depth
+    pub bit_depth_chroma: u32,
+    /// sps_max_num_reorder_pics of the highest sub-layer
+    pub max_num_reorder_pics: u32,
+    /// aspect ratio and video signal description from the VUI
+    pub vui: Option<VuiHeader>,
+    /// vui_num_units_in_tick and vui_time_scale
+    pub timing: Option<(u32, u32)>,
+}
+
+impl Sps {
+    /// Displayed width after the conformance window.
+    pub fn width(&self) -> u32 {
+        self.coded_width.saturating_sub(
+            self.conformance_window
+                .0
+                .saturating_add(self.conformance_window.1),
+        )
+    }
+
+    /// Displayed height after the conformance window.
+    pub fn height(&self) -> u32 {
+        self.coded_height.saturating_sub(
+            self.conformance_window
+                .2
+                .saturating_add(self.conformance_window.3),
+        )
+    }
+}
+
+/// Parses an `hvcC` payload.
+pub fn read_config(hvcc: &[u8]) -> Result<HevcConfig> {
+    let mut r = Reader::new(hvcc);
+    let version = r.u8()?;
+    let profile = r.u8()?;
+    let compatibility_flags = r.u32()?;
+    let constraint_flags = (u64::from(r.u32()?) << 16) | u64::from(r.u16()?);
+    let mut config = HevcConfig {
+        version,
+        profile_space: profile >> 6,
+        tier: (profile >> 5) & 1,
+        profile_idc: profile & 0x1f,
+        compatibility_flags,
+        constraint_flags,
+        level_idc: r.u8()?,
+        min_spatial_segmentation: r.u16()? & 0x0fff,
+        parallelism_type: r.u8()? & 0x3,
+        chroma_format_idc: r.u8()? & 0x3,
+        bit_depth_luma: (r.u8()? & 0x7) + 8,
+        bit_depth_chroma: (r.u8()? & 0x7) + 8,
+        avg_frame_rate: r.u16()?,
+        ..Default::default()
+    };
+    let b = r.u8()?;
+    config.constant_frame_rate = b >> 6;
+    config.temporal_layers = (b >> 3) & 0x7;
+    config.temporal_id_nested = (b >> 2) & 1 == 1;
+    config.nal_length_size = (b & 0x3) + 1;
+    for _ in 0..r.u8()? {
+        let b = r.u8()?;
+        let mut array = NalArray {
+            complete: b >> 7 == 1,
+            nal_unit_type: b & 0x3f,
+            units: Vec::new(),
+        };
+        for _ in 0..r.u16()? {
+            let length = usize::from(r.u16()?);
+            array.units.push(r.bytes(length)?.to_vec());
+        }
+        config.arrays.push(array);
+    }
+    Ok(config)
+}
+
+fn profile_tier_level(r: &mut BitReader, max_sub_layers_minus1: u32, sps: &mut Sps) -> Result<()> {
+    r.skip(3)?;
+    sps.profile_idc = r.bits(5)?;
+    r.skip(32 + 48)?;
+    sps.level_idc = r.bits(8)?;
+    let mut present = Vec::new();
+    for _ in 0..max_sub_layers_minus1 {
+        present.push((r.flag()?, r.flag()?));
+    }
+    if max_sub_layers_minus1 > 0 {
+        r.skip(2 * (8 - max_sub_layers_minus1 as usize))?;
+    }
+    for (profile_present, level_present) in present {
+        if profile_present {
+            r.skip(88)?;
+        }
+        if level_present {
+            r.skip(8)?;
+        }
+    }
+    Ok(())
+}
+
+fn scaling_list_data(r: &mut BitReader) -> Result<()> {
+    for size_id in 0..4 {
+        let step = if size_id == 3 { 3 } else { 1 };
+        for _ in (0..6).step_by(step) {
+            if !r.flag()? {
+                r.ue()?;
+            } else {
+                let coefficients = 64.min(1 << (4 + (size_id << 1)));
+                if size_id > 1 {
+                    r.se()?;
+                }
+                for _ in 0..coefficients {
+                    r.se()?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Skips st_ref_pic_set(idx) and returns its NumDeltaPocs.
+fn short_term_ref_pic_set(r: &mut BitReader, idx: usize, num_delta_pocs: &[u32]) -> Result<u32> {
+    if idx != 0 && r.flag()? {
+        r.skip(1)?;
+        r.ue()?;
+        let mut count = 0;
+        for _ in 0..=num_delta_pocs[idx - 1] {
+            let used_by_curr_pic = r.flag()?;
+            if used_by_curr_pic || r.flag()? {
+                count += 1;
+            }
+        }
+        return Ok(count);
+    }
+    let negative = r.ue()?;
+    let positive = r.ue()?;
+    // each count is bounded by sps_max_dec_pic_buffering, at most 16
+    if negative > 16 || positive > 16 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "too many pictures in st_ref_pic_set",
+        ));
+    }
+    for _ in 0..negative + positive {
+        r.ue()?;
+        r.skip(1)?;
+    }
+    Ok(negative + positive)
+}
+
+/// Decodes a sequence parameter set NAL unit, including its two byte header.
+/// Decoding stops after the VUI timing information.
+pub fn read_sps(nal: &[u8]) -> Result<Sps> {
+    let data = bits::rbsp(nal.get(2..).unwrap_or(&[]));
+    let mut r = BitReader::new(&data);
+    r.skip(4)?;
+    let max_sub_layers_minus1 = r.bits(3)?;
+    r.skip(1)?;
+    let mut sps = Sps {
+        max_sub_layers: max_sub_layers_minus1 + 1,
+        ..Default::default()
+    };
+    profile_tier_level(&mut r, max_sub_layers_minus1, &mut sps)?;
+    sps.id = r.ue()?;
+    sps.chroma_format_idc = r.ue()?;
+    let mut separate_colour_plane = false;
+    if sps.chroma_format_idc == 3 {
+        separate_colour_plane = r.flag()?;
+    }
+    sps.coded_width = r.ue()?;
+    sps.coded_height = r.ue()?;
+    if r.flag()? {
+        let (unit_x, unit_y) = match (separate_colour_plane, sps.chroma_format_idc) {
+            (false, 1) => (2, 2),
+            (false, 2) => (2, 1),
+            _ => (1, 1),
+        };
+        sps.conformance_window = (
+            bits::mul(r.ue()?, unit_x)?,
+            bits::mul(r.ue()?, unit_x)?,
+            bits::mul(r.ue()?, unit_y)?,
+            bits::mul(r.ue()?, unit_y)?,
+        );
+        let (left, right, top, bottom) = sps.conformance_window;
+        if u64::from(left) + u64::from(right) > u64::from(sps.coded_width)
+            || u64::from(top) + u64::from(bottom) > u64::from(sps.coded_height)
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "conformance window larger than the picture",
+            ));
+        }
+    }
+    sps.bit_depth_luma = r.ue_plus(8)?;
+    sps.bit_depth_chroma = r.ue_plus(8)?;
+    let log2_max_poc_lsb = r.ue_plus(4)?;
+    let ordering_info_present = r.flag()?;
+    let first = if ordering_info_present {
+        0
+    } else {
+        max_sub_layers_minus1
+    };
+    for _ in first..=max_sub_layers_minus1 {
+        r.ue()?;
+        sps.max_num_reorder_pics = r.ue()?;
+        r.ue()?;
+    }
+    for _ in 0..6 {
+        r.ue()?;
+    }
+    if r.flag()? && r.flag()? {
+        scaling_list_data(&mut r)?;
+    }
+    r.skip(2)?;
+    if r.flag()? {
+        r.skip(8)?;
+        r.ue()?;
+        r.ue()?;
+        r.skip(1)?;
+    }
+    let num_short_term_ref_pic_sets = r.ue()? as usize;
+    if num_short_term_ref_pic_sets > 64 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "num_short_term_ref_pic_sets exceeds 64",
+        ));
+    }
+    let mut num_delta_pocs = Vec::with_capacity(num_short_term_ref_pic_sets);
+    for idx in 0..num_short_term_ref_pic_sets {
+        let count = short_term_ref_pic_set(&mut r, idx, &num_delta_pocs)?;
+        num_delta_pocs.push(count);
+    }
+    if r.flag()? {
+        for _ in 0..r.ue()? {
+            r.skip(log2_max_poc_lsb as usize + 1)?;
+        }
+    }
+    r.skip(2)?;
+    if r.flag()? {
+        sps.vui = Some(avc::read_vui_header(&mut r)?);
+        if r.flag()? {
+            r.ue()?;
+            r.ue()?;
+        }
+        r.skip(3)?;
+        if r.flag()? {
+            for _ in 0..4 {
+                r.ue()?;
+            }
+        }
+        if r.flag()? {
+            sps.timing = Some((r.bits(32)?, r.bits(32)?));
+        }
+    }
+    Ok(sps)
+}
+
+/// Profile name of a general_profile_idc, per H.265 Annex A.
+pub fn profile_name(profile_idc: u32) -> &'static str {
+    match profile_idc {
+        1 => "Main",
+        2 => "Main 10",
+        3 => "Main Still Picture",
+        4 => "Format Range Extensions",
+        5 => "High Throughput",
+        6 => "Multiview Main",
+        7 => "Scalable Main",
+        9 => "Screen Content Coding Extensions",
+        _ => "Unknown",
+    }
+}
+
+fn nal_unit_type_name(nal_unit_type: u8) -> &'static str {
+    match nal_unit_type {
+        32 => "VPS",
+        33 => "SPS",
+        34 => "PPS",
+        39 => "PREFIX_SEI",
+        40 => "SUFFIX_SEI",
+        _ => "OTHER",
+    }
+}
+
+/// Prints the sample entry, codec and `[media.track.video.hevc]` sections of an
+/// `hvc1`/`hev1` sample entry.
+/// # Arguments
+/// * `buf` - Whole file contents
+/// * `entry` - Raw `hvc1`/`hev1` sample entry box
+/// * `codecs` - RFC 6381 codecs string of the track, if known
+pub fn print_report(buf: &[u8], entry: &Mp4Box, codecs: Option<&str>) {
+    if let Some((width, height)) = boxes::visual_entry_size(buf, entry) {
+        println!("[media.track.video.sample.entry]");
+        println!("width = {:?}", width);
+        println!("height = {:?}", height);
+    }
+    println!("[media.track.video.codec]");
+    println!("codec_name = \"HEVC\"");
+    println!("fourcc = \"{}\"", String::from_utf8_lossy(&entry.name));
+    if let Some(codecs) = codecs {
+        println!("codecs = \"{}\"", codecs);
+    }
+    println!("[media.track.video.hevc]");
+    let hvcc = boxes::visual_entry_children(buf, entry)
+        .into_iter()
+        .find(|b| b.is(b"hvcC"));
+    let config = match hvcc.map(|b| read_config(b.body(buf))) {
+        Some(Ok(config)) => config,
+        Some(Err(e)) => {
+            println!("error = \"{}\"", e);
+            return;
+        }
+        None => {
+            println!("error = \"hvcC box not found\"");
+            return;
+        }
+    };
+    println!("configuration_version = {:?}", config.version);
+    println!("profile_space = {:?}", config.profile_space);
+    println!(
+        "tier = \"{}\"",
+        if config.tier == 0 { "Main" } else { "High" }
+    );
+    println!(
+        "profile = \"{}\"",
+        profile_name(u32::from(config.profile_idc))
+    );
+    println!("profile_idc = {:?}", config.profile_idc);
+    println!(
+        "compatibility_flags = \"{:08X}\"",
+        config.compatibility_flags
+    );
+    println!("constraint_flags = \"{:012X}\"", config.constraint_flags);
+    println!("level = \"{}\"", format_level(u32::from(config.level_idc)));
+    println!("level_idc = {:?}", config.level_idc);
+    println!(
+        "min_spatial_segmentation = {:?}",
+        config.min_spatial_segmentation
+    );
+    println!(
+        "parallelism_type = \"{}\"",
+        match config.parallelism_type {
+            1 => "slice",
+            2 => "tile",
+            3 => "wavefront",
+            _ => "mixed or unknown",
+        }
+    );
+    println!("chroma_format_idc = {:?}", config.chroma_format_idc);
+    println!("bit_depth_luma = {:?}", config.bit_depth_luma);
+    println!("bit_depth_chroma = {:?}", config.bit_depth_chroma);
+    println!(
+        "avg_frame_rate = {:.3}",
+        f64::from(config.avg_frame_rate) / 256.0
+    );
+    println!("constant_frame_rate = {:?}", config.constant_frame_rate);
+    println!("temporal_layers = {:?}", config.temporal_layers);
+    println!("temporal_id_nested = {:?}", config.temporal_id_nested);
+    println!("nal_length_size = {:?}", config.nal_length_size);
+    for array in &config.arrays {
+        println!("[media.track.video.hevc.array]");
+        println!(
+            "nal_unit_type = \"{}\"",
+            nal_unit_type_name(array.nal_unit_type)
+        );
+        println!("nal_unit_type_id = {:?}", array.nal_unit_type);
+        println!("complete = {:?}", array.complete);
+        println!("count = {:?}", array.units.len());
+        let sizes: Vec<String> = array.units.iter().map(|u| u.len().to_string()).collect();
+        println!("bytes = [{}]", sizes.join(", "));
+    }
+    let sps_units = config
+        .arrays
+        .iter()
+        .filter(|a| a.nal_unit_type == 33)
+        .flat_map(|a| a.units.iter());
+    for nal in sps_units {
+        println!("[media.track.video.hevc.sps]");
+        let sps = match read_sps(nal) {
+            Ok(sps) => sps,
+            Err(e) => {
+                println!("error = \"{}\"", e);
+                continue;
+            }
+        };
+        println!("id = {:?}", sps.id);
+        println!("profile = \"{}\"", profile_name(sps.profile_idc));
+        println!("level = \"{}\"", format_level(sps.level_idc));
+        println!("max_sub_layers = {:?}", sps.max_sub_layers);
+        println!("chroma_format_idc = {:?}", sps.chroma_format_idc);
+        println!("bit_depth_luma = {:?}", sps.bit_depth_luma);
+        println!("bit_depth_chroma = {:?}", sps.bit_depth_chroma);
+        println!("coded_width = {:?}", sps.coded_width);
+        println!("coded_height = {:?}", sps.coded_height);
+        println!("width = {:?}", sps.width());
+        println!("height = {:?}", sps.height());
+        println!("max_num_reorder_pics = {:?}", sps.max_num_reorder_pics);
+        if let Some(vui) = sps.vui {
+            if let Some((width, height)) = vui.sample_aspect_ratio {
+                println!("sample_aspect_ratio = \"{}:{}\"", width, height);
+            }
+            if let Some(signal) = vui.video_signal {
+                println!("video_format = {:?}", signal.video_format);
+                println!("full_range = {:?}", signal.full_range);
+                if let Some(v) = signal.colour_primaries {
+                    println!("colour_primaries = {:?}", v);
+                }
+                if let Some(v) = signal.transfer_characteristics {
+                    println!("transfer_characteristics = {:?}", v);
+                }
+                if let Some(v) = signal.matrix_coefficients {
+                    println!("matrix_coefficients = {:?}", v);
+                }
+            }
+        }
+        if let Some((num_units_in_tick, time_scale)) = sps.timing {
+            println!("num_units_in_tick = {:?}", num_units_in_tick);
+            println!("time_scale = {:?}", time_scale);
+            if num_units_in_tick > 0 {
+                println!(
+                    "frame_rate = {:.3}",
+                    f64::from(time_scale) / f64::from(num_units_in_tick)
+                );
+            }
+        }
+    }
+}
+
+/// Level name of a general_level_idc, which is 30 times the level number.
+fn format_level(level_idc: u32) -> String {
+    let tenths = level_idc / 3;
+    if tenths.is_multiple_of(10) {
+        format!("{}", tenths / 10)
+    } else {
+        format!("{}.{}", tenths / 10, tenths % 10)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes SPS syntax elements for the tests.
+    #[derive(Default)]
+    struct BitWriter(Vec<bool>);
+
+    impl BitWriter {
+        fn bits(&mut self, n: u32, value: u32) -> &mut Self {
+            for i in (0..n).rev() {
+                self.0.push((value >> i) & 1 == 1);
+            }
+            self
+        }
+
+        fn ue(&mut self, value: u32) -> &mut Self {
+            let code = u64::from(value) + 1;
+            let len = 64 - code.leading_zeros();
+            self.bits(len - 1, 0);
+            for i in (0..len).rev() {
+                self.0.push((code >> i) & 1 == 1);
+            }
+            self
+        }
+
+        /// Bytes with rbsp trailing bits.
+        fn finish(&mut self) -> Vec<u8> {
+            self.0.push(true);
+            while !self.0.len().is_multiple_of(8) {
+                self.0.push(false);
+            }
+            self.0
+                .chunks(8)
+                .map(|c| c.iter().fold(0, |b, &bit| b << 1 | u8::from(bit)))
+                .collect()
+        }
+    }
+
+    /// A 1920x1080 Main profile SPS up to, not including,
+    /// num_short_term_ref_pic_sets.
+    fn sps_header(conformance_window: Option<[u32; 4]>) -> BitWriter {
+        let mut w = BitWriter::default();
+        w.bits(16, 0x4201).bits(4, 0).bits(3, 0).bits(1, 1);
+        w.bits(3, 0).bits(5, 1).bits(32, 0x6000_0000);
+        w.bits(32, 0x9000_0000).bits(16, 0).bits(8, 93);
+        w.ue(0).ue(1).ue(1920).ue(1088);
+        match conformance_window {
+            Some(window) => {
+                w.bits(1, 1);
+                for offset in window {
+                    w.ue(offset);
+                }
+            }
+            None => {
+                w.bits(1, 0);
+            }
+        }
+        w.ue(0).ue(0).ue(4).bits(1, 1).ue(4).ue(2).ue(0);
+        w.ue(0)
+            .ue(3)
+            .ue(0)
+            .ue(3)
+            .ue(0)
+            .ue(0)
+            .bits(1, 0)
+            .bits(2, 0)
+            .bits(1, 0);
+        w
+    }
+
+    #[test]
+    fn unit_hevc_sps() {
+        let mut w = sps_header(Some([0, 0, 0, 4]));
+        // one set with a single negative picture, no long term pictures, no VUI
+        w.ue(1).ue(1).ue(0).ue(0).bits(1, 1);
+        w.bits(1, 0).bits(2, 0).bits(1, 0);
+        let sps = read_sps(&w.finish()).unwrap();
+        assert_eq!((sps.width(), sps.height()), (1920, 1080));
+    }
+
+    #[test]
+    fn unit_hevc_sps_out_of_range() {
+        let err = |w: &mut BitWriter| read_sps(&w.finish()).unwrap_err().kind();
+        let mut w = sps_header(None);
+        w.ue(65);
+        assert_eq!(err(&mut w), ErrorKind::InvalidData);
+        let mut w = sps_header(None);
+        w.ue(1).ue(u32::MAX - 1).ue(u32::MAX - 1);
+        assert_eq!(err(&mut w), ErrorKind::InvalidData);
+        let mut w = sps_header(Some([u32::MAX / 2, u32::MAX / 2, 0, 0]));
+        assert_eq!(err(&mut w), ErrorKind::InvalidData);
+    }
+}
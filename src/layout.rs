@@ -0,0 +1,207 @@
+//! Chunk layout and interleaving report.
+//!
+//! Walks `stco`/`co64` and `stsc` of every track and orders the chunks by
+//! file offset to show how track data is interleaved in `mdat`.
+
+use crate::boxes;
+use crate::sample_table;
+use mp4parse::{MediaContext, TrackType};
+
+/// A chunk placed in the file.
+#[derive(Debug, Clone, Copy)]
+pub struct ChunkLayout {
+    /// track id from tkhd
+    pub track_id: u32,
+    /// index of the track in `moov`, unique even when track ids are not
+    pub track_index: usize,
+    /// file offset of the chunk
+    pub offset: u64,
+    /// chunk size in bytes
+    pub size: u64,
+    /// number of samples in the chunk
+    pub samples: usize,
+    /// decode time of the first sample in seconds
+    pub start: f64,
+    /// summed sample durations in seconds
+    pub duration: f64,
+}
+
+/// Per track chunk summary.
+#[derive(Debug, Clone)]
+pub struct TrackLayout {
+    /// track id from tkhd
+    pub track_id: u32,
+    /// track type name
+    pub track_type: &'static str,
+    /// number of chunks
+    pub chunks: usize,
+    /// longest chunk duration in seconds
+    pub max_chunk_duration: f64,
+    /// average chunk duration in seconds
+    pub avg_chunk_duration: f64,
+    /// largest chunk in bytes
+    pub max_chunk_size: u64,
+    /// true when any chunk lies outside every `mdat` box
+    pub outside_mdat: bool,
+}
+
+/// Layout report for the whole file.
+#[derive(Debug, Clone)]
+pub struct Layout {
+    /// per track summaries
+    pub tracks: Vec<TrackLayout>,
+    /// all chunks in file order
+    pub chunks: Vec<ChunkLayout>,
+    /// largest run of consecutive bytes holding a single track's chunks
+    pub max_interleave_bytes: u64,
+    /// largest lead, in seconds, of one track's data over another while reading in file order
+    pub max_interleave_seconds: f64,
+}
+
+fn track_type_name(track_type: &TrackType) -> &'static str {
+    match track_type {
+        TrackType::Video => "video",
+        TrackType::Audio => "audio",
+        TrackType::Picture => "picture",
+        TrackType::AuxiliaryVideo => "auxiliaryvideo",
+        TrackType::Metadata => "metadata",
+        TrackType::Unknown => "unknown",
+    }
+}
+
+/// Builds the chunk layout of every track.
+/// # Arguments
+/// * `buf` - Whole file contents
+/// * `context` - mp4parse context read from `buf`
+pub fn analyze(buf: &[u8], context: &MediaContext) -> Layout {
+    let mdats: Vec<(u64, u64)> = boxes::top_level(buf)
+        .iter()
+        .filter(|b| b.is(b"mdat"))
+        .map(|b| (b.body_offset() as u64, b.end() as u64))
+        .collect();
+
+    let mut tracks = Vec::new();
+    let mut chunks = Vec::new();
+    for (track_index, track) in context.tracks.iter().enumerate() {
+        let track_id = track.track_id.unwrap_or(0);
+        let timescale = track.timescale.map_or(1, |t| t.0.max(1)) as f64;
+        let sizes = sample_table::sample_sizes(track);
+        let times = sample_table::decode_times(track);
+        let track_chunks = sample_table::chunks(track);
+        let mut summary = TrackLayout {
+            track_id,
+            track_type: track_type_name(&track.track_type),
+            chunks: track_chunks.len(),
+            max_chunk_duration: 0.0,
+            avg_chunk_duration: 0.0,
+            max_chunk_size: 0,
+            outside_mdat: false,
+        };
+        let mut total_duration = 0.0;
+        for chunk in track_chunks {
+            let last = (chunk.first_sample + chunk.samples).min(sizes.len());
+            let first = chunk.first_sample.min(last);
+            let size: u64 = sizes[first..last].iter().map(|&s| u64::from(s)).sum();
+            let start = times.get(first).copied().unwrap_or(0);
+            let end = match times.get(last) {
+                Some(&t) => t,
+                None => track.duration.map_or(start, |d| d.0.max(start)),
+            };
+            let duration = (end - start) as f64 / timescale;
+            if !mdats
+                .iter()
+                .any(|&(s, e)| chunk.offset >= s && chunk.offset + size <= e)
+            {
+                summary.outside_mdat = true;
+            }
+            summary.max_chunk_duration = summary.max_chunk_duration.max(duration);
+            summary.max_chunk_size = summary.max_chunk_size.max(size);
+            total_duration += duration;
+            chunks.push(ChunkLayout {
+                track_id,
+                track_index,
+                offset: chunk.offset,
+                size,
+                samples: chunk.samples,
+                start: start as f64 / timescale,
+                duration,
+            });
+        }
+        if summary.chunks > 0 {
+            summary.avg_chunk_duration = total_duration / summary.chunks as f64;
+        }
+        tracks.push(summary);
+    }
+    chunks.sort_by_key(|c| c.offset);
+
+    let mut max_interleave_bytes = 0;
+    let mut run_track = None;
+    let mut run_bytes = 0;
+    for c in &chunks {
+        if run_track != Some(c.track_index) {
+            run_track = Some(c.track_index);
+            run_bytes = 0;
+        }
+        run_bytes += c.size;
+        max_interleave_bytes = max_interleave_bytes.max(run_bytes);
+    }
+
+    // Time buffered per track after reading each chunk; only tracks that
+    // still have data ahead of the read position are compared.
+    let mut max_interleave_seconds: f64 = 0.0;
+    let mut buffered: Vec<(f64, usize)> = tracks.iter().map(|t| (0.0, t.chunks)).collect();
+    for c in &chunks {
+        let b = &mut buffered[c.track_index];
+        b.0 = c.start + c.duration;
+        b.1 -= 1;
+        let pending: Vec<f64> = buffered.iter().filter(|b| b.1 > 0).map(|b| b.0).collect();
+        if pending.len() > 1 {
+            let lead = pending.iter().cloned().fold(f64::MIN, f64::max)
+                - pending.iter().cloned().fold(f64::MAX, f64::min);
+            max_interleave_seconds = max_interleave_seconds.max(lead);
+        }
+    }
+
+    Layout {
+        tracks,
+        chunks,
+        max_interleave_bytes,
+        max_interleave_seconds,
+    }
+}
+
+/// Prints the `[media.layout]` section.
+pub fn print_report(layout: &Layout) {
+    println!("[media.layout]");
+    println!("chunk_count = {:?}", layout.chunks.len());
+    println!("max_interleave_bytes = {:?}", layout.max_interleave_bytes);
+    println!(
+        "max_interleave_seconds = {:.6}",
+        layout.max_interleave_seconds
+    );
+    let outside: Vec<u32> = layout
+        .tracks
+        .iter()
+        .filter(|t| t.outside_mdat)
+        .map(|t| t.track_id)
+        .collect();
+    println!("tracks_outside_mdat = {:?}", outside);
+    println!("chunks = [");
+    for c in &layout.chunks {
+        println!(
+            "  {{ track_id = {}, offset = {}, size = {}, samples = {}, start = {:.6}, duration = {:.6} }},",
+            c.track_id, c.offset, c.size, c.samples, c.start, c.duration
+        );
+    }
+    println!("]");
+    for t in &layout.tracks {
+        println!("[media.layout.track]");
+        println!("track_id = {:?}", t.track_id);
+        println!("type = \"{}\"", t.track_type);
+        println!("chunks = {:?}", t.chunks);
+        println!("max_chunk_duration = {:.6}", t.max_chunk_duration);
+        println!("avg_chunk_duration = {:.6}", t.avg_chunk_duration);
+        println!("max_chunk_size = {:?}", t.max_chunk_size);
+        println!("outside_mdat = {:?}", t.outside_mdat);
+    }
+}
@@ -26,7 +26,6 @@ use mp4parse::SampleEntry;
 use mp4parse::TrackType;
 use mp4parse::VideoCodecSpecific;
 use no_color::is_no_color;
-use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
 use std::fmt::Debug;
@@ -34,10 +33,89 @@ use std::fmt::Formatter;
 use std::fs;
 use std::fs::File;
 use std::io::{Cursor, Read};
+use std::path::Path;
+
+mod aac;
+mod alac;
+mod av1;
+mod avc;
+mod bits;
+mod boxes;
+mod capture;
+mod chapters;
+mod check;
+mod cmaf;
+mod codecs;
+mod colour;
+mod cover;
+mod dolby;
+mod dovi;
+mod faststart;
+mod flac;
+mod fragment;
+mod hevc;
+mod layout;
+mod manifest;
+mod opus;
+mod sample_table;
+mod segments;
+mod sidx;
+mod spatial;
+mod sync;
+mod tags;
+mod vpx;
 
 /// MEDIAFILE Argument constant
 pub const ARG_MEDIAFILE: &str = "MEDIAFILE";
 
+/// av-sync Argument constant
+pub const ARG_AV_SYNC: &str = "av-sync";
+
+/// av-sync-tolerance Argument constant, in milliseconds
+pub const ARG_AV_SYNC_TOLERANCE: &str = "av-sync-tolerance";
+
+/// layout Argument constant
+pub const ARG_LAYOUT: &str = "layout";
+
+/// assert-faststart Argument constant
+pub const ARG_ASSERT_FASTSTART: &str = "assert-faststart";
+
+/// check Argument constant
+pub const ARG_CHECK: &str = "check";
+
+/// export-chapters Argument constant
+pub const ARG_EXPORT_CHAPTERS: &str = "export-chapters";
+
+/// extract-cover Argument constant
+pub const ARG_EXTRACT_COVER: &str = "extract-cover";
+
+/// init Argument constant
+pub const ARG_INIT: &str = "init";
+
+/// faststart Subcommand constant
+pub const CMD_FASTSTART: &str = "faststart";
+
+/// mime Argument constant
+pub const ARG_MIME: &str = "mime";
+
+/// manifest Subcommand constant
+pub const CMD_MANIFEST: &str = "manifest";
+
+/// hls Argument constant
+pub const ARG_HLS: &str = "hls";
+
+/// dash Argument constant
+pub const ARG_DASH: &str = "dash";
+
+/// FILE Argument constant
+pub const ARG_FILE: &str = "FILE";
+
+/// INPUT Argument constant
+pub const ARG_INPUT: &str = "INPUT";
+
+/// OUTPUT Argument constant
+pub const ARG_OUTPUT: &str = "OUTPUT";
+
 /// Media struct which holds file metadata
 pub struct Media {
     /// filename
@@ -86,6 +164,26 @@ pub fn run(matches: ArgMatches) -> Result<(), Box<dyn Error>> {
     if is_no_color() {
         colorize_outout = false;
     }
+    match matches.subcommand() {
+        Some((CMD_FASTSTART, sub_matches)) => return run_faststart(sub_matches),
+        Some((CMD_MANIFEST, sub_matches)) => return run_manifest(sub_matches),
+        _ => {}
+    }
+    let files: Vec<&String> = matches
+        .get_many::<String>(ARG_MEDIAFILE)
+        .map(|v| v.collect())
+        .unwrap_or_default();
+    if let Some(init) = matches.get_one::<String>(ARG_INIT) {
+        return run_segments(init, &files);
+    }
+    if files.len() > 1 {
+        return Err("multiple MEDIAFILE arguments require --init".into());
+    }
+    if matches.get_flag(ARG_MIME) {
+        if let Some(file) = files.first() {
+            return run_mime(file);
+        }
+    }
     if let Some(file) = matches.get_one::<String>(ARG_MEDIAFILE).map(|s| s.as_str()) {
         println!("[media]");
         println!("uri = \"{}\"", file);
@@ -154,20 +252,27 @@ pub fn run(matches: ArgMatches) -> Result<(), Box<dyn Error>> {
                 "\"error: not supported on this platform.\""
             );
         }
-        let mut c = Cursor::new(buf);
+        let mut c = Cursor::new(&buf);
         let context = read_mp4(&mut c).expect("read_mp4 failed");
-        for track in context.tracks {
+        let faststart = faststart::detect(&buf, &context);
+        faststart::print_fields(&faststart);
+        let traks = boxes::traks(&buf);
+        for (i, track) in context.tracks.iter().enumerate() {
             match track.track_type {
                 // see https://docs.rs/mp4parse/latest/mp4parse/struct.Track.html
                 TrackType::Video => {
                     println!("[media.track.video]");
                     println!("track_id = {:?}", track.track_id.unwrap());
                     println!("duration = {:?}", track.duration.unwrap());
-                    println!("empty_duration = \"{:?}\"", track.empty_duration.unwrap());
-                    println!("media_time = \"{:?}\"", track.media_time.unwrap()); // 1 = 64 bit creation and modification times. 0 = 64 bit creation and modification times.
+                    if let Some(empty_duration) = track.empty_duration {
+                        println!("empty_duration = \"{:?}\"", empty_duration);
+                    }
+                    if let Some(media_time) = track.media_time {
+                        println!("media_time = \"{:?}\"", media_time); // 1 = 64 bit creation and modification times. 0 = 64 bit creation and modification times.
+                    }
                     println!("timescale = \"{:?}\"", track.timescale.unwrap());
 
-                    let thb = track.tkhd.unwrap(); // TrackHeaderBox
+                    let thb = track.tkhd.as_ref().unwrap(); // TrackHeaderBox
                     println!("[media.track.video.header]");
                     println!("disabled = {:?}", thb.disabled);
                     println!("duration = {:?}", thb.duration);
@@ -176,53 +281,31 @@ pub fn run(matches: ArgMatches) -> Result<(), Box<dyn Error>> {
 
                     let stsd = track
                         .stsd
+                        .as_ref()
                         .expect("TrackType::Video missing SampleDescriptionBox");
-                    let v = match stsd
-                        .descriptions
-                        .first()
-                        .expect("TrackType::Video missing SampleEntry")
-                    {
-                        SampleEntry::Video(v) => v,
-                        _ => panic!("TrackType::Video missing VideoSampleEntry"),
-                    };
-                    println!("[media.track.video.sample.entry]");
-                    println!("width = {:?}", v.width);
-                    println!("height = {:?}", v.height);
-
-                    let mut vcsd = HashMap::new(); // VideoCodecSpecific data
-                    let codec = match v.codec_specific {
-                        VideoCodecSpecific::AV1Config(ref _av1c) => "AV1",
-                        VideoCodecSpecific::AVCConfig(ref avc) => {
-                            // vcsd.insert(String::from("avc.bytes_length"), avc.len());
-                            "AVC"
-                        }
-                        VideoCodecSpecific::VPxConfig(ref vpx) => {
-                            vcsd.insert(String::from("vpx.bit_depth"), vpx.bit_depth);
-                            vcsd.insert(String::from("vpx.colour_primaries"), vpx.colour_primaries);
-                            vcsd.insert(
-                                String::from("vpx.chroma_subsampling"),
-                                vpx.chroma_subsampling,
-                            );
-                            "VPx"
-                        }
-                        VideoCodecSpecific::ESDSConfig(ref mp4v) => "MP4V",
-                        VideoCodecSpecific::H263Config(ref _h263) => "H263",
-                    };
-                    println!("[media.track.video.codec]");
-                    println!("codec_name = \"{}\"", codec);
-                    for (key, value) in &vcsd {
-                        println!("{} = {:?}", key, value);
+                    let raw_entries = traks
+                        .get(i)
+                        .map_or_else(Vec::new, |t| boxes::sample_entries(&buf, t));
+                    let ranges = sample_table::description_ranges(track);
+                    for (index, entry) in stsd.descriptions.iter().enumerate() {
+                        let raw = raw_entries.get(index);
+                        print_sample_description("video", &buf, index, raw, &ranges);
+                        print_video_entry(&buf, raw, entry);
                     }
                 }
                 TrackType::Audio => {
                     println!("[media.track.audio]");
                     println!("track_id = {:?}", track.track_id.unwrap());
                     println!("duration = \"{:?}\"", track.duration.unwrap());
-                    println!("empty_duration = \"{:?}\"", track.empty_duration.unwrap());
-                    println!("media_time = \"{:?}\"", track.media_time.unwrap());
+                    if let Some(empty_duration) = track.empty_duration {
+                        println!("empty_duration = \"{:?}\"", empty_duration);
+                    }
+                    if let Some(media_time) = track.media_time {
+                        println!("media_time = \"{:?}\"", media_time);
+                    }
                     println!("timescale = \"{:?}\"", track.timescale.unwrap());
 
-                    let thb = track.tkhd.unwrap();
+                    let thb = track.tkhd.as_ref().unwrap();
                     println!("[media.track.audio.header]");
                     println!("disabled = {:?}", thb.disabled);
                     println!("duration = {:?}", thb.duration);
@@ -231,61 +314,16 @@ pub fn run(matches: ArgMatches) -> Result<(), Box<dyn Error>> {
 
                     let stsd = track
                         .stsd
+                        .as_ref()
                         .expect("TrackType::Audio missing SampleDescriptionBox");
-                    let a = match stsd
-                        .descriptions
-                        .first()
-                        .expect("TrackType::Audio missing SampleEntry")
-                    {
-                        SampleEntry::Audio(a) => a,
-                        _ => panic!("TrackType::Audio missing AudioSampleEntry"),
-                    };
-
-                    println!("[media.track.audio.sample.entry]");
-                    println!("channelcount = {:?}", a.channelcount);
-                    println!("samplesize = {:?}", a.samplesize);
-                    println!("samplerate = {:?}", a.samplerate);
-
-                    let mut acsd = HashMap::new(); // AudioCodecSpecific data
-                    let codec = match &a.codec_specific {
-                        AudioCodecSpecific::ES_Descriptor(esds) => {
-                            acsd.insert(
-                                String::from("esds.audio_sample_rate"),
-                                esds.audio_sample_rate.unwrap(),
-                            );
-                            acsd.insert(
-                                String::from("esds.audio_object_type"),
-                                esds.audio_object_type.unwrap() as u32,
-                            );
-                            "ES"
-                        }
-                        AudioCodecSpecific::FLACSpecificBox(flac) => {
-                            acsd.insert(
-                                String::from("flac.blocks[0].block_type"),
-                                flac.blocks[0].block_type as u32,
-                            );
-                            acsd.insert(
-                                String::from("flac.blocks[0].data.len()"),
-                                flac.blocks[0].data.len() as u32,
-                            );
-                            "FLAC"
-                        }
-                        AudioCodecSpecific::OpusSpecificBox(opus) => {
-                            acsd.insert(String::from("opus.version"), opus.version as u32);
-                            "Opus"
-                        }
-                        AudioCodecSpecific::ALACSpecificBox(alac) => {
-                            acsd.insert(String::from("alac.data.len()"), alac.data.len() as u32);
-                            "ALAC"
-                        }
-                        AudioCodecSpecific::MP3 => "MP3",
-                        AudioCodecSpecific::LPCM => "LPCM",
-                    };
-
-                    println!("[media.track.audio.codec]");
-                    println!("codec_name = \"{}\"", codec);
-                    for (key, value) in &acsd {
-                        println!("{} = {:?}", key, value);
+                    let raw_entries = traks
+                        .get(i)
+                        .map_or_else(Vec::new, |t| boxes::sample_entries(&buf, t));
+                    let ranges = sample_table::description_ranges(track);
+                    for (index, entry) in stsd.descriptions.iter().enumerate() {
+                        let raw = raw_entries.get(index);
+                        print_sample_description("audio", &buf, index, raw, &ranges);
+                        print_audio_entry(&buf, raw, entry);
                     }
                 }
                 TrackType::Picture => {
@@ -304,10 +342,17 @@ pub fn run(matches: ArgMatches) -> Result<(), Box<dyn Error>> {
                 }
                 TrackType::Metadata => {
                     println!("[media.track.metadata]");
-                    println!(
-                        "error = {:?}",
-                        "TrackType::Metadata found, but not supported by this application."
-                    );
+                    if let Some(track_id) = track.track_id {
+                        println!("track_id = {:?}", track_id);
+                    }
+                    // mp4parse does not read metadata sample entries
+                    let raw_entries = traks
+                        .get(i)
+                        .map_or_else(Vec::new, |t| boxes::sample_entries(&buf, t));
+                    let ranges = sample_table::description_ranges(track);
+                    for (index, raw) in raw_entries.iter().enumerate() {
+                        print_sample_description("metadata", &buf, index, Some(raw), &ranges);
+                    }
                 }
                 TrackType::Unknown => {
                     println!("[media.track.unknown]");
@@ -315,7 +360,383 @@ pub fn run(matches: ArgMatches) -> Result<(), Box<dyn Error>> {
                 }
             }
         }
+        let tags = match tags::read_tags(&buf) {
+            Ok(tags) => {
+                let cover_paths = match matches.get_one::<String>(ARG_EXTRACT_COVER) {
+                    Some(dir) => cover::extract(&buf, &tags::covers(&tags), Path::new(dir))?,
+                    None => Vec::new(),
+                };
+                tags::print_report(&buf, &tags, &cover_paths);
+                tags
+            }
+            Err(e) => {
+                println!("[media.tags]");
+                println!("error = \"{}\"", e);
+                Vec::new()
+            }
+        };
+        capture::print_report(&buf, &tags);
+        match chapters::read_chapters(&buf, &context) {
+            Ok(chapters) => {
+                chapters::print_report(&chapters);
+                if let Some(path) = matches.get_one::<String>(ARG_EXPORT_CHAPTERS) {
+                    chapters::export(&chapters, path)?;
+                }
+            }
+            Err(e) => {
+                println!("[media.chapters]");
+                println!("error = \"{}\"", e);
+                if matches.contains_id(ARG_EXPORT_CHAPTERS) {
+                    return Err(e.into());
+                }
+            }
+        }
+        let movie_fragments = fragment::read_trex(&buf).and_then(|trexs| {
+            let fragments = fragment::read_fragments(&buf, &trexs)?;
+            Ok((trexs, fragments))
+        });
+        let (trexs, fragments) = match movie_fragments {
+            Ok((trexs, fragments)) => {
+                if !fragments.is_empty() {
+                    fragment::print_report(&context, &trexs, &fragments);
+                }
+                (trexs, fragments)
+            }
+            Err(e) => {
+                println!("[media.fragments]");
+                println!("error = \"{}\"", e);
+                (Vec::new(), Vec::new())
+            }
+        };
+        let indexes = sidx::read_sidxs(&buf)?;
+        let mfra = sidx::read_mfra(&buf)?;
+        if !indexes.is_empty() || mfra.is_some() {
+            sidx::print_report(&indexes, mfra.as_ref());
+        }
+        let checks: Vec<&String> = matches
+            .get_many::<String>(ARG_CHECK)
+            .map(|v| v.collect())
+            .unwrap_or_default();
+        let mut failed_checks = Vec::new();
+        if checks.iter().any(|c| c.as_str() == "cmaf") {
+            let violations = cmaf::check(&buf, &context, &trexs, &fragments);
+            check::print_report("media.check.cmaf", &violations);
+            if !violations.is_empty() {
+                failed_checks.push(format!("cmaf ({} violations)", violations.len()));
+            }
+        }
+        if checks.iter().any(|c| c.as_str() == "sidx") {
+            let violations = sidx::check(&buf, &context, &fragments, &indexes, mfra.as_ref());
+            check::print_report("media.check.sidx", &violations);
+            if !violations.is_empty() {
+                failed_checks.push(format!("sidx ({} violations)", violations.len()));
+            }
+        }
+        if matches.get_flag(ARG_LAYOUT) {
+            layout::print_report(&layout::analyze(&buf, &context));
+        }
+        if matches.get_flag(ARG_ASSERT_FASTSTART) && !faststart.is_faststart() {
+            failed_checks.push("faststart (moov is not before mdat)".to_string());
+        }
+        if matches.get_flag(ARG_AV_SYNC) {
+            let tolerance = matches
+                .get_one::<f64>(ARG_AV_SYNC_TOLERANCE)
+                .copied()
+                .unwrap_or(40.0)
+                / 1000.0;
+            let report = sync::analyze(&buf, &context)?;
+            sync::print_report(&report, tolerance);
+            if !report.within_tolerance(tolerance) {
+                failed_checks.push(format!(
+                    "av-sync (exceeds tolerance of {} ms)",
+                    tolerance * 1000.0
+                ));
+            }
+        }
+        if !failed_checks.is_empty() {
+            return Err(format!("check failed: {}", failed_checks.join(", ")).into());
+        }
+    }
+    println!();
+    Ok(())
+}
+
+/// Print the `[media.track.<kind>.sample.description]` section of the sample
+/// entry at `index` with the sample ranges `stsc` assigns to it.
+/// # Arguments
+/// * `kind` - Track kind, `video` or `audio`
+/// * `buf` - Whole file contents
+/// * `index` - Zero based index of the entry in `stsd`
+/// * `raw` - Raw sample entry box
+/// * `ranges` - Sample description ranges of the track
+fn print_sample_description(
+    kind: &str,
+    buf: &[u8],
+    index: usize,
+    raw: Option<&boxes::Mp4Box>,
+    ranges: &[sample_table::DescriptionRange],
+) {
+    println!("[media.track.{}.sample.description]", kind);
+    println!("index = {:?}", index + 1);
+    if let Some(raw) = raw {
+        println!("fourcc = \"{}\"", String::from_utf8_lossy(&raw.name));
+        if let Some(data_reference_index) = boxes::data_reference_index(buf, raw) {
+            println!("data_reference_index = {:?}", data_reference_index);
+        }
+    }
+    let ranges: Vec<[usize; 2]> = ranges
+        .iter()
+        .filter(|r| r.sample_description_index as usize == index + 1)
+        .map(|r| [r.first_sample, r.last_sample])
+        .collect();
+    println!(
+        "sample_count = {:?}",
+        ranges.iter().map(|r| r[1] + 1 - r[0]).sum::<usize>()
+    );
+    println!("sample_ranges = {:?}", ranges);
+}
+
+/// Print the sample entry and codec configuration sections of a video sample entry.
+fn print_video_entry(buf: &[u8], raw: Option<&boxes::Mp4Box>, entry: &SampleEntry) {
+    let codecs_string = raw.and_then(|raw| codecs::entry_codecs(buf, raw, Some(entry)));
+    let v = match entry {
+        SampleEntry::Video(v) => v,
+        _ => {
+            match raw {
+                Some(raw)
+                    if raw.is(b"hvc1") || raw.is(b"hev1") || raw.is(b"dvh1") || raw.is(b"dvhe") =>
+                {
+                    hevc::print_report(buf, raw, codecs_string.as_deref());
+                }
+                Some(raw) if raw.is(b"dav1") || raw.is(b"dva1") || raw.is(b"dvav") => {
+                    println!("[media.track.video.codec]");
+                    println!("codec_name = \"Dolby Vision\"");
+                    println!("fourcc = \"{}\"", String::from_utf8_lossy(&raw.name));
+                    if let Some(codecs_string) = &codecs_string {
+                        println!("codecs = \"{}\"", codecs_string);
+                    }
+                }
+                _ => {
+                    println!("[media.track.video.codec]");
+                    println!("codec_name = \"Unsupported\"");
+                    if let Some(raw) = raw {
+                        println!("fourcc = \"{}\"", String::from_utf8_lossy(&raw.name));
+                    }
+                    println!(
+                        "error = {:?}",
+                        "Sample entry found, but not supported by this application."
+                    );
+                }
+            }
+            if let Some(raw) = raw {
+                dovi::print_report(buf, raw);
+                colour::print_report(buf, raw);
+                spatial::print_report(buf, raw);
+            }
+            return;
+        }
+    };
+    println!("[media.track.video.sample.entry]");
+    println!("width = {:?}", v.width);
+    println!("height = {:?}", v.height);
+
+    let mut avc_config = None;
+    let mut av1_config = None;
+    let codec = match v.codec_specific {
+        VideoCodecSpecific::AV1Config(ref av1c) => {
+            av1_config = Some(av1c);
+            "AV1"
+        }
+        VideoCodecSpecific::AVCConfig(ref avc) => {
+            avc_config = Some(avc::read_config(avc));
+            "AVC"
+        }
+        VideoCodecSpecific::VPxConfig(_) => "VPx",
+        VideoCodecSpecific::ESDSConfig(ref _mp4v) => "MP4V",
+        VideoCodecSpecific::H263Config(ref _h263) => "H263",
+    };
+    println!("[media.track.video.codec]");
+    println!("codec_name = \"{}\"", codec);
+    if let Some(codecs_string) = &codecs_string {
+        println!("codecs = \"{}\"", codecs_string);
+    }
+    match avc_config {
+        Some(Ok(config)) => avc::print_report(&config),
+        Some(Err(e)) => {
+            println!("[media.track.video.avc]");
+            println!("error = \"{}\"", e);
+        }
+        None => {}
+    }
+    if let Some(av1c) = av1_config {
+        av1::print_report(av1c);
+    }
+    if let VideoCodecSpecific::VPxConfig(_) = v.codec_specific {
+        // profile, level, transfer and matrix are private in mp4parse
+        let vpcc = raw.and_then(|raw| {
+            boxes::visual_entry_children(buf, raw)
+                .into_iter()
+                .find(|b| b.is(b"vpcC"))
+        });
+        if let Some(vpcc) = vpcc {
+            vpx::print_report(vpcc.body(buf));
+        }
+    }
+    if let Some(raw) = raw {
+        dovi::print_report(buf, raw);
+        colour::print_report(buf, raw);
+        spatial::print_report(buf, raw);
+    }
+}
+
+/// Print the sample entry and codec configuration sections of an audio sample entry.
+fn print_audio_entry(buf: &[u8], raw: Option<&boxes::Mp4Box>, entry: &SampleEntry) {
+    let codecs_string = raw.and_then(|raw| codecs::entry_codecs(buf, raw, Some(entry)));
+    let a = match entry {
+        SampleEntry::Audio(a) => a,
+        _ => {
+            match raw {
+                Some(raw) if raw.is(b"ac-3") || raw.is(b"ec-3") || raw.is(b"ac-4") => {
+                    dolby::print_report(buf, raw, codecs_string.as_deref());
+                }
+                _ => {
+                    println!("[media.track.audio.codec]");
+                    println!("codec_name = \"Unsupported\"");
+                    if let Some(raw) = raw {
+                        println!("fourcc = \"{}\"", String::from_utf8_lossy(&raw.name));
+                    }
+                    println!(
+                        "error = {:?}",
+                        "Sample entry found, but not supported by this application."
+                    );
+                }
+            }
+            return;
+        }
+    };
+
+    println!("[media.track.audio.sample.entry]");
+    println!("channelcount = {:?}", a.channelcount);
+    println!("samplesize = {:?}", a.samplesize);
+    println!("samplerate = {:?}", a.samplerate);
+
+    let codec = match &a.codec_specific {
+        AudioCodecSpecific::ES_Descriptor(_) => "ES",
+        AudioCodecSpecific::FLACSpecificBox(_) => "FLAC",
+        AudioCodecSpecific::OpusSpecificBox(_) => "Opus",
+        AudioCodecSpecific::ALACSpecificBox(_) => "ALAC",
+        AudioCodecSpecific::MP3 => "MP3",
+        AudioCodecSpecific::LPCM => "LPCM",
+    };
+
+    println!("[media.track.audio.codec]");
+    println!("codec_name = \"{}\"", codec);
+    if let Some(codecs_string) = &codecs_string {
+        println!("codecs = \"{}\"", codecs_string);
+    }
+    match &a.codec_specific {
+        AudioCodecSpecific::ES_Descriptor(esds) => {
+            aac::print_report(&esds.codec_esds, &esds.decoder_specific_data)
+        }
+        AudioCodecSpecific::FLACSpecificBox(dfla) => flac::print_report(dfla),
+        AudioCodecSpecific::OpusSpecificBox(dops) => opus::print_report(dops),
+        AudioCodecSpecific::ALACSpecificBox(alac) => alac::print_report(&alac.data),
+        AudioCodecSpecific::MP3 | AudioCodecSpecific::LPCM => {}
+    }
+}
+
+/// Rewrite an mp4 file with moov placed before mdat.
+/// # Arguments
+/// * `matches` - Argument matches of the faststart subcommand
+pub fn run_faststart(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let input = matches
+        .get_one::<String>(ARG_INPUT)
+        .ok_or("missing INPUT argument")?;
+    let output = matches
+        .get_one::<String>(ARG_OUTPUT)
+        .ok_or("missing OUTPUT argument")?;
+    let buf = fs::read(input)?;
+    let rewrite = faststart::rewrite(&buf)?;
+    fs::write(output, &rewrite.data)?;
+    println!("[faststart]");
+    println!("input = \"{}\"", input);
+    println!("output = \"{}\"", output);
+    println!("already_faststart = {:?}", rewrite.already_faststart);
+    println!("moov_offset = {:?}", rewrite.moov_offset);
+    println!("moov_size = {:?}", rewrite.moov_size);
+    println!("co64_upgrades = {:?}", rewrite.co64_upgrades);
+    println!("verified_chunks = {:?}", rewrite.verified_chunks);
+    println!();
+    Ok(())
+}
+
+/// Print the MIME type of an mp4 file with an RFC 6381 codecs parameter.
+/// # Arguments
+/// * `file` - mp4 file path
+pub fn run_mime(file: &str) -> Result<(), Box<dyn Error>> {
+    let buf = fs::read(file)?;
+    let context = read_mp4(&mut Cursor::new(&buf))?;
+    let traks = boxes::traks(&buf);
+    let mut codecs = Vec::new();
+    for (track, trak) in context.tracks.iter().zip(traks.iter()) {
+        // chapter text, timecode and metadata tracks have no codecs parameter
+        if !matches!(track.track_type, TrackType::Video | TrackType::Audio) {
+            continue;
+        }
+        let entry = track.stsd.as_ref().and_then(|s| s.descriptions.first());
+        let codec = codecs::codecs(&buf, trak, entry)
+            .ok_or_else(|| format!("no codecs string for track {:?}", track.track_id))?;
+        codecs.push(codec);
+    }
+    let has_video = context
+        .tracks
+        .iter()
+        .any(|t| t.track_type == TrackType::Video);
+    println!("{}", codecs::mime(has_video, &codecs));
+    Ok(())
+}
+
+/// Print an HLS media playlist or DASH MPD for a fragmented mp4 file.
+/// # Arguments
+/// * `matches` - Argument matches of the manifest subcommand
+pub fn run_manifest(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let file = matches
+        .get_one::<String>(ARG_FILE)
+        .ok_or("missing FILE argument")?;
+    let buf = fs::read(file)?;
+    let context = read_mp4(&mut Cursor::new(&buf))?;
+    let trexs = fragment::read_trex(&buf)?;
+    let fragments = fragment::read_fragments(&buf, &trexs)?;
+    let indexes = sidx::read_sidxs(&buf)?;
+    let uri = Path::new(file)
+        .file_name()
+        .map_or(file.clone(), |name| name.to_string_lossy().to_string());
+    let presentation = manifest::analyze(&buf, &uri, &context, &fragments, &indexes)?;
+    if matches.get_flag(ARG_DASH) {
+        print!("{}", manifest::dash(&presentation));
+    } else {
+        print!("{}", manifest::hls(&presentation));
+    }
+    Ok(())
+}
+
+/// Inspect media segments against the moov of their init segment.
+/// # Arguments
+/// * `init` - Init segment path
+/// * `files` - Media segment paths, in playback order
+pub fn run_segments(init: &str, files: &[&String]) -> Result<(), Box<dyn Error>> {
+    let buf = fs::read(init)?;
+    let context = read_mp4(&mut Cursor::new(&buf))?;
+    let trexs = fragment::read_trex(&buf)?;
+    let mut segments = Vec::with_capacity(files.len());
+    for file in files {
+        segments.push((file.to_string(), fs::read(file)?));
     }
+    println!("[media]");
+    println!("init = \"{}\"", init);
+    println!("bytes = {}", buf.len());
+    println!("track_count = {:?}", context.tracks.len());
+    segments::print_report(&segments::analyze(&context, &trexs, &segments)?);
     println!();
     Ok(())
 }
@@ -1,5 +1,5 @@
 extern crate clap;
-use clap::{Arg, Command};
+use clap::{value_parser, Arg, ArgAction, ArgGroup, Command};
 use std::process;
 
 /// Central application entry point.
@@ -7,12 +7,125 @@ fn main() {
     let matches = Command::new(env!("CARGO_PKG_NAME"))
         .version(env!("CARGO_PKG_VERSION"))
         .about(env!("CARGO_PKG_DESCRIPTION")) // CARGO_PKG_HOMEPAGE
+        .args_conflicts_with_subcommands(true)
+        .subcommand_negates_reqs(true)
         .arg(
             Arg::new(mpn::ARG_MEDIAFILE)
-                .help("Pass a valid mp4 file path as an argument for inspection")
+                .help("Pass a valid mp4 file path as an argument for inspection, or media segments with --init")
                 .required(true)
+                .num_args(1..)
                 .index(1),
         )
+        .arg(
+            Arg::new(mpn::ARG_INIT)
+                .long(mpn::ARG_INIT)
+                .value_name("INIT")
+                .help("Init segment whose moov is used to inspect MEDIAFILE media segments")
+                .conflicts_with_all([
+                    mpn::ARG_MIME,
+                    mpn::ARG_CHECK,
+                    mpn::ARG_LAYOUT,
+                    mpn::ARG_AV_SYNC,
+                    mpn::ARG_ASSERT_FASTSTART,
+                    mpn::ARG_EXTRACT_COVER,
+                    mpn::ARG_EXPORT_CHAPTERS,
+                ]),
+        )
+        .arg(
+            Arg::new(mpn::ARG_MIME)
+                .long(mpn::ARG_MIME)
+                .help("Print only the MIME type with an RFC 6381 codecs parameter")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new(mpn::ARG_AV_SYNC)
+                .long(mpn::ARG_AV_SYNC)
+                .help("Report track start offsets and fail when A/V sync exceeds the tolerance")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new(mpn::ARG_AV_SYNC_TOLERANCE)
+                .long(mpn::ARG_AV_SYNC_TOLERANCE)
+                .value_name("MILLISECONDS")
+                .help("Allowed A/V offset and track duration mismatch for --av-sync")
+                .value_parser(value_parser!(f64))
+                .default_value("40"),
+        )
+        .arg(
+            Arg::new(mpn::ARG_LAYOUT)
+                .long(mpn::ARG_LAYOUT)
+                .help("Report chunk layout and audio/video interleaving in mdat")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new(mpn::ARG_ASSERT_FASTSTART)
+                .long(mpn::ARG_ASSERT_FASTSTART)
+                .help("Fail when moov is not placed before mdat")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new(mpn::ARG_CHECK)
+                .long(mpn::ARG_CHECK)
+                .value_name("PROFILE")
+                .help("Check conformance to a profile or index consistency and fail on violations")
+                .value_parser(["cmaf", "sidx"])
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new(mpn::ARG_EXTRACT_COVER)
+                .long(mpn::ARG_EXTRACT_COVER)
+                .value_name("DIR")
+                .help("Write each covr image to DIR as cover-<n>.jpg or cover-<n>.png"),
+        )
+        .arg(
+            Arg::new(mpn::ARG_EXPORT_CHAPTERS)
+                .long(mpn::ARG_EXPORT_CHAPTERS)
+                .value_name("FILE")
+                .help("Write chapters to FILE as WebVTT for a .vtt extension, FFMETADATA otherwise"),
+        )
+        .subcommand(
+            Command::new(mpn::CMD_FASTSTART)
+                .about("Rewrite a file with moov moved before mdat, without re-encoding")
+                .arg(
+                    Arg::new(mpn::ARG_INPUT)
+                        .help("mp4 file to rewrite")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new(mpn::ARG_OUTPUT)
+                        .help("Path of the rewritten mp4 file")
+                        .required(true)
+                        .index(2),
+                ),
+        )
+        .subcommand(
+            Command::new(mpn::CMD_MANIFEST)
+                .about("Print an HLS media playlist or DASH MPD for a fragmented mp4 file")
+                .arg(
+                    Arg::new(mpn::ARG_HLS)
+                        .long(mpn::ARG_HLS)
+                        .help("Print a single-rendition HLS media playlist with byte ranges")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new(mpn::ARG_DASH)
+                        .long(mpn::ARG_DASH)
+                        .help("Print a static DASH MPD")
+                        .action(ArgAction::SetTrue),
+                )
+                .group(
+                    ArgGroup::new("format")
+                        .args([mpn::ARG_HLS, mpn::ARG_DASH])
+                        .required(true),
+                )
+                .arg(
+                    Arg::new(mpn::ARG_FILE)
+                        .help("Fragmented mp4 file to describe")
+                        .required(true)
+                        .index(1),
+                ),
+        )
         .get_matches();
 
     match mpn::run(matches) {
@@ -0,0 +1,355 @@
+//! Single-rendition HLS media playlists and on-demand DASH MPDs for fragmented files.
+//!
+//! Segments are addressed by byte range into the inspected file, taken from
+//! `sidx` when present and from the `moof`/`mdat` layout otherwise.
+
+use crate::boxes;
+use crate::codecs;
+use crate::fragment::MovieFragment;
+use crate::sidx::SegmentIndex;
+use mp4parse::{MediaContext, SampleEntry, TrackType};
+use std::error::Error;
+
+/// A byte range of the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    /// first byte
+    pub offset: u64,
+    /// number of bytes
+    pub length: u64,
+}
+
+impl ByteRange {
+    /// Last byte, inclusive, as used by DASH ranges.
+    pub fn last(&self) -> u64 {
+        self.offset + self.length - 1
+    }
+}
+
+/// A media segment of the presentation.
+#[derive(Debug, Clone, Copy)]
+pub struct MediaSegment {
+    /// bytes of the segment
+    pub range: ByteRange,
+    /// duration in seconds
+    pub duration: f64,
+}
+
+/// Everything needed to describe the file as a single rendition.
+#[derive(Debug, Clone)]
+pub struct Presentation {
+    /// file name the manifest refers to
+    pub uri: String,
+    /// `ftyp` and `moov`
+    pub init: ByteRange,
+    /// `sidx`, when segments come from it
+    pub index: Option<ByteRange>,
+    /// media segments in playback order
+    pub segments: Vec<MediaSegment>,
+    /// RFC 6381 codecs of every track
+    pub codecs: Vec<String>,
+    /// video width and height, when there is a video track
+    pub resolution: Option<(u16, u16)>,
+    /// audio sample rate, when there is an audio track
+    pub sample_rate: Option<u32>,
+}
+
+impl Presentation {
+    /// Total duration in seconds.
+    pub fn duration(&self) -> f64 {
+        self.segments.iter().map(|s| s.duration).sum()
+    }
+
+    /// Peak segment bitrate in bits per second.
+    pub fn bandwidth(&self) -> u64 {
+        self.segments
+            .iter()
+            .filter(|s| s.duration > 0.0)
+            .map(|s| (s.range.length as f64 * 8.0 / s.duration).ceil() as u64)
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn mime_type(&self) -> &'static str {
+        if self.resolution.is_some() {
+            "video/mp4"
+        } else {
+            "audio/mp4"
+        }
+    }
+}
+
+/// Collects byte ranges, durations and codecs of a fragmented file.
+/// # Arguments
+/// * `buf` - Whole file contents
+/// * `uri` - File name written into the manifest
+/// * `context` - mp4parse context read from `buf`
+/// * `fragments` - Movie fragments of `buf`
+/// * `indexes` - Segment indexes of `buf`
+pub fn analyze(
+    buf: &[u8],
+    uri: &str,
+    context: &MediaContext,
+    fragments: &[MovieFragment],
+    indexes: &[SegmentIndex],
+) -> Result<Presentation, Box<dyn Error>> {
+    if fragments.is_empty() {
+        return Err("manifest requires a fragmented mp4 file".into());
+    }
+    let top = boxes::top_level(buf);
+    let init_end = top
+        .iter()
+        .find(|b| b.is(b"sidx") || b.is(b"styp") || b.is(b"moof"))
+        .map_or(0, |b| b.offset) as u64;
+    if init_end == 0 {
+        return Err("manifest requires ftyp and moov before the first fragment".into());
+    }
+
+    let mut codecs = Vec::new();
+    let mut resolution = None;
+    let mut sample_rate = None;
+    let traks = boxes::traks(buf);
+    for (track, trak) in context.tracks.iter().zip(traks.iter()) {
+        // text, timecode and metadata tracks are not part of the rendition
+        if !matches!(track.track_type, TrackType::Video | TrackType::Audio) {
+            continue;
+        }
+        let entry = track.stsd.as_ref().and_then(|s| s.descriptions.first());
+        match (entry, &track.tkhd) {
+            (Some(SampleEntry::Video(v)), _) => {
+                resolution = resolution.or(Some((v.width, v.height)))
+            }
+            (Some(SampleEntry::Audio(a)), _) => {
+                sample_rate = sample_rate.or(Some(a.samplerate as u32))
+            }
+            // sample entries mp4parse does not know, such as hvc1, still have a track header size
+            (_, Some(tkhd)) if track.track_type == TrackType::Video => {
+                resolution = resolution.or(Some((
+                    (tkhd.width >> 16) as u16,
+                    (tkhd.height >> 16) as u16,
+                )))
+            }
+            _ => {}
+        }
+        if let Some(codec) = codecs::codecs(buf, trak, entry) {
+            codecs.push(codec);
+        }
+    }
+    if codecs.is_empty() {
+        return Err("no audio or video track has a codecs string".into());
+    }
+
+    let index = indexes
+        .first()
+        .filter(|i| !i.references.is_empty() && i.references.iter().all(|r| !r.reference_type));
+    let segments = match index {
+        Some(index) => index
+            .references
+            .iter()
+            .map(|r| MediaSegment {
+                range: ByteRange {
+                    offset: r.offset,
+                    length: u64::from(r.referenced_size),
+                },
+                duration: f64::from(r.subsegment_duration) / f64::from(index.timescale.max(1)),
+            })
+            .collect(),
+        None => {
+            let timescale = |track_id: u32| {
+                context
+                    .tracks
+                    .iter()
+                    .find(|t| t.track_id == Some(track_id))
+                    .and_then(|t| t.timescale)
+                    .map_or(1, |t| t.0.max(1)) as f64
+            };
+            fragments
+                .iter()
+                .map(|f| {
+                    let position = top.iter().position(|b| b.offset == f.moof.offset);
+                    let start = match position {
+                        Some(i) if i > 0 && top[i - 1].is(b"styp") => top[i - 1].offset,
+                        _ => f.moof.offset,
+                    } as u64;
+                    let end = f.mdat.map_or(f.moof.end(), |m| m.end()) as u64;
+                    MediaSegment {
+                        range: ByteRange {
+                            offset: start,
+                            length: end - start,
+                        },
+                        duration: f
+                            .trafs
+                            .iter()
+                            .map(|t| t.duration() as f64 / timescale(t.track_id))
+                            .fold(0.0, f64::max),
+                    }
+                })
+                .collect()
+        }
+    };
+    Ok(Presentation {
+        uri: uri.to_string(),
+        init: ByteRange {
+            offset: 0,
+            length: init_end,
+        },
+        index: index.map(|i| ByteRange {
+            offset: i.sidx.offset as u64,
+            length: i.sidx.size as u64,
+        }),
+        segments,
+        codecs,
+        resolution,
+        sample_rate,
+    })
+}
+
+/// Renders an HLS media playlist. Media playlists cannot carry `CODECS`, so the
+/// matching multivariant `EXT-X-STREAM-INF` line is included as a comment.
+pub fn hls(p: &Presentation) -> String {
+    let target = p
+        .segments
+        .iter()
+        .map(|s| (s.duration * 1000.0).round() / 1000.0)
+        .fold(0.0, f64::max)
+        .ceil() as u64;
+    let mut stream_inf = format!(
+        "BANDWIDTH={},CODECS=\"{}\"",
+        p.bandwidth(),
+        p.codecs.join(",")
+    );
+    if let Some((width, height)) = p.resolution {
+        stream_inf.push_str(&format!(",RESOLUTION={}x{}", width, height));
+    }
+    let mut out = String::new();
+    out.push_str("#EXTM3U\n");
+    out.push_str(&format!("# EXT-X-STREAM-INF:{}\n", stream_inf));
+    out.push_str("#EXT-X-VERSION:7\n");
+    out.push_str(&format!("#EXT-X-TARGETDURATION:{}\n", target));
+    out.push_str("#EXT-X-PLAYLIST-TYPE:VOD\n");
+    out.push_str("#EXT-X-INDEPENDENT-SEGMENTS\n");
+    let uri = percent_encode(&p.uri);
+    out.push_str(&format!(
+        "#EXT-X-MAP:URI=\"{}\",BYTERANGE=\"{}@{}\"\n",
+        uri, p.init.length, p.init.offset
+    ));
+    for s in &p.segments {
+        out.push_str(&format!("#EXTINF:{:.6},\n", s.duration));
+        out.push_str(&format!(
+            "#EXT-X-BYTERANGE:{}@{}\n",
+            s.range.length, s.range.offset
+        ));
+        out.push_str(&format!("{}\n", uri));
+    }
+    out.push_str("#EXT-X-ENDLIST\n");
+    out
+}
+
+/// Percent-encodes the bytes of `uri` that may not appear in a URI reference,
+/// such as quotes, spaces and line breaks.
+fn percent_encode(uri: &str) -> String {
+    let mut out = String::with_capacity(uri.len());
+    for &b in uri.as_bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@/".contains(&b) {
+            out.push(char::from(b));
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+/// Escapes the XML special characters of `value`.
+fn xml_escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Renders a static DASH MPD. Files with a `sidx` use the on-demand profile and
+/// `SegmentBase`, others list each fragment in a `SegmentList`.
+pub fn dash(p: &Presentation) -> String {
+    let profile = if p.index.is_some() {
+        "urn:mpeg:dash:profile:isoff-on-demand:2011"
+    } else {
+        "urn:mpeg:dash:profile:isoff-main:2011"
+    };
+    let max_duration = p.segments.iter().map(|s| s.duration).fold(0.0, f64::max);
+    let mut representation = format!(
+        "id=\"1\" codecs=\"{}\" bandwidth=\"{}\"",
+        p.codecs.join(","),
+        p.bandwidth()
+    );
+    if let Some((width, height)) = p.resolution {
+        representation.push_str(&format!(" width=\"{}\" height=\"{}\"", width, height));
+    }
+    if let Some(rate) = p.sample_rate {
+        representation.push_str(&format!(" audioSamplingRate=\"{}\"", rate));
+    }
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str(&format!(
+        "<MPD xmlns=\"urn:mpeg:dash:schema:mpd:2011\" profiles=\"{}\" type=\"static\" mediaPresentationDuration=\"PT{:.3}S\" minBufferTime=\"PT{:.3}S\">\n",
+        profile,
+        p.duration(),
+        max_duration
+    ));
+    out.push_str("  <Period>\n");
+    out.push_str(&format!(
+        "    <AdaptationSet mimeType=\"{}\" segmentAlignment=\"true\" startWithSAP=\"1\">\n",
+        p.mime_type()
+    ));
+    out.push_str(&format!("      <Representation {}>\n", representation));
+    out.push_str(&format!(
+        "        <BaseURL>{}</BaseURL>\n",
+        xml_escape(&p.uri)
+    ));
+    match p.index {
+        Some(index) => {
+            out.push_str(&format!(
+                "        <SegmentBase indexRange=\"{}-{}\">\n",
+                index.offset,
+                index.last()
+            ));
+            out.push_str(&format!(
+                "          <Initialization range=\"{}-{}\"/>\n",
+                p.init.offset,
+                p.init.last()
+            ));
+            out.push_str("        </SegmentBase>\n");
+        }
+        None => {
+            out.push_str(&format!(
+                "        <SegmentList timescale=\"1000\" duration=\"{:.0}\">\n",
+                max_duration * 1000.0
+            ));
+            out.push_str(&format!(
+                "          <Initialization range=\"{}-{}\"/>\n",
+                p.init.offset,
+                p.init.last()
+            ));
+            for s in &p.segments {
+                out.push_str(&format!(
+                    "          <SegmentURL mediaRange=\"{}-{}\"/>\n",
+                    s.range.offset,
+                    s.range.last()
+                ));
+            }
+            out.push_str("        </SegmentList>\n");
+        }
+    }
+    out.push_str("      </Representation>\n");
+    out.push_str("    </AdaptationSet>\n");
+    out.push_str("  </Period>\n");
+    out.push_str("</MPD>\n");
+    out
+}
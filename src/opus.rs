@@ -0,0 +1,92 @@
+//! Opus codec configuration (`dOps`).
+//!
+//! mp4parse keeps the `dOps` fields private, so they are read back from the
+//! Ogg `OpusHead` it serializes. See "Encapsulation of Opus in ISO Base Media
+//! File Format" § 4.3.2 and RFC 7845 § 5.1.
+
+use mp4parse::{serialize_opus_header, OpusSpecificBox};
+use std::io::{Error, ErrorKind, Result};
+
+/// Decoded Opus identification header.
+#[derive(Debug, Clone, Default)]
+pub struct OpusHeader {
+    /// OutputChannelCount
+    pub output_channel_count: u8,
+    /// PreSkip in 48 kHz samples
+    pub pre_skip: u16,
+    /// InputSampleRate in Hz
+    pub input_sample_rate: u32,
+    /// OutputGain in Q7.8 dB
+    pub output_gain: i16,
+    /// ChannelMappingFamily
+    pub channel_mapping_family: u8,
+    /// StreamCount, CoupledCount and ChannelMapping when the family is not 0
+    pub channel_mapping_table: Option<(u8, u8, Vec<u8>)>,
+}
+
+/// Decodes the `dOps` fields of an Opus sample entry.
+pub fn read_header(dops: &OpusSpecificBox) -> Result<OpusHeader> {
+    let mut head = Vec::new();
+    serialize_opus_header(dops, &mut head)
+        .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{:?}", e)))?;
+    if head.len() < 19 {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "OpusHead truncated"));
+    }
+    let mut header = OpusHeader {
+        output_channel_count: head[9],
+        pre_skip: u16::from_le_bytes([head[10], head[11]]),
+        input_sample_rate: u32::from_le_bytes([head[12], head[13], head[14], head[15]]),
+        output_gain: i16::from_le_bytes([head[16], head[17]]),
+        channel_mapping_family: head[18],
+        channel_mapping_table: None,
+    };
+    if head.len() >= 21 {
+        header.channel_mapping_table = Some((head[19], head[20], head[21..].to_vec()));
+    }
+    Ok(header)
+}
+
+fn channel_mapping_family_name(family: u8) -> &'static str {
+    match family {
+        0 => "mono or stereo",
+        1 => "Vorbis channel order",
+        2 => "ambisonics",
+        3 => "ambisonics with demixing matrix",
+        255 => "discrete",
+        _ => "reserved",
+    }
+}
+
+/// Prints the `[media.track.audio.opus]` section.
+pub fn print_report(dops: &OpusSpecificBox) {
+    println!("[media.track.audio.opus]");
+    println!("version = {:?}", dops.version);
+    let header = match read_header(dops) {
+        Ok(header) => header,
+        Err(e) => {
+            println!("error = \"{}\"", e);
+            return;
+        }
+    };
+    println!("output_channel_count = {:?}", header.output_channel_count);
+    println!("pre_skip = {:?}", header.pre_skip);
+    println!("input_sample_rate = {:?}", header.input_sample_rate);
+    println!("output_gain = {:?}", header.output_gain);
+    println!(
+        "output_gain_db = {:.3}",
+        f64::from(header.output_gain) / 256.0
+    );
+    println!(
+        "channel_mapping_family = {:?}",
+        header.channel_mapping_family
+    );
+    println!(
+        "channel_mapping_family_name = \"{}\"",
+        channel_mapping_family_name(header.channel_mapping_family)
+    );
+    if let Some((stream_count, coupled_count, channel_mapping)) = &header.channel_mapping_table {
+        println!("stream_count = {:?}", stream_count);
+        println!("coupled_count = {:?}", coupled_count);
+        println!("channel_mapping = {:?}", channel_mapping);
+    }
+}
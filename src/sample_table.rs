@@ -0,0 +1,128 @@
+//! Sample table helpers over the `stbl` boxes mp4parse exposes on `Track`.
+
+use mp4parse::{TimeOffsetVersion, Track};
+
+/// Decode timestamps of every sample, in track timescale units, from `stts`.
+pub fn decode_times(track: &Track) -> Vec<u64> {
+    let mut times = Vec::new();
+    let mut time = 0u64;
+    if let Some(ref stts) = track.stts {
+        for entry in stts.samples.iter() {
+            for _ in 0..entry.sample_count {
+                times.push(time);
+                time += u64::from(entry.sample_delta);
+            }
+        }
+    }
+    times
+}
+
+/// Composition offsets of every sample from `ctts`, or zeros when absent.
+pub fn composition_offsets(track: &Track, samples: usize) -> Vec<i64> {
+    let mut offsets = Vec::with_capacity(samples);
+    if let Some(ref ctts) = track.ctts {
+        for entry in ctts.samples.iter() {
+            let offset = match entry.time_offset {
+                TimeOffsetVersion::Version0(o) => i64::from(o),
+                TimeOffsetVersion::Version1(o) => i64::from(o),
+            };
+            for _ in 0..entry.sample_count {
+                offsets.push(offset);
+            }
+        }
+    }
+    offsets.resize(samples, 0);
+    offsets
+}
+
+/// Earliest composition timestamp of the track's samples, in track timescale units.
+pub fn first_composition_time(track: &Track) -> Option<i64> {
+    let dts = decode_times(track);
+    let offsets = composition_offsets(track, dts.len());
+    dts.iter()
+        .zip(offsets.iter())
+        .map(|(&d, &o)| d as i64 + o)
+        .min()
+}
+
+/// Sizes of every sample from `stsz`.
+pub fn sample_sizes(track: &Track) -> Vec<u32> {
+    match track.stsz {
+        Some(ref stsz) if stsz.sample_size == 0 => stsz.sample_sizes.iter().copied().collect(),
+        Some(ref stsz) => vec![stsz.sample_size; decode_times(track).len()],
+        None => Vec::new(),
+    }
+}
+
+/// A chunk of consecutive samples, as described by `stsc` and `stco`/`co64`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chunk {
+    /// file offset of the chunk
+    pub offset: u64,
+    /// index of the first sample in the chunk, zero based
+    pub first_sample: usize,
+    /// number of samples in the chunk
+    pub samples: usize,
+    /// one based sample description index used by the chunk
+    pub sample_description_index: u32,
+}
+
+/// Chunks of the track in chunk order.
+pub fn chunks(track: &Track) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    let (stco, stsc) = match (&track.stco, &track.stsc) {
+        (Some(stco), Some(stsc)) => (stco, stsc),
+        _ => return chunks,
+    };
+    let entries = &stsc.samples;
+    let mut e = 0;
+    let mut first_sample = 0;
+    for (i, offset) in stco.offsets.iter().enumerate() {
+        let chunk_number = i as u32 + 1;
+        while e + 1 < entries.len() && entries[e + 1].first_chunk <= chunk_number {
+            e += 1;
+        }
+        let entry = match entries.get(e) {
+            Some(entry) if entry.first_chunk <= chunk_number => entry,
+            _ => continue,
+        };
+        chunks.push(Chunk {
+            offset: *offset,
+            first_sample,
+            samples: entry.samples_per_chunk as usize,
+            sample_description_index: entry.sample_description_index,
+        });
+        first_sample += entry.samples_per_chunk as usize;
+    }
+    chunks
+}
+
+/// A run of consecutive samples that use the same sample description.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DescriptionRange {
+    /// one based sample description index
+    pub sample_description_index: u32,
+    /// first sample of the run, one based
+    pub first_sample: usize,
+    /// last sample of the run, one based and inclusive
+    pub last_sample: usize,
+}
+
+/// Runs of samples per sample description, merging consecutive chunks that
+/// `stsc` assigns to the same description.
+pub fn description_ranges(track: &Track) -> Vec<DescriptionRange> {
+    let mut ranges: Vec<DescriptionRange> = Vec::new();
+    for chunk in chunks(track).iter().filter(|c| c.samples > 0) {
+        match ranges.last_mut() {
+            Some(last) if last.sample_description_index == chunk.sample_description_index => {
+                last.last_sample = chunk.first_sample + chunk.samples;
+            }
+            _ => ranges.push(DescriptionRange {
+                sample_description_index: chunk.sample_description_index,
+                first_sample: chunk.first_sample + 1,
+                last_sample: chunk.first_sample + chunk.samples,
+            }),
+        }
+    }
+    ranges
+}
@@ -0,0 +1,169 @@
+//! Inspection of separately delivered init and media segments (DASH/HLS `.m4s`).
+//!
+//! The init segment's `moov` supplies timescales and `trex` defaults, each media
+//! segment is then read as a run of movie fragments against it.
+
+use crate::fragment::{self, FragmentedTrack, TrackExtends};
+use mp4parse::MediaContext;
+use std::io::Result;
+
+/// A track of one media segment.
+#[derive(Debug, Clone, Copy)]
+pub struct SegmentTrack {
+    /// fragment statistics of the track within the segment
+    pub stats: FragmentedTrack,
+    /// `tfdt` of the first track fragment
+    pub base_media_decode_time: Option<u64>,
+    /// difference between `tfdt` and the end of the previous segment, in timescale units
+    pub decode_time_gap: Option<i64>,
+}
+
+/// A media segment read against the init segment.
+#[derive(Debug, Clone)]
+pub struct Segment {
+    /// segment path
+    pub uri: String,
+    /// segment size in bytes
+    pub bytes: usize,
+    /// number of movie fragments
+    pub fragments: usize,
+    /// `mfhd` sequence number of the first fragment
+    pub first_sequence_number: Option<u32>,
+    /// expected and found sequence numbers where the numbering skips
+    pub sequence_gaps: Vec<(u32, u32)>,
+    /// tracks carried by the segment
+    pub tracks: Vec<SegmentTrack>,
+}
+
+impl Segment {
+    /// Longest track duration in seconds.
+    pub fn duration(&self) -> f64 {
+        self.tracks
+            .iter()
+            .map(|t| t.stats.duration as f64 / t.stats.timescale as f64)
+            .fold(0.0, f64::max)
+    }
+}
+
+/// Reads each media segment and tracks `tfdt` and sequence number continuity
+/// across them.
+/// # Arguments
+/// * `context` - mp4parse context of the init segment
+/// * `trexs` - Track defaults from the init segment's `mvex`
+/// * `segments` - Path and contents of each media segment, in playback order
+pub fn analyze(
+    context: &MediaContext,
+    trexs: &[TrackExtends],
+    segments: &[(String, Vec<u8>)],
+) -> Result<Vec<Segment>> {
+    let mut result = Vec::new();
+    let mut next_sequence_number: Option<u32> = None;
+    let mut next_decode_time: Vec<(u32, u64)> = Vec::new();
+    for (uri, buf) in segments {
+        let fragments = fragment::read_fragments(buf, trexs)?;
+        let mut sequence_gaps = Vec::new();
+        for sequence_number in fragments.iter().filter_map(|f| f.sequence_number) {
+            if let Some(expected) = next_sequence_number {
+                if sequence_number != expected {
+                    sequence_gaps.push((expected, sequence_number));
+                }
+            }
+            next_sequence_number = Some(sequence_number.wrapping_add(1));
+        }
+        let mut tracks = Vec::new();
+        for stats in fragment::track_statistics(context, &fragments) {
+            if stats.fragments == 0 {
+                continue;
+            }
+            let base_media_decode_time = fragments
+                .iter()
+                .flat_map(|f| f.trafs.iter())
+                .find(|t| t.track_id == stats.track_id)
+                .and_then(|t| t.base_media_decode_time);
+            let expected = next_decode_time
+                .iter()
+                .find(|n| n.0 == stats.track_id)
+                .map(|n| n.1);
+            let decode_time_gap = base_media_decode_time
+                .zip(expected)
+                .map(|(time, expected)| time as i64 - expected as i64);
+            if let Some(time) = base_media_decode_time {
+                next_decode_time.retain(|n| n.0 != stats.track_id);
+                next_decode_time.push((stats.track_id, time + stats.duration));
+            }
+            tracks.push(SegmentTrack {
+                stats,
+                base_media_decode_time,
+                decode_time_gap,
+            });
+        }
+        result.push(Segment {
+            uri: uri.clone(),
+            bytes: buf.len(),
+            fragments: fragments.len(),
+            first_sequence_number: fragments.first().and_then(|f| f.sequence_number),
+            sequence_gaps,
+            tracks,
+        });
+    }
+    Ok(result)
+}
+
+/// Prints the `[media.segment]` sections and a continuity summary.
+pub fn print_report(segments: &[Segment]) {
+    for s in segments {
+        let duration = s.duration();
+        println!("[media.segment]");
+        println!("uri = \"{}\"", s.uri);
+        println!("bytes = {:?}", s.bytes);
+        println!("fragments = {:?}", s.fragments);
+        if let Some(sequence_number) = s.first_sequence_number {
+            println!("sequence_number = {:?}", sequence_number);
+        }
+        println!("duration = {:.6}", duration);
+        if duration > 0.0 {
+            println!("bitrate = {:.0}", s.bytes as f64 * 8.0 / duration);
+        }
+        for (expected, found) in &s.sequence_gaps {
+            println!("[media.segment.sequence_gap]");
+            println!("expected = {:?}", expected);
+            println!("found = {:?}", found);
+        }
+        for t in &s.tracks {
+            println!("[media.segment.track]");
+            println!("track_id = {:?}", t.stats.track_id);
+            if let Some(time) = t.base_media_decode_time {
+                println!("base_media_decode_time = {:?}", time);
+            }
+            println!("sample_count = {:?}", t.stats.samples);
+            println!(
+                "duration = {:.6}",
+                t.stats.duration as f64 / t.stats.timescale as f64
+            );
+            println!("bytes = {:?}", t.stats.bytes);
+            if let Some(gap) = t.decode_time_gap {
+                println!("continuous = {:?}", gap == 0);
+                if gap != 0 {
+                    println!("decode_time_gap = {:?}", gap);
+                }
+            }
+        }
+    }
+    let discontinuities = segments
+        .iter()
+        .flat_map(|s| s.tracks.iter())
+        .filter(|t| t.decode_time_gap.is_some_and(|gap| gap != 0))
+        .count();
+    let sequence_gaps: usize = segments.iter().map(|s| s.sequence_gaps.len()).sum();
+    let duration: f64 = segments.iter().map(Segment::duration).sum();
+    let bytes: usize = segments.iter().map(|s| s.bytes).sum();
+    println!("[media.segments]");
+    println!("segment_count = {:?}", segments.len());
+    println!("duration = {:.6}", duration);
+    println!("bytes = {:?}", bytes);
+    if duration > 0.0 {
+        println!("bitrate = {:.0}", bytes as f64 * 8.0 / duration);
+    }
+    println!("discontinuities = {:?}", discontinuities);
+    println!("sequence_gaps = {:?}", sequence_gaps);
+}
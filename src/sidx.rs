@@ -0,0 +1,395 @@
+//! Segment index (`sidx`) and movie fragment random access (`mfra/tfra`) reporting.
+//!
+//! Both index fragments by byte position, so each entry is checked against
+//! the `moof`/`mdat` boxes actually found in the file.
+
+use crate::boxes::{self, Mp4Box, Reader};
+use crate::check::Violation;
+use crate::fragment::MovieFragment;
+use mp4parse::MediaContext;
+use std::io::{Error, ErrorKind, Result};
+
+/// A single `sidx` reference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SidxReference {
+    /// true when the reference points at another `sidx`
+    pub reference_type: bool,
+    /// size of the referenced material in bytes
+    pub referenced_size: u32,
+    /// subsegment duration in sidx timescale units
+    pub subsegment_duration: u32,
+    /// starts_with_SAP flag
+    pub starts_with_sap: bool,
+    /// SAP type
+    pub sap_type: u8,
+    /// SAP delta time
+    pub sap_delta_time: u32,
+    /// resolved file offset of the referenced material
+    pub offset: u64,
+}
+
+/// Segment index box `sidx`.
+#[derive(Debug, Clone)]
+pub struct SegmentIndex {
+    /// the `sidx` box
+    pub sidx: Mp4Box,
+    /// track id the index refers to
+    pub reference_id: u32,
+    /// timescale of the durations
+    pub timescale: u32,
+    /// earliest presentation time of the first subsegment
+    pub earliest_presentation_time: u64,
+    /// distance from the end of the box to the first referenced byte
+    pub first_offset: u64,
+    /// references
+    pub references: Vec<SidxReference>,
+}
+
+/// A `tfra` entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TfraEntry {
+    /// presentation time of the sync sample
+    pub time: u64,
+    /// file offset of the moof holding the sample
+    pub moof_offset: u64,
+    /// one based traf number within the moof
+    pub traf_number: u32,
+    /// one based trun number within the traf
+    pub trun_number: u32,
+    /// one based sample number within the trun
+    pub sample_number: u32,
+}
+
+/// Track fragment random access box `tfra`.
+#[derive(Debug, Clone)]
+pub struct TrackFragmentRandomAccess {
+    /// track id
+    pub track_id: u32,
+    /// random access entries
+    pub entries: Vec<TfraEntry>,
+}
+
+/// Movie fragment random access box `mfra`.
+#[derive(Debug, Clone)]
+pub struct MovieFragmentRandomAccess {
+    /// the `mfra` box
+    pub mfra: Mp4Box,
+    /// per track random access tables
+    pub tfras: Vec<TrackFragmentRandomAccess>,
+    /// mfra size recorded in `mfro`
+    pub mfro_size: Option<u32>,
+}
+
+/// Reads every top level `sidx`.
+pub fn read_sidxs(buf: &[u8]) -> Result<Vec<SegmentIndex>> {
+    let mut indexes = Vec::new();
+    for sidx in boxes::top_level(buf).iter().filter(|b| b.is(b"sidx")) {
+        let mut r = Reader::new(sidx.body(buf));
+        let (version, _) = r.full_box()?;
+        let reference_id = r.u32()?;
+        let timescale = r.u32()?;
+        let (earliest_presentation_time, first_offset) = if version == 0 {
+            (u64::from(r.u32()?), u64::from(r.u32()?))
+        } else {
+            (r.u64()?, r.u64()?)
+        };
+        r.skip(2)?;
+        let count = r.u16()?;
+        let overflow = || Error::new(ErrorKind::InvalidData, "sidx reference offset overflows");
+        let mut offset = (sidx.end() as u64)
+            .checked_add(first_offset)
+            .ok_or_else(overflow)?;
+        let mut references = Vec::new();
+        for _ in 0..count {
+            let size = r.u32()?;
+            let subsegment_duration = r.u32()?;
+            let sap = r.u32()?;
+            references.push(SidxReference {
+                reference_type: size >> 31 == 1,
+                referenced_size: size & 0x7fff_ffff,
+                subsegment_duration,
+                starts_with_sap: sap >> 31 == 1,
+                sap_type: ((sap >> 28) & 0x7) as u8,
+                sap_delta_time: sap & 0x0fff_ffff,
+                offset,
+            });
+            offset = offset
+                .checked_add(u64::from(size & 0x7fff_ffff))
+                .ok_or_else(overflow)?;
+        }
+        indexes.push(SegmentIndex {
+            sidx: *sidx,
+            reference_id,
+            timescale,
+            earliest_presentation_time,
+            first_offset,
+            references,
+        });
+    }
+    Ok(indexes)
+}
+
+/// Reads the top level `mfra`, if any.
+pub fn read_mfra(buf: &[u8]) -> Result<Option<MovieFragmentRandomAccess>> {
+    let mfra = match boxes::find_top(buf, b"mfra") {
+        Some(mfra) => mfra,
+        None => return Ok(None),
+    };
+    let mut result = MovieFragmentRandomAccess {
+        mfra,
+        tfras: Vec::new(),
+        mfro_size: None,
+    };
+    for b in boxes::children(buf, &mfra) {
+        let mut r = Reader::new(b.body(buf));
+        match &b.name {
+            b"tfra" => {
+                let (version, _) = r.full_box()?;
+                let track_id = r.u32()?;
+                let sizes = r.u32()?;
+                let count = r.u32()?;
+                let field = |r: &mut Reader, bits: u32| -> Result<u32> {
+                    let mut value = 0;
+                    for _ in 0..=bits {
+                        value = (value << 8) | u32::from(r.u8()?);
+                    }
+                    Ok(value)
+                };
+                let mut entries = Vec::new();
+                for _ in 0..count {
+                    let (time, moof_offset) = if version == 1 {
+                        (r.u64()?, r.u64()?)
+                    } else {
+                        (u64::from(r.u32()?), u64::from(r.u32()?))
+                    };
+                    entries.push(TfraEntry {
+                        time,
+                        moof_offset,
+                        traf_number: field(&mut r, (sizes >> 4) & 0x3)?,
+                        trun_number: field(&mut r, (sizes >> 2) & 0x3)?,
+                        sample_number: field(&mut r, sizes & 0x3)?,
+                    });
+                }
+                result
+                    .tfras
+                    .push(TrackFragmentRandomAccess { track_id, entries });
+            }
+            b"mfro" => {
+                r.full_box()?;
+                result.mfro_size = Some(r.u32()?);
+            }
+            _ => {}
+        }
+    }
+    Ok(Some(result))
+}
+
+/// Checks that `sidx` references cover the actual `moof`/`mdat` byte ranges and
+/// that `tfra` entries point at real `moof` boxes.
+/// # Arguments
+/// * `buf` - Whole file contents
+/// * `context` - mp4parse context read from `buf`
+/// * `fragments` - Movie fragments of `buf`
+/// * `indexes` - Segment indexes from `read_sidxs`
+/// * `mfra` - Random access index from `read_mfra`
+pub fn check(
+    buf: &[u8],
+    context: &MediaContext,
+    fragments: &[MovieFragment],
+    indexes: &[SegmentIndex],
+    mfra: Option<&MovieFragmentRandomAccess>,
+) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let top = boxes::top_level(buf);
+    let timescale = |track_id: u32| {
+        context
+            .tracks
+            .iter()
+            .find(|t| t.track_id == Some(track_id))
+            .and_then(|t| t.timescale)
+            .map_or(1, |t| t.0.max(1))
+    };
+
+    for (i, index) in indexes.iter().enumerate() {
+        for (j, reference) in index.references.iter().enumerate() {
+            let path = format!("sidx[{}]/reference[{}]", i + 1, j + 1);
+            let start = reference.offset as usize;
+            let end = match start.checked_add(reference.referenced_size as usize) {
+                Some(end) => end,
+                None => {
+                    violations.push(Violation::new(
+                        path,
+                        "referenced range extends past the largest file offset",
+                    ));
+                    continue;
+                }
+            };
+            let first = top.iter().position(|b| b.offset == start);
+            let last = top.iter().position(|b| b.end() == end);
+            let (first, last) = match (first, last) {
+                (Some(first), Some(last)) if first <= last => (first, last),
+                _ => {
+                    violations.push(Violation::new(
+                        path,
+                        &format!(
+                            "referenced range {}..{} does not match top level box boundaries",
+                            start, end
+                        ),
+                    ));
+                    continue;
+                }
+            };
+            if reference.reference_type {
+                if !top[first].is(b"sidx") {
+                    violations.push(Violation::new(
+                        path,
+                        "reference_type 1 does not point at a sidx box",
+                    ));
+                }
+                continue;
+            }
+            let covered: Vec<&MovieFragment> = fragments
+                .iter()
+                .filter(|f| f.moof.offset >= start && f.moof.end() <= end)
+                .collect();
+            if covered.is_empty() || !top[first..=last].iter().any(|b| b.is(b"mdat")) {
+                violations.push(Violation::new(
+                    path,
+                    "referenced range does not contain a moof and mdat",
+                ));
+                continue;
+            }
+            let track_scale = u128::from(timescale(index.reference_id));
+            let duration: u64 = covered
+                .iter()
+                .flat_map(|f| f.trafs.iter())
+                .filter(|t| t.track_id == index.reference_id)
+                .map(|t| t.duration())
+                .sum();
+            // rounded to the nearest sidx tick, allowing one tick of rounding error
+            let expected = (u128::from(duration) * u128::from(index.timescale) + track_scale / 2)
+                / track_scale;
+            if expected.abs_diff(u128::from(reference.subsegment_duration)) > 1 {
+                violations.push(Violation::new(
+                    path.clone(),
+                    &format!(
+                        "subsegment_duration {} does not match fragment duration {}",
+                        reference.subsegment_duration, expected
+                    ),
+                ));
+            }
+            let starts_with_sync = covered
+                .first()
+                .and_then(|f| f.trafs.iter().find(|t| t.track_id == index.reference_id))
+                .and_then(|t| t.samples.first())
+                .is_some_and(|s| s.is_sync());
+            if reference.starts_with_sap && !starts_with_sync {
+                violations.push(Violation::new(
+                    path,
+                    "starts_with_SAP is set but the first sample is not a sync sample",
+                ));
+            }
+        }
+    }
+
+    if let Some(mfra) = mfra {
+        if mfra
+            .mfro_size
+            .is_some_and(|size| size as usize != mfra.mfra.size)
+        {
+            violations.push(Violation::new(
+                "mfra/mfro".to_string(),
+                "mfro size does not match the mfra box size",
+            ));
+        }
+        for (i, tfra) in mfra.tfras.iter().enumerate() {
+            for (j, entry) in tfra.entries.iter().enumerate() {
+                let path = format!("mfra/tfra[{}]/entry[{}]", i + 1, j + 1);
+                let fragment = fragments
+                    .iter()
+                    .find(|f| f.moof.offset as u64 == entry.moof_offset);
+                let traf = match fragment {
+                    Some(f) => (entry.traf_number as usize)
+                        .checked_sub(1)
+                        .and_then(|n| f.trafs.get(n)),
+                    None => {
+                        violations.push(Violation::new(
+                            path,
+                            &format!("moof_offset {} is not a moof box", entry.moof_offset),
+                        ));
+                        continue;
+                    }
+                };
+                match traf {
+                    Some(t) if t.track_id == tfra.track_id => {
+                        if t.base_media_decode_time
+                            .is_some_and(|time| time > entry.time)
+                        {
+                            violations.push(Violation::new(
+                                path,
+                                "time is earlier than the fragment decode time",
+                            ));
+                        }
+                    }
+                    _ => violations.push(Violation::new(
+                        path,
+                        &format!(
+                            "traf_number {} does not carry track {}",
+                            entry.traf_number, tfra.track_id
+                        ),
+                    )),
+                }
+            }
+        }
+    }
+    violations
+}
+
+/// Prints the `[media.sidx]` and `[media.mfra]` sections.
+pub fn print_report(indexes: &[SegmentIndex], mfra: Option<&MovieFragmentRandomAccess>) {
+    for index in indexes {
+        println!("[media.sidx]");
+        println!("offset = {:?}", index.sidx.offset);
+        println!("reference_id = {:?}", index.reference_id);
+        println!("timescale = {:?}", index.timescale);
+        println!(
+            "earliest_presentation_time = {:?}",
+            index.earliest_presentation_time
+        );
+        println!("first_offset = {:?}", index.first_offset);
+        println!("reference_count = {:?}", index.references.len());
+        for r in &index.references {
+            println!("[media.sidx.reference]");
+            println!(
+                "reference_type = \"{}\"",
+                if r.reference_type { "index" } else { "media" }
+            );
+            println!("offset = {:?}", r.offset);
+            println!("referenced_size = {:?}", r.referenced_size);
+            println!("subsegment_duration = {:?}", r.subsegment_duration);
+            println!("starts_with_sap = {:?}", r.starts_with_sap);
+            println!("sap_type = {:?}", r.sap_type);
+            println!("sap_delta_time = {:?}", r.sap_delta_time);
+        }
+    }
+    if let Some(mfra) = mfra {
+        println!("[media.mfra]");
+        println!("offset = {:?}", mfra.mfra.offset);
+        println!("size = {:?}", mfra.mfra.size);
+        if let Some(size) = mfra.mfro_size {
+            println!("mfro_size = {:?}", size);
+        }
+        for tfra in &mfra.tfras {
+            println!("[media.mfra.tfra]");
+            println!("track_id = {:?}", tfra.track_id);
+            println!("entries = [");
+            for e in &tfra.entries {
+                println!(
+                    "  {{ time = {}, moof_offset = {}, traf_number = {}, trun_number = {}, sample_number = {} }},",
+                    e.time, e.moof_offset, e.traf_number, e.trun_number, e.sample_number
+                );
+            }
+            println!("]");
+        }
+    }
+}
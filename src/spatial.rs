@@ -0,0 +1,252 @@
+//! Stereoscopic and spherical video metadata: the Spherical Video V2 `st3d` and
+//! `sv3d` boxes and Apple's `vexu` and `hfov` stereo video extensions.
+//!
+//! See the Google Spherical Video V2 RFC and Apple's "ISO Base Media File Format
+//! and Apple HEVC Stereo Video".
+
+use crate::boxes::{self, Mp4Box, Reader};
+use std::io::Result;
+
+/// Projection of an `sv3d` box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// `equi` bounds, top, bottom, left and right as 0.32 fixed point fractions
+    Equirectangular([u32; 4]),
+    /// `cbmp` layout and padding
+    Cubemap(u32, u32),
+    /// `mshp` mesh encoding
+    Mesh([u8; 4]),
+}
+
+/// Spatial metadata of a visual sample entry.
+#[derive(Debug, Clone, Default)]
+pub struct SpatialInfo {
+    /// st3d stereo_mode
+    pub stereo_mode: Option<u8>,
+    /// svhd metadata_source
+    pub metadata_source: Option<String>,
+    /// prhd pose yaw, pitch and roll in degrees
+    pub pose: Option<(f64, f64, f64)>,
+    /// projection box of the proj container
+    pub projection: Option<Projection>,
+    /// vexu stri flags: left eye, right eye, additional views, views reversed
+    pub stereo_views: Option<u8>,
+    /// vexu hero eye indicator
+    pub hero_eye: Option<u8>,
+    /// vexu camera baseline in micrometres
+    pub baseline: Option<u32>,
+    /// vexu disparity adjustment in 1/10000 of the image width
+    pub disparity_adjustment: Option<i32>,
+    /// vexu projection kind
+    pub projection_kind: Option<[u8; 4]>,
+    /// hfov field of view in thousandths of a degree
+    pub field_of_view: Option<u32>,
+}
+
+impl SpatialInfo {
+    fn is_empty(&self) -> bool {
+        self.stereo_mode.is_none()
+            && self.metadata_source.is_none()
+            && self.pose.is_none()
+            && self.projection.is_none()
+            && self.stereo_views.is_none()
+            && self.hero_eye.is_none()
+            && self.baseline.is_none()
+            && self.disparity_adjustment.is_none()
+            && self.projection_kind.is_none()
+            && self.field_of_view.is_none()
+    }
+}
+
+fn fixed_16_16(value: i32) -> f64 {
+    f64::from(value) / 65536.0
+}
+
+fn read_sv3d(buf: &[u8], sv3d: &Mp4Box, info: &mut SpatialInfo) -> Result<()> {
+    for b in boxes::children(buf, sv3d) {
+        if b.is(b"svhd") {
+            let mut r = Reader::new(b.body(buf));
+            r.full_box()?;
+            let source = r.bytes(r.remaining())?;
+            let end = source.iter().position(|&c| c == 0).unwrap_or(source.len());
+            info.metadata_source = Some(String::from_utf8_lossy(&source[..end]).to_string());
+        } else if b.is(b"proj") {
+            for p in boxes::children(buf, &b) {
+                let mut r = Reader::new(p.body(buf));
+                match &p.name {
+                    b"prhd" => {
+                        r.full_box()?;
+                        info.pose = Some((
+                            fixed_16_16(r.i32()?),
+                            fixed_16_16(r.i32()?),
+                            fixed_16_16(r.i32()?),
+                        ));
+                    }
+                    b"equi" => {
+                        r.full_box()?;
+                        info.projection = Some(Projection::Equirectangular([
+                            r.u32()?,
+                            r.u32()?,
+                            r.u32()?,
+                            r.u32()?,
+                        ]));
+                    }
+                    b"cbmp" => {
+                        r.full_box()?;
+                        info.projection = Some(Projection::Cubemap(r.u32()?, r.u32()?));
+                    }
+                    b"mshp" => {
+                        r.full_box()?;
+                        r.skip(4)?;
+                        let encoding = r.bytes(4)?.try_into().unwrap_or_default();
+                        info.projection = Some(Projection::Mesh(encoding));
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn read_vexu(buf: &[u8], vexu: &Mp4Box, info: &mut SpatialInfo) -> Result<()> {
+    for container in boxes::children(buf, vexu) {
+        for b in boxes::children(buf, &container) {
+            // other children need not be full boxes
+            if !matches!(&b.name, b"stri" | b"hero" | b"blin" | b"dadj" | b"prji") {
+                continue;
+            }
+            let mut r = Reader::new(b.body(buf));
+            r.full_box()?;
+            match &b.name {
+                b"stri" => info.stereo_views = Some(r.u8()?),
+                b"hero" => info.hero_eye = Some(r.u8()?),
+                b"blin" => info.baseline = Some(r.u32()?),
+                b"dadj" => info.disparity_adjustment = Some(r.i32()?),
+                b"prji" => info.projection_kind = Some(r.bytes(4)?.try_into().unwrap_or_default()),
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads the `st3d`, `sv3d`, `vexu` and `hfov` children of a visual sample entry.
+pub fn read_spatial_info(buf: &[u8], entry: &Mp4Box) -> Result<SpatialInfo> {
+    let mut info = SpatialInfo::default();
+    for b in boxes::visual_entry_children(buf, entry) {
+        match &b.name {
+            b"st3d" => {
+                let mut r = Reader::new(b.body(buf));
+                r.full_box()?;
+                info.stereo_mode = Some(r.u8()?);
+            }
+            b"sv3d" => read_sv3d(buf, &b, &mut info)?,
+            b"vexu" => read_vexu(buf, &b, &mut info)?,
+            b"hfov" => info.field_of_view = Some(Reader::new(b.body(buf)).u32()?),
+            _ => {}
+        }
+    }
+    Ok(info)
+}
+
+fn stereo_mode_name(stereo_mode: u8) -> &'static str {
+    match stereo_mode {
+        0 => "monoscopic",
+        1 => "top-bottom",
+        2 => "left-right",
+        3 => "stereo-custom",
+        4 => "right-left",
+        _ => "reserved",
+    }
+}
+
+fn hero_eye_name(hero_eye: u8) -> &'static str {
+    match hero_eye {
+        0 => "none",
+        1 => "left",
+        2 => "right",
+        _ => "reserved",
+    }
+}
+
+fn cubemap_layout_name(layout: u32) -> &'static str {
+    match layout {
+        0 => "3x2",
+        _ => "reserved",
+    }
+}
+
+/// Prints the `[media.track.video.spatial]` section when the sample entry has
+/// any stereoscopic or spherical metadata.
+pub fn print_report(buf: &[u8], entry: &Mp4Box) {
+    let info = match read_spatial_info(buf, entry) {
+        Ok(info) if info.is_empty() => return,
+        Ok(info) => info,
+        Err(e) => {
+            println!("[media.track.video.spatial]");
+            println!("error = \"{}\"", e);
+            return;
+        }
+    };
+    println!("[media.track.video.spatial]");
+    if let Some(stereo_mode) = info.stereo_mode {
+        println!("stereo_mode = {:?}", stereo_mode);
+        println!("stereo_mode_name = \"{}\"", stereo_mode_name(stereo_mode));
+    }
+    if let Some(source) = &info.metadata_source {
+        println!("metadata_source = {:?}", source);
+    }
+    if let Some((yaw, pitch, roll)) = info.pose {
+        println!("pose_yaw = {:.3}", yaw);
+        println!("pose_pitch = {:.3}", pitch);
+        println!("pose_roll = {:.3}", roll);
+    }
+    match info.projection {
+        Some(Projection::Equirectangular([top, bottom, left, right])) => {
+            println!("projection = \"equirectangular\"");
+            let fraction = |v: u32| f64::from(v) / 4_294_967_296.0;
+            println!("bounds_top = {:.6}", fraction(top));
+            println!("bounds_bottom = {:.6}", fraction(bottom));
+            println!("bounds_left = {:.6}", fraction(left));
+            println!("bounds_right = {:.6}", fraction(right));
+        }
+        Some(Projection::Cubemap(layout, padding)) => {
+            println!("projection = \"cubemap\"");
+            println!("cubemap_layout = \"{}\"", cubemap_layout_name(layout));
+            println!("cubemap_padding = {:?}", padding);
+        }
+        Some(Projection::Mesh(encoding)) => {
+            println!("projection = \"mesh\"");
+            println!("mesh_encoding = \"{}\"", String::from_utf8_lossy(&encoding));
+        }
+        None => {}
+    }
+    if let Some(views) = info.stereo_views {
+        println!("has_left_eye = {:?}", views & 0x1 != 0);
+        println!("has_right_eye = {:?}", views & 0x2 != 0);
+        println!("eye_views_reversed = {:?}", views & 0x8 != 0);
+        println!("has_additional_views = {:?}", views & 0x4 != 0);
+    }
+    if let Some(hero_eye) = info.hero_eye {
+        println!("hero_eye = \"{}\"", hero_eye_name(hero_eye));
+    }
+    if let Some(baseline) = info.baseline {
+        println!("baseline_mm = {:.3}", f64::from(baseline) / 1000.0);
+    }
+    if let Some(disparity) = info.disparity_adjustment {
+        println!(
+            "disparity_adjustment = {:.4}",
+            f64::from(disparity) / 10000.0
+        );
+    }
+    if let Some(kind) = info.projection_kind {
+        println!("projection_kind = \"{}\"", String::from_utf8_lossy(&kind));
+    }
+    if let Some(field_of_view) = info.field_of_view {
+        println!(
+            "horizontal_field_of_view = {:.3}",
+            f64::from(field_of_view) / 1000.0
+        );
+    }
+}
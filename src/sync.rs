@@ -0,0 +1,189 @@
+//! Audio/video sync and start offset report.
+//!
+//! Each track's presentation start is derived from its edit list and the
+//! earliest composition timestamp (`stts` + `ctts`) of its samples.
+
+use crate::boxes;
+use crate::sample_table;
+use mp4parse::{MediaContext, TrackType};
+use std::error::Error;
+
+/// Presentation timing of a single track.
+#[derive(Debug, Clone, Copy)]
+pub struct TrackTiming {
+    /// track id from tkhd
+    pub track_id: u32,
+    /// true for video tracks, false for audio tracks
+    pub is_video: bool,
+    /// track (mdhd) timescale
+    pub timescale: u64,
+    /// number of edit list entries
+    pub edits: usize,
+    /// leading empty edit duration in seconds
+    pub empty_duration: f64,
+    /// media time of the first non-empty edit in track timescale units
+    pub media_time: i64,
+    /// earliest sample composition time in track timescale units
+    pub first_composition_time: i64,
+    /// presentation start in seconds
+    pub start_time: f64,
+    /// presented duration in seconds; the edited duration when an edit list is present
+    pub duration: f64,
+}
+
+/// Sync report for all audio and video tracks.
+#[derive(Debug, Clone)]
+pub struct AvSync {
+    /// movie (mvhd) duration in seconds
+    pub movie_duration: f64,
+    /// per track timing
+    pub tracks: Vec<TrackTiming>,
+}
+
+impl AvSync {
+    /// Start of the first audio track minus start of the first video track, in seconds.
+    /// Positive values mean audio starts after video.
+    pub fn av_offset(&self) -> Option<f64> {
+        let audio = self.tracks.iter().find(|t| !t.is_video)?;
+        let video = self.tracks.iter().find(|t| t.is_video)?;
+        Some(audio.start_time - video.start_time)
+    }
+
+    /// Largest difference between a track duration and the movie duration, in seconds.
+    pub fn max_duration_mismatch(&self) -> f64 {
+        self.tracks
+            .iter()
+            .map(|t| (t.duration - self.movie_duration).abs())
+            .fold(0.0, f64::max)
+    }
+
+    /// Returns true when both the A/V offset and duration mismatches are within `tolerance` seconds.
+    pub fn within_tolerance(&self, tolerance: f64) -> bool {
+        self.av_offset().is_none_or(|o| o.abs() <= tolerance)
+            && self.max_duration_mismatch() <= tolerance
+    }
+}
+
+/// Computes presentation start times and durations for audio and video tracks.
+/// # Arguments
+/// * `buf` - Whole file contents
+/// * `context` - mp4parse context read from `buf`
+pub fn analyze(buf: &[u8], context: &MediaContext) -> Result<AvSync, Box<dyn Error>> {
+    let mvhd = boxes::read_mvhd(buf)?;
+    let movie_timescale = mvhd.timescale.max(1) as f64;
+    let traks = boxes::traks(buf);
+    let mut tracks = Vec::new();
+    for (track, trak) in context.tracks.iter().zip(traks.iter()) {
+        let is_video = match track.track_type {
+            TrackType::Video => true,
+            TrackType::Audio => false,
+            _ => continue,
+        };
+        let timescale = track.timescale.map_or(1, |t| t.0.max(1));
+        let edits = boxes::read_elst(buf, trak)?;
+        let first_composition_time = sample_table::first_composition_time(track).unwrap_or(0);
+
+        let empty: u64 = edits
+            .iter()
+            .take_while(|e| e.media_time == -1)
+            .map(|e| e.segment_duration)
+            .sum();
+        let empty_duration = empty as f64 / movie_timescale;
+        let (media_time, start_time, duration) = match edits.iter().find(|e| e.media_time != -1) {
+            Some(edit) => {
+                let skipped = (first_composition_time - edit.media_time).max(0);
+                let edited: u64 = edits.iter().map(|e| e.segment_duration).sum();
+                (
+                    edit.media_time,
+                    empty_duration + skipped as f64 / timescale as f64,
+                    edited as f64 / movie_timescale,
+                )
+            }
+            None => (
+                0,
+                empty_duration + first_composition_time as f64 / timescale as f64,
+                empty_duration + track.duration.map_or(0, |d| d.0) as f64 / timescale as f64,
+            ),
+        };
+        tracks.push(TrackTiming {
+            track_id: track.track_id.unwrap_or(0),
+            is_video,
+            timescale,
+            edits: edits.len(),
+            empty_duration,
+            media_time,
+            first_composition_time,
+            start_time,
+            duration,
+        });
+    }
+    Ok(AvSync {
+        movie_duration: mvhd.duration as f64 / movie_timescale,
+        tracks,
+    })
+}
+
+/// Prints the `[media.av_sync]` section.
+/// # Arguments
+/// * `sync` - Report from `analyze`
+/// * `tolerance` - Allowed offset and duration mismatch in seconds
+pub fn print_report(sync: &AvSync, tolerance: f64) {
+    println!("[media.av_sync]");
+    println!("movie_duration = {:.6}", sync.movie_duration);
+    println!("tolerance_ms = {:.3}", tolerance * 1000.0);
+    for t in &sync.tracks {
+        println!("[media.av_sync.track]");
+        println!("track_id = {:?}", t.track_id);
+        println!("type = \"{}\"", if t.is_video { "video" } else { "audio" });
+        println!("timescale = {:?}", t.timescale);
+        println!("edits = {:?}", t.edits);
+        println!("empty_duration = {:.6}", t.empty_duration);
+        println!("media_time = {:?}", t.media_time);
+        println!("first_composition_time = {:?}", t.first_composition_time);
+        println!("start_time = {:.6}", t.start_time);
+        println!("duration = {:.6}", t.duration);
+        println!(
+            "duration_mismatch_ms = {:.3}",
+            (t.duration - sync.movie_duration) * 1000.0
+        );
+    }
+    println!("[media.av_sync.result]");
+    if let Some(offset) = sync.av_offset() {
+        println!("av_offset_ms = {:.3}", offset * 1000.0);
+    }
+    println!(
+        "max_duration_mismatch_ms = {:.3}",
+        sync.max_duration_mismatch() * 1000.0
+    );
+    println!("within_tolerance = {:?}", sync.within_tolerance(tolerance));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timing(is_video: bool, start_time: f64, duration: f64) -> TrackTiming {
+        TrackTiming {
+            track_id: 1,
+            is_video,
+            timescale: 1000,
+            edits: 0,
+            empty_duration: 0.0,
+            media_time: 0,
+            first_composition_time: 0,
+            start_time,
+            duration,
+        }
+    }
+
+    #[test]
+    fn unit_av_offset_exceeds_tolerance() {
+        let sync = AvSync {
+            movie_duration: 10.0,
+            tracks: vec![timing(true, 0.0, 10.0), timing(false, 0.1, 10.0)],
+        };
+        assert!((sync.av_offset().unwrap() - 0.1).abs() < 1e-9);
+        assert!(!sync.within_tolerance(0.04));
+        assert!(sync.within_tolerance(0.2));
+    }
+}
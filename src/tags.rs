@@ -0,0 +1,416 @@
+//! iTunes style metadata from `moov/udta/meta/ilst` and QuickTime `©xxx` user
+//! data text atoms.
+//!
+//! mp4parse only keeps a subset of the well known `ilst` items and drops
+//! freeform `----` items, so the item list is walked directly.
+
+use crate::boxes::{self, Mp4Box, Reader};
+use crate::cover;
+use std::io::Result;
+use std::path::PathBuf;
+
+/// `data` well-known type of a UTF-8 string.
+const TYPE_UTF8: u32 = 1;
+/// `data` well-known type of a UTF-16 string.
+const TYPE_UTF16: u32 = 2;
+/// `data` well-known type of a JPEG image.
+const TYPE_JPEG: u32 = 13;
+/// `data` well-known type of a PNG image.
+const TYPE_PNG: u32 = 14;
+/// `data` well-known type of a big endian signed integer.
+const TYPE_BE_SIGNED: u32 = 21;
+/// `data` well-known type of a big endian unsigned integer.
+const TYPE_BE_UNSIGNED: u32 = 22;
+/// `data` well-known type of a big endian 32-bit float.
+const TYPE_BE_FLOAT32: u32 = 23;
+/// `data` well-known type of a big endian 64-bit float.
+const TYPE_BE_FLOAT64: u32 = 24;
+/// `data` well-known type of a BMP image.
+const TYPE_BMP: u32 = 27;
+
+/// Value of one `data` atom.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TagValue {
+    /// UTF-8 or UTF-16 text
+    Text(String),
+    /// integer value
+    Integer(i64),
+    /// floating point value
+    Float(f64),
+    /// number and total, as stored by `trkn` and `disk`
+    Pair(u16, u16),
+    /// embedded image
+    Image(Image),
+    /// payload of any other type: the well-known type and size in bytes
+    Binary(u32, usize),
+}
+
+/// Image payload of a `covr` `data` atom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Image {
+    /// `data` well-known type
+    pub data_type: u32,
+    /// offset of the image bytes from the start of the buffer
+    pub offset: usize,
+    /// size of the image in bytes
+    pub size: usize,
+}
+
+impl Image {
+    /// Image bytes within the file buffer.
+    pub fn bytes<'a>(&self, buf: &'a [u8]) -> &'a [u8] {
+        &buf[self.offset..self.offset + self.size]
+    }
+
+    /// Image format detected from the file signature, falling back to the `data` type.
+    pub fn format(&self, buf: &[u8]) -> &'static str {
+        signature_format(self.bytes(buf))
+            .or_else(|| type_format(self.data_type))
+            .unwrap_or("unknown")
+    }
+
+    /// Warning when the `data` type names a different image format than the signature.
+    pub fn type_mismatch(&self, buf: &[u8]) -> Option<String> {
+        match (
+            type_format(self.data_type),
+            signature_format(self.bytes(buf)),
+        ) {
+            (Some(declared), Some(detected)) if declared != detected => Some(format!(
+                "covr data type is {} but the image is {}",
+                declared, detected
+            )),
+            _ => None,
+        }
+    }
+}
+
+fn signature_format(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+        Some("jpeg")
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("png")
+    } else if bytes.starts_with(b"BM") {
+        Some("bmp")
+    } else {
+        None
+    }
+}
+
+fn type_format(data_type: u32) -> Option<&'static str> {
+    match data_type {
+        TYPE_JPEG => Some("jpeg"),
+        TYPE_PNG => Some("png"),
+        TYPE_BMP => Some("bmp"),
+        _ => None,
+    }
+}
+
+/// A metadata item and its values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tag {
+    /// item atom type, e.g. `©nam`, `trkn` or `----`
+    pub atom: [u8; 4],
+    /// `mean` of a freeform item, its reverse DNS namespace
+    pub mean: Option<String>,
+    /// `name` of a freeform item
+    pub name: Option<String>,
+    /// one value per `data` atom
+    pub values: Vec<TagValue>,
+}
+
+impl Tag {
+    /// Returns true when the item has the given atom type.
+    pub fn is(&self, atom: &[u8; 4]) -> bool {
+        &self.atom == atom
+    }
+}
+
+/// Item atoms whose untyped `data` holds an integer.
+const INTEGER_ATOMS: [&[u8; 4]; 12] = [
+    b"cpil", b"pgap", b"pcst", b"hdvd", b"stik", b"rtng", b"tmpo", b"gnre", b"tvsn", b"tves",
+    b"akID", b"sfID",
+];
+
+/// Item atom display name, mapping the 0xa9 prefix byte to `©`.
+pub fn atom_name(atom: &[u8; 4]) -> String {
+    atom.iter().map(|&b| char::from(b)).collect()
+}
+
+/// Key printed for a well known item atom.
+fn key_name(atom: &[u8; 4]) -> Option<&'static str> {
+    Some(match atom {
+        b"\xa9nam" => "title",
+        b"\xa9ART" => "artist",
+        b"aART" => "album_artist",
+        b"\xa9alb" => "album",
+        b"\xa9day" => "date",
+        b"\xa9too" => "encoder",
+        b"\xa9enc" => "encoded_by",
+        b"\xa9wrt" => "composer",
+        b"\xa9cmt" => "comment",
+        b"\xa9gen" => "genre",
+        b"gnre" => "genre_id",
+        b"\xa9grp" => "grouping",
+        b"\xa9lyr" => "lyrics",
+        b"trkn" => "track",
+        b"disk" => "disk",
+        b"desc" => "description",
+        b"ldes" => "long_description",
+        b"tvsh" => "tv_show",
+        b"tven" => "tv_episode_id",
+        b"tvnn" => "tv_network",
+        b"tvsn" => "tv_season",
+        b"tves" => "tv_episode",
+        b"stik" => "media_kind",
+        b"cprt" => "copyright",
+        b"cpil" => "compilation",
+        b"pgap" => "gapless_playback",
+        b"tmpo" => "tempo",
+        b"rtng" => "rating",
+        b"hdvd" => "hd_video",
+        b"purd" => "purchase_date",
+        b"pcst" => "podcast",
+        b"purl" => "podcast_url",
+        b"egid" => "episode_guid",
+        b"catg" => "category",
+        b"keyw" => "keyword",
+        b"sonm" => "sort_title",
+        b"soar" => "sort_artist",
+        b"soaa" => "sort_album_artist",
+        b"soal" => "sort_album",
+        b"soco" => "sort_composer",
+        b"sosn" => "sort_tv_show",
+        _ => return None,
+    })
+}
+
+fn read_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).to_string()
+}
+
+fn read_integer(bytes: &[u8], signed: bool) -> Option<i64> {
+    let mut value: u64 = 0;
+    for &b in bytes {
+        value = value << 8 | u64::from(b);
+    }
+    match (bytes.len(), signed) {
+        (1, true) => Some(i64::from(value as u8 as i8)),
+        (2, true) => Some(i64::from(value as u16 as i16)),
+        (4, true) => Some(i64::from(value as u32 as i32)),
+        (8, _) => Some(value as i64),
+        (1..=4, _) => Some(value as i64),
+        _ => None,
+    }
+}
+
+/// Parses the payload of a `data` atom of the `atom` item.
+fn read_data(atom: &[u8; 4], buf: &[u8], data: &Mp4Box) -> Result<TagValue> {
+    let body = data.body(buf);
+    let mut r = Reader::new(body);
+    let data_type = r.u32()? & 0x00ff_ffff;
+    r.skip(4)?;
+    let offset = data.body_offset() + 8;
+    let payload = r.bytes(r.remaining())?;
+    let binary = TagValue::Binary(data_type, payload.len());
+    Ok(match data_type {
+        _ if atom == b"covr" => TagValue::Image(Image {
+            data_type,
+            offset,
+            size: payload.len(),
+        }),
+        TYPE_UTF8 => TagValue::Text(read_string(payload)),
+        TYPE_UTF16 => {
+            let units: Vec<u16> = payload
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .take_while(|&u| u != 0)
+                .collect();
+            TagValue::Text(String::from_utf16_lossy(&units))
+        }
+        TYPE_BE_FLOAT32 if payload.len() == 4 => TagValue::Float(f64::from(f32::from_be_bytes([
+            payload[0], payload[1], payload[2], payload[3],
+        ]))),
+        TYPE_BE_FLOAT64 if payload.len() == 8 => {
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(payload);
+            TagValue::Float(f64::from_be_bytes(bytes))
+        }
+        TYPE_BE_SIGNED | TYPE_BE_UNSIGNED => {
+            read_integer(payload, data_type == TYPE_BE_SIGNED).map_or(binary, TagValue::Integer)
+        }
+        0 if atom == b"trkn" || atom == b"disk" => {
+            let mut r = Reader::new(payload);
+            r.skip(2)?;
+            TagValue::Pair(r.u16()?, r.u16()?)
+        }
+        0 if INTEGER_ATOMS.contains(&atom) => {
+            read_integer(payload, false).map_or(binary, TagValue::Integer)
+        }
+        _ => binary,
+    })
+}
+
+/// Reads the items of an `ilst` box, in first appearance order.
+pub fn read_ilst(buf: &[u8], ilst: &Mp4Box) -> Result<Vec<Tag>> {
+    let mut tags = Vec::new();
+    for item in boxes::children(buf, ilst) {
+        let mut tag = Tag {
+            atom: item.name,
+            mean: None,
+            name: None,
+            values: Vec::new(),
+        };
+        for b in boxes::children(buf, &item) {
+            match &b.name {
+                b"data" => tag.values.push(read_data(&item.name, buf, &b)?),
+                b"mean" | b"name" => {
+                    let mut r = Reader::new(b.body(buf));
+                    r.full_box()?;
+                    let value = read_string(r.bytes(r.remaining())?);
+                    if b.is(b"mean") {
+                        tag.mean = Some(value);
+                    } else {
+                        tag.name = Some(value);
+                    }
+                }
+                _ => {}
+            }
+        }
+        // repeated items are merged so every key is printed once
+        match tags
+            .iter_mut()
+            .find(|t: &&mut Tag| t.is(&tag.atom) && !tag.is(b"----"))
+        {
+            Some(existing) => existing.values.extend(tag.values),
+            None => tags.push(tag),
+        }
+    }
+    Ok(tags)
+}
+
+/// Reads a QuickTime user data text atom: one or more length, language and text records.
+fn read_udta_text(buf: &[u8], b: &Mp4Box) -> Result<Tag> {
+    let mut r = Reader::new(b.body(buf));
+    let mut values = Vec::new();
+    while r.remaining() >= 4 {
+        let length = usize::from(r.u16()?);
+        r.skip(2)?;
+        values.push(TagValue::Text(read_string(r.bytes(length)?)));
+    }
+    Ok(Tag {
+        atom: b.name,
+        mean: None,
+        name: None,
+        values,
+    })
+}
+
+/// Reads the movie level `udta` metadata: the `meta/ilst` items followed by
+/// QuickTime `©xxx` text atoms that no `ilst` item already covers.
+pub fn read_tags(buf: &[u8]) -> Result<Vec<Tag>> {
+    let udta =
+        match boxes::find_top(buf, b"moov").and_then(|moov| boxes::child(buf, &moov, b"udta")) {
+            Some(udta) => udta,
+            None => return Ok(Vec::new()),
+        };
+    let mut tags = Vec::new();
+    if let Some(ilst) = boxes::find_path(buf, &udta, &[b"meta", b"ilst"]) {
+        tags.extend(read_ilst(buf, &ilst)?);
+    }
+    for b in boxes::children(buf, &udta) {
+        // tools often write the same item to both places
+        if b.name[0] == 0xa9 && !tags.iter().any(|t| t.is(&b.name)) {
+            tags.push(read_udta_text(buf, &b)?);
+        }
+    }
+    Ok(tags)
+}
+
+/// Images of the `covr` items, in file order.
+pub fn covers(tags: &[Tag]) -> Vec<Image> {
+    tags.iter()
+        .filter(|t| t.is(b"covr"))
+        .flat_map(|t| t.values.iter())
+        .filter_map(|v| match v {
+            TagValue::Image(image) => Some(*image),
+            _ => None,
+        })
+        .collect()
+}
+
+fn format_value(buf: &[u8], value: &TagValue) -> String {
+    match value {
+        TagValue::Text(text) => format!("{:?}", text),
+        TagValue::Integer(value) => format!("{:?}", value),
+        TagValue::Float(value) => format!("{:?}", value),
+        TagValue::Pair(number, total) => format!("[{}, {}]", number, total),
+        TagValue::Image(image) => format!(
+            "{{ format = \"{}\", size = {} }}",
+            image.format(buf),
+            image.size
+        ),
+        TagValue::Binary(data_type, size) => {
+            format!("{{ data_type = {}, size = {} }}", data_type, size)
+        }
+    }
+}
+
+fn format_values(buf: &[u8], values: &[TagValue]) -> String {
+    match values {
+        [value] => format_value(buf, value),
+        _ => {
+            let values: Vec<String> = values.iter().map(|v| format_value(buf, v)).collect();
+            format!("[{}]", values.join(", "))
+        }
+    }
+}
+
+/// Prints `[media.tags]` with one key per item, followed by a
+/// `[media.tags.cover]` section per cover image and a `[media.tags.freeform]`
+/// section per freeform item.
+/// # Arguments
+/// * `buf` - Whole file contents
+/// * `tags` - Tags read by `read_tags`
+/// * `cover_paths` - Files the cover images were extracted to, in `covers` order
+pub fn print_report(buf: &[u8], tags: &[Tag], cover_paths: &[PathBuf]) {
+    if tags.is_empty() {
+        return;
+    }
+    println!("[media.tags]");
+    for tag in tags.iter().filter(|t| !t.is(b"covr") && !t.is(b"----")) {
+        match key_name(&tag.atom) {
+            Some(key) => println!("{} = {}", key, format_values(buf, &tag.values)),
+            None => println!(
+                "{:?} = {}",
+                atom_name(&tag.atom),
+                format_values(buf, &tag.values)
+            ),
+        }
+    }
+    for (index, image) in covers(tags).iter().enumerate() {
+        println!("[media.tags.cover]");
+        println!("format = \"{}\"", image.format(buf));
+        println!("size = {:?}", image.size);
+        if let Some((width, height)) = cover::dimensions(image.bytes(buf)) {
+            println!("width = {:?}", width);
+            println!("height = {:?}", height);
+        }
+        if let Some(path) = cover_paths.get(index) {
+            println!("path = {:?}", path.display().to_string());
+        }
+        if let Some(warning) = image.type_mismatch(buf) {
+            println!("warning = {:?}", warning);
+        }
+    }
+    for tag in tags.iter().filter(|t| t.is(b"----")) {
+        println!("[media.tags.freeform]");
+        if let Some(mean) = &tag.mean {
+            println!("mean = {:?}", mean);
+        }
+        if let Some(name) = &tag.name {
+            println!("name = {:?}", name);
+        }
+        println!("value = {}", format_values(buf, &tag.values));
+    }
+}
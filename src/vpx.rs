@@ -0,0 +1,127 @@
+//! VP8/VP9 codec configuration (`vpcC`).
+//!
+//! mp4parse keeps profile, level, transfer, matrix and range private, so the
+//! record is read from the raw box. See "VP Codec ISO Media File Format Binding" § 2.2.
+
+use crate::boxes::Reader;
+use crate::colour;
+use std::io::Result;
+
+/// VPCodecConfigurationRecord.
+#[derive(Debug, Clone, Default)]
+pub struct VpxConfig {
+    /// box version, 0 for the original draft layout
+    pub version: u8,
+    /// profile
+    pub profile: u8,
+    /// level
+    pub level: u8,
+    /// bitDepth
+    pub bit_depth: u8,
+    /// chromaSubsampling
+    pub chroma_subsampling: u8,
+    /// videoFullRangeFlag
+    pub full_range: bool,
+    /// colourPrimaries, version 1 only
+    pub colour_primaries: Option<u8>,
+    /// transferCharacteristics
+    pub transfer_characteristics: u8,
+    /// matrixCoefficients, version 1 only
+    pub matrix_coefficients: Option<u8>,
+    /// codecInitializationData
+    pub codec_init_data: Vec<u8>,
+}
+
+/// Parses a `vpcC` payload.
+pub fn read_config(vpcc: &[u8]) -> Result<VpxConfig> {
+    let mut r = Reader::new(vpcc);
+    let (version, _) = r.full_box()?;
+    let mut config = VpxConfig {
+        version,
+        profile: r.u8()?,
+        level: r.u8()?,
+        ..Default::default()
+    };
+    if version == 0 {
+        // bitDepth(4) colorSpace(4) chromaSubsampling(4) transferFunction(3) videoFullRangeFlag(1)
+        config.bit_depth = r.u8()? >> 4;
+        let b = r.u8()?;
+        config.chroma_subsampling = b >> 4;
+        config.transfer_characteristics = (b >> 1) & 0x7;
+        config.full_range = b & 1 == 1;
+    } else {
+        let b = r.u8()?;
+        config.bit_depth = b >> 4;
+        config.chroma_subsampling = (b >> 1) & 0x7;
+        config.full_range = b & 1 == 1;
+        config.colour_primaries = Some(r.u8()?);
+        config.transfer_characteristics = r.u8()?;
+        config.matrix_coefficients = Some(r.u8()?);
+    }
+    let size = usize::from(r.u16()?);
+    config.codec_init_data = r.bytes(size)?.to_vec();
+    Ok(config)
+}
+
+fn chroma_subsampling_name(value: u8) -> &'static str {
+    match value {
+        0 => "4:2:0 vertical",
+        1 => "4:2:0 colocated",
+        2 => "4:2:2",
+        3 => "4:4:4",
+        _ => "reserved",
+    }
+}
+
+/// Prints the `[media.track.video.vpx]` section.
+pub fn print_report(vpcc: &[u8]) {
+    println!("[media.track.video.vpx]");
+    let config = match read_config(vpcc) {
+        Ok(config) => config,
+        Err(e) => {
+            println!("error = \"{}\"", e);
+            return;
+        }
+    };
+    println!("version = {:?}", config.version);
+    println!("profile = {:?}", config.profile);
+    println!("level = {:?}", config.level);
+    println!("bit_depth = {:?}", config.bit_depth);
+    println!("chroma_subsampling = {:?}", config.chroma_subsampling);
+    println!(
+        "chroma_subsampling_name = \"{}\"",
+        chroma_subsampling_name(config.chroma_subsampling)
+    );
+    println!("full_range = {:?}", config.full_range);
+    if let Some(v) = config.colour_primaries {
+        println!("colour_primaries = {:?}", v);
+        println!(
+            "colour_primaries_name = \"{}\"",
+            colour::primaries_name(u32::from(v))
+        );
+    }
+    println!(
+        "transfer_characteristics = {:?}",
+        config.transfer_characteristics
+    );
+    println!(
+        "transfer_characteristics_name = \"{}\"",
+        colour::transfer_name(u32::from(config.transfer_characteristics))
+    );
+    if let Some(v) = config.matrix_coefficients {
+        println!("matrix_coefficients = {:?}", v);
+        println!(
+            "matrix_coefficients_name = \"{}\"",
+            colour::matrix_name(u32::from(v))
+        );
+    }
+    println!("codec_init_data_size = {:?}", config.codec_init_data.len());
+    if !config.codec_init_data.is_empty() {
+        let hex: String = config
+            .codec_init_data
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        println!("codec_init_data = \"{}\"", hex);
+    }
+}
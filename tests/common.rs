@@ -30,6 +30,770 @@ async fn get_temp_file(url: String, file_name: String) -> Result<std::ffi::OsStr
     Ok(env::temp_dir().join(&file_path).into_os_string())
 }
 
+/// Copies `src` to `dst` with its top level boxes reordered as ftyp, mdat, moov.
+/// `stco` chunk offsets are moved back by the size of moov to follow the mdat.
+pub fn write_moov_at_end(src: &str, dst: &Path) {
+    let buf = std::fs::read(src).unwrap();
+    let mut boxes = Vec::new();
+    let mut offset = 0;
+    while offset + 8 <= buf.len() {
+        let mut size = u32::from_be_bytes(buf[offset..offset + 4].try_into().unwrap()) as usize;
+        if size == 1 {
+            size = u64::from_be_bytes(buf[offset + 8..offset + 16].try_into().unwrap()) as usize;
+        }
+        boxes.push((
+            &buf[offset + 4..offset + 8],
+            buf[offset..offset + size].to_vec(),
+        ));
+        offset += size;
+    }
+    for (name, bytes) in boxes.iter_mut() {
+        if *name != b"moov" {
+            continue;
+        }
+        let moov_size = bytes.len() as u32;
+        let mut i = 4;
+        while i + 12 <= bytes.len() {
+            if &bytes[i..i + 4] == b"stco" {
+                let count = u32::from_be_bytes(bytes[i + 8..i + 12].try_into().unwrap()) as usize;
+                for n in 0..count {
+                    let at = i + 12 + n * 4;
+                    let chunk = u32::from_be_bytes(bytes[at..at + 4].try_into().unwrap());
+                    bytes[at..at + 4].copy_from_slice(&(chunk - moov_size).to_be_bytes());
+                }
+            }
+            i += 1;
+        }
+    }
+    let mut out = Vec::new();
+    for name in [&b"ftyp"[..], &b"mdat"[..], &b"moov"[..]] {
+        for (box_name, bytes) in &boxes {
+            if *box_name == name {
+                out.extend_from_slice(bytes);
+            }
+        }
+    }
+    std::fs::write(dst, out).unwrap();
+}
+
+/// Serialises a box with an 8 byte header.
+pub fn mp4_box(name: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut out = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+    out.extend_from_slice(name);
+    out.extend_from_slice(body);
+    out
+}
+
+/// Serialises a full box with the given version and flags.
+pub fn full_box(name: &[u8; 4], version: u8, flags: u32, body: &[u8]) -> Vec<u8> {
+    let mut content = (flags | (u32::from(version) << 24)).to_be_bytes().to_vec();
+    content.extend_from_slice(body);
+    mp4_box(name, &content)
+}
+
+fn be32(values: &[u32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_be_bytes()).collect()
+}
+
+/// The avc1 `stsd` box of the 854x480 test file.
+pub fn video_stsd() -> Vec<u8> {
+    let buf = std::fs::read(TEST_BOKEH_AU_0T_VD_30F_854X480_MP4_FILE).unwrap();
+    let avc1 = buf.windows(4).position(|w| w == b"avc1").unwrap();
+    let start = avc1 - 20;
+    let size = u32::from_be_bytes(buf[start..start + 4].try_into().unwrap()) as usize;
+    buf[start..start + size].to_vec()
+}
+
+/// A movie fragment of the single video track written by `init_segment`.
+#[derive(Debug, Clone)]
+pub struct FragmentSpec {
+    pub sequence_number: u32,
+    pub base_media_decode_time: u64,
+    pub sample_sizes: Vec<u32>,
+}
+
+/// A `stsd` holding one visual sample entry with the given child boxes.
+pub fn visual_stsd(fourcc: &[u8; 4], width: u16, height: u16, children: &[u8]) -> Vec<u8> {
+    let mut entry = vec![0, 0, 0, 0, 0, 0, 0, 1];
+    entry.extend_from_slice(&[0; 16]);
+    entry.extend_from_slice(&width.to_be_bytes());
+    entry.extend_from_slice(&height.to_be_bytes());
+    entry.extend_from_slice(&be32(&[0x48_0000, 0x48_0000, 0]));
+    entry.extend_from_slice(&[0, 1]);
+    entry.extend_from_slice(&[0; 32]);
+    entry.extend_from_slice(&[0, 0x18, 0xff, 0xff]);
+    entry.extend_from_slice(children);
+    full_box(
+        b"stsd",
+        0,
+        0,
+        &[&be32(&[1])[..], &mp4_box(fourcc, &entry)].concat(),
+    )
+}
+
+/// An hvc1 `stsd` for 1920x1080 Main profile level 4.1 with BT.2020 PQ colour in the SPS VUI.
+pub fn hvc1_stsd() -> Vec<u8> {
+    hevc_stsd(b"hvc1", &[])
+}
+
+/// A Dolby Vision configuration box for `profile` and level 6 with RPU and base layer present.
+pub fn dovi_config(name: &[u8; 4], profile: u16, bl_signal_compatibility_id: u8) -> Vec<u8> {
+    let mut body = vec![1, 0];
+    body.extend_from_slice(&(profile << 9 | 6 << 3 | 0x5).to_be_bytes());
+    body.push(bl_signal_compatibility_id << 4);
+    body.extend_from_slice(&[0; 19]);
+    mp4_box(name, &body)
+}
+
+/// The `hvc1_stsd` configuration under the `fourcc` sample entry, followed by `children`.
+pub fn hevc_stsd(fourcc: &[u8; 4], children: &[u8]) -> Vec<u8> {
+    let sps = [
+        0x42, 0x01, 0x01, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0x90, 0x00, 0x00, 0x03, 0x00, 0x00,
+        0x03, 0x00, 0x7b, 0xa0, 0x03, 0xc0, 0x80, 0x11, 0x07, 0xcb, 0x96, 0x57, 0x92, 0x4d, 0x9a,
+        0xf7, 0x78, 0x0b, 0x50, 0x91, 0x00, 0x90, 0x40, 0x00, 0x00, 0xfa, 0x40, 0x00, 0x3a, 0x98,
+        0x02,
+    ];
+    let mut hvcc = vec![1, 0x01, 0x60, 0, 0, 0, 0x90, 0, 0, 0, 0, 0, 123];
+    hvcc.extend_from_slice(&[0xf0, 0, 0xfc, 0xfd, 0xf8, 0xf8, 0, 0, 0x0f, 3]);
+    for (nal_unit_type, nal) in [
+        (0xa0, &[0x40, 0x01, 0x0c, 0x01][..]),
+        (0xa1, &sps[..]),
+        (0xa2, &[0x44, 0x01, 0xc1, 0x72][..]),
+    ] {
+        hvcc.extend_from_slice(&[nal_unit_type, 0, 1]);
+        hvcc.extend_from_slice(&(nal.len() as u16).to_be_bytes());
+        hvcc.extend_from_slice(nal);
+    }
+    visual_stsd(
+        fourcc,
+        1920,
+        1080,
+        &[&mp4_box(b"hvcC", &hvcc)[..], children].concat(),
+    )
+}
+
+/// An av01 `stsd` for 1920x1080 main profile level 4.0 with a sequence header OBU.
+pub fn av01_stsd() -> Vec<u8> {
+    let mut av1c = vec![0x81, 0x08, 0x0c, 0x00];
+    av1c.extend_from_slice(&[
+        0x0a, 0x0e, 0x00, 0x00, 0x00, 0x42, 0xab, 0xbf, 0xc3, 0x73, 0xff, 0xe6, 0x40, 0x40, 0x40,
+        0x41,
+    ]);
+    visual_stsd(b"av01", 1920, 1080, &mp4_box(b"av1C", &av1c))
+}
+
+/// `stsd` with a 3840x2160 vp09 entry: profile 2, level 5.1, 10-bit 4:2:0 colocated,
+/// limited range BT.2020 PQ.
+pub fn vp09_stsd() -> Vec<u8> {
+    let vpcc = [1, 0, 0, 0, 2, 51, 0xa2, 9, 16, 9, 0, 0];
+    visual_stsd(b"vp09", 3840, 2160, &mp4_box(b"vpcC", &vpcc))
+}
+
+/// The `vp09_stsd` entry with HDR10 colour boxes: nclx BT.2020 PQ, a P3-D65 1000 nit
+/// mastering display, MaxCLL 1000 and MaxFALL 400, square pixels, a full clean
+/// aperture and a bitrate box.
+pub fn hdr_vp09_stsd() -> Vec<u8> {
+    let vpcc = mp4_box(b"vpcC", &[1, 0, 0, 0, 2, 51, 0xa2, 9, 16, 9, 0, 0]);
+    let mut colr = b"nclx".to_vec();
+    colr.extend_from_slice(&[0, 9, 0, 16, 0, 9, 0]);
+    let mut mdcv: Vec<u8> = [8500u16, 39850, 6550, 2300, 35400, 14600, 15635, 16450]
+        .iter()
+        .flat_map(|v| v.to_be_bytes())
+        .collect();
+    mdcv.extend_from_slice(&be32(&[10_000_000, 50]));
+    let children = [
+        vpcc,
+        mp4_box(b"colr", &colr),
+        mp4_box(b"mdcv", &mdcv),
+        mp4_box(b"clli", &[0x03, 0xe8, 0x01, 0x90]),
+        mp4_box(b"pasp", &be32(&[1, 1])),
+        mp4_box(b"clap", &be32(&[3840, 1, 2160, 1, 0, 1, 0, 1])),
+        mp4_box(b"btrt", &be32(&[0, 25_000_000, 18_000_000])),
+    ]
+    .concat();
+    visual_stsd(b"vp09", 3840, 2160, &children)
+}
+
+/// `stsd` with a 3840x3840 vp09 entry carrying top-bottom `st3d` and an `sv3d`
+/// equirectangular projection with a 90 degree yaw and 0.25 top and bottom bounds.
+pub fn spherical_vp09_stsd() -> Vec<u8> {
+    let vpcc = mp4_box(b"vpcC", &[1, 0, 0, 0, 0, 40, 0x82, 1, 1, 1, 0, 0]);
+    let svhd = full_box(b"svhd", 0, 0, b"mpn test\0");
+    let prhd = full_box(b"prhd", 0, 0, &be32(&[90 << 16, 0, 0]));
+    let equi = full_box(b"equi", 0, 0, &be32(&[0x4000_0000, 0x4000_0000, 0, 0]));
+    let proj = mp4_box(b"proj", &[prhd, equi].concat());
+    let children = [
+        vpcc,
+        full_box(b"st3d", 0, 0, &[1]),
+        mp4_box(b"sv3d", &[svhd, proj].concat()),
+    ]
+    .concat();
+    visual_stsd(b"vp09", 3840, 3840, &children)
+}
+
+/// The `hvc1_stsd` entry with an Apple `vexu` stereo extension, reversed left and right eyes,
+/// left hero eye, a 63.5 mm baseline, 2% disparity adjustment and rectilinear
+/// projection, followed by a 65 degree `hfov`.
+pub fn stereo_hvc1_stsd() -> Vec<u8> {
+    let eyes = mp4_box(
+        b"eyes",
+        &[
+            full_box(b"stri", 0, 0, &[0xb]),
+            full_box(b"hero", 0, 0, &[1]),
+        ]
+        .concat(),
+    );
+    // an empty box that is not a full box, which readers must skip
+    let cams = mp4_box(
+        b"cams",
+        &[
+            mp4_box(b"xcam", &[]),
+            full_box(b"blin", 0, 0, &be32(&[63_500])),
+        ]
+        .concat(),
+    );
+    let cmfy = mp4_box(b"cmfy", &full_box(b"dadj", 0, 0, &be32(&[200])));
+    let proj = mp4_box(b"proj", &full_box(b"prji", 0, 0, b"rect"));
+    let children = [
+        mp4_box(b"vexu", &[eyes, cams, cmfy, proj].concat()),
+        mp4_box(b"hfov", &be32(&[65_000])),
+    ]
+    .concat();
+    hevc_stsd(b"hvc1", &children)
+}
+
+/// A `stsd` holding one audio sample entry with the given child boxes.
+pub fn audio_stsd(fourcc: &[u8; 4], channels: u16, sample_rate: u16, children: &[u8]) -> Vec<u8> {
+    let mut entry = vec![0, 0, 0, 0, 0, 0, 0, 1];
+    entry.extend_from_slice(&[0; 8]);
+    entry.extend_from_slice(&channels.to_be_bytes());
+    entry.extend_from_slice(&[0, 16, 0, 0, 0, 0]);
+    entry.extend_from_slice(&sample_rate.to_be_bytes());
+    entry.extend_from_slice(&[0, 0]);
+    entry.extend_from_slice(children);
+    full_box(
+        b"stsd",
+        0,
+        0,
+        &[&be32(&[1])[..], &mp4_box(fourcc, &entry)].concat(),
+    )
+}
+
+/// A `fLaC` `stsd` with a STREAMINFO for 10 seconds of 44.1 kHz 16-bit stereo.
+pub fn flac_stsd() -> Vec<u8> {
+    let mut dfla = vec![0x80, 0, 0, 34];
+    dfla.extend_from_slice(&[
+        0x10, 0x00, 0x10, 0x00, 0x00, 0x00, 0x10, 0x00, 0x36, 0xb0, 0x0a, 0xc4, 0x42, 0xf0, 0x00,
+        0x06, 0xba, 0xa8,
+    ]);
+    dfla.extend(0..16u8);
+    audio_stsd(b"fLaC", 2, 44100, &full_box(b"dfLa", 0, 0, &dfla))
+}
+
+/// An `Opus` `stsd` for 5.1 with the Vorbis channel mapping and -1 dB output gain.
+pub fn opus_stsd() -> Vec<u8> {
+    let dops = [
+        0, 6, 0x01, 0x38, 0, 0, 0xbb, 0x80, 0xff, 0x00, 1, 4, 2, 0, 4, 1, 2, 3, 5,
+    ];
+    audio_stsd(b"Opus", 6, 48000, &mp4_box(b"dOps", &dops))
+}
+
+/// An `alac` `stsd` with a 24-byte magic cookie for 48 kHz 24-bit stereo.
+pub fn alac_stsd() -> Vec<u8> {
+    let mut cookie = be32(&[4096]);
+    cookie.extend_from_slice(&[0, 24, 40, 10, 14, 2, 0, 255]);
+    cookie.extend_from_slice(&be32(&[13_000, 2_304_000, 48000]));
+    audio_stsd(b"alac", 2, 48000, &full_box(b"alac", 0, 0, &cookie))
+}
+
+/// An `ac-3` `stsd` for 48 kHz 5.1 at 448 kbit/s.
+pub fn ac3_stsd() -> Vec<u8> {
+    audio_stsd(b"ac-3", 2, 48000, &mp4_box(b"dac3", &[0x10, 0x3d, 0xe0]))
+}
+
+/// An `ec-3` `stsd` for 48 kHz 5.1 at 768 kbit/s with Atmos JOC complexity index 16.
+pub fn ec3_stsd() -> Vec<u8> {
+    let dec3 = [0x18, 0x00, 0x20, 0x0f, 0x00, 0x01, 0x10];
+    audio_stsd(b"ec-3", 2, 48000, &mp4_box(b"dec3", &dec3))
+}
+
+/// An `ac-4` `stsd` with bitstream version 2 at 48 kHz and 25 fps and one
+/// version 1 presentation with mdcompat 3.
+pub fn ac4_stsd() -> Vec<u8> {
+    let dac4 = [
+        0x20, 0xa4, 0x01, 0x00, 0x00, 0x00, 0x00, 0x1f, 0xff, 0xff, 0xff, 0xe0, 0x01, 0x02, 0x03,
+        0x00,
+    ];
+    audio_stsd(b"ac-4", 2, 48000, &mp4_box(b"dac4", &dac4))
+}
+
+/// ftyp and moov of a fragmented file with one 30 fps avc1 track and one frame per `trex` duration.
+pub fn init_segment(brands: &[&[u8; 4]]) -> Vec<u8> {
+    init_segment_with_stsd(brands, &video_stsd())
+}
+
+/// `init_segment` with the given video `stsd`.
+pub fn init_segment_with_stsd(brands: &[&[u8; 4]], stsd: &[u8]) -> Vec<u8> {
+    init_segment_with_track(brands, b"vide", stsd, &empty_sample_tables())
+}
+
+/// `init_segment` with one audio track described by `stsd`.
+pub fn audio_init_segment(stsd: &[u8]) -> Vec<u8> {
+    init_segment_with_track(&[b"iso6"], b"soun", stsd, &empty_sample_tables())
+}
+
+/// ftyp and moov of a spliced file whose video track has an avc1 and an hvc1
+/// sample entry, each used by one chunk of two 100-byte samples.
+pub fn spliced_mp4() -> Vec<u8> {
+    let avc1 = video_stsd()[16..].to_vec();
+    let hvc1 = hvc1_stsd()[16..].to_vec();
+    let stsd = full_box(b"stsd", 0, 0, &[be32(&[2]), avc1, hvc1].concat());
+    let tables = [
+        full_box(b"stts", 0, 0, &be32(&[1, 4, 1])),
+        full_box(b"stsc", 0, 0, &be32(&[2, 1, 2, 1, 2, 2, 2])),
+        full_box(b"stsz", 0, 0, &be32(&[100, 4])),
+        full_box(b"stco", 0, 0, &be32(&[2, 0, 200])),
+    ]
+    .concat();
+    init_segment_with_track(&[b"isom"], b"vide", &stsd, &tables)
+}
+
+fn empty_sample_tables() -> Vec<u8> {
+    [
+        full_box(b"stts", 0, 0, &be32(&[0])),
+        full_box(b"stsc", 0, 0, &be32(&[0])),
+        full_box(b"stsz", 0, 0, &be32(&[0, 0])),
+        full_box(b"stco", 0, 0, &be32(&[0])),
+    ]
+    .concat()
+}
+
+fn ftyp(brands: &[&[u8; 4]]) -> Vec<u8> {
+    let mut ftyp = brands[0].to_vec();
+    ftyp.extend_from_slice(&[0, 0, 0, 0]);
+    for brand in brands {
+        ftyp.extend_from_slice(&brand[..]);
+    }
+    mp4_box(b"ftyp", &ftyp)
+}
+
+const MATRIX: [u32; 9] = [0x10000, 0, 0, 0, 0x10000, 0, 0, 0, 0x4000_0000];
+
+fn mvhd(timescale: u32, duration: u32, next_track_id: u32) -> Vec<u8> {
+    let mut mvhd = be32(&[0, 0, timescale, duration, 0x10000]);
+    mvhd.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    mvhd.extend_from_slice(&be32(&MATRIX));
+    mvhd.extend_from_slice(&[0; 24]);
+    mvhd.extend_from_slice(&be32(&[next_track_id]));
+    full_box(b"mvhd", 0, 0, &mvhd)
+}
+
+/// A `trak` with a `handler` media of the given timescale, followed by `extra` boxes such as `tref`.
+fn trak(
+    track_id: u32,
+    handler: &[u8; 4],
+    timescale: u32,
+    stsd: &[u8],
+    sample_tables: &[u8],
+    extra: &[u8],
+) -> Vec<u8> {
+    let mut tkhd = be32(&[0, 0, track_id, 0, 0, 0, 0, 0, 0]);
+    tkhd.extend_from_slice(&be32(&MATRIX));
+    if handler == b"vide" {
+        tkhd.extend_from_slice(&be32(&[854 << 16, 480 << 16]));
+    } else {
+        tkhd.extend_from_slice(&be32(&[0, 0]));
+    }
+
+    let mdhd = be32(&[0, 0, timescale, 0, 0x55c4_0000]);
+    let mut hdlr = be32(&[0]);
+    hdlr.extend_from_slice(handler);
+    hdlr.extend_from_slice(&[0; 13]);
+    let dref = full_box(
+        b"dref",
+        0,
+        0,
+        &[&be32(&[1])[..], &full_box(b"url ", 0, 1, &[])].concat(),
+    );
+
+    let stbl = [stsd, sample_tables].concat();
+    let media_header = match handler {
+        b"soun" => full_box(b"smhd", 0, 0, &[0; 4]),
+        b"vide" => full_box(b"vmhd", 0, 1, &[0; 8]),
+        _ => full_box(b"nmhd", 0, 0, &[]),
+    };
+    let minf = [
+        media_header,
+        mp4_box(b"dinf", &dref),
+        mp4_box(b"stbl", &stbl),
+    ]
+    .concat();
+    let mdia = [
+        full_box(b"mdhd", 0, 0, &mdhd),
+        full_box(b"hdlr", 0, 0, &hdlr),
+        mp4_box(b"minf", &minf),
+    ]
+    .concat();
+    let trak = [
+        full_box(b"tkhd", 0, 3, &tkhd),
+        mp4_box(b"mdia", &mdia),
+        extra.to_vec(),
+    ]
+    .concat();
+    mp4_box(b"trak", &trak)
+}
+
+fn init_segment_with_track(
+    brands: &[&[u8; 4]],
+    handler: &[u8; 4],
+    stsd: &[u8],
+    sample_tables: &[u8],
+) -> Vec<u8> {
+    let mvex = full_box(b"trex", 0, 0, &be32(&[1, 1, 1, 0, 0x0001_0000]));
+    let moov = [
+        mvhd(1000, 0, 2),
+        trak(1, handler, 30, stsd, sample_tables, &[]),
+        mp4_box(b"mvex", &mvex),
+    ]
+    .concat();
+    [ftyp(brands), mp4_box(b"moov", &moov)].concat()
+}
+
+/// An empty QuickTime timecode `trak`.
+pub fn timecode_trak(track_id: u32) -> Vec<u8> {
+    let stsd = full_box(
+        b"stsd",
+        0,
+        0,
+        &[
+            &be32(&[1])[..],
+            &mp4_box(b"tmcd", &[0, 0, 0, 0, 0, 0, 0, 1]),
+        ]
+        .concat(),
+    );
+    trak(track_id, b"tmcd", 30, &stsd, &empty_sample_tables(), &[])
+}
+
+/// An audiobook with an empty Opus track whose `tref/chap` references a text
+/// chapter track of three chapters, "Opening" for 60 s, "Chapter 1" for 30.5 s
+/// and "Chapter 2" for 29.5 s, and a Nero `chpl` holding `chpl_titles` at the
+/// same start times.
+pub fn chaptered_m4a(chpl_titles: &[&str]) -> Vec<u8> {
+    let titles = ["Opening", "Chapter 1", "Chapter 2"];
+    let samples: Vec<Vec<u8>> = titles
+        .iter()
+        .map(|t| [&(t.len() as u16).to_be_bytes()[..], t.as_bytes()].concat())
+        .collect();
+    let mut chpl = vec![0; 4];
+    chpl.push(chpl_titles.len() as u8);
+    for (title, start) in chpl_titles.iter().zip([0u64, 600_000_000, 905_000_000]) {
+        chpl.extend_from_slice(&start.to_be_bytes());
+        chpl.push(title.len() as u8);
+        chpl.extend_from_slice(title.as_bytes());
+    }
+    let udta = mp4_box(b"udta", &full_box(b"chpl", 1, 0, &chpl));
+    let tref = mp4_box(b"tref", &mp4_box(b"chap", &be32(&[2])));
+    let text_stsd = full_box(
+        b"stsd",
+        0,
+        0,
+        &[
+            &be32(&[1])[..],
+            &mp4_box(b"text", &[0, 0, 0, 0, 0, 0, 0, 1]),
+        ]
+        .concat(),
+    );
+    let moov = |mdat_offset: u32| {
+        let mut stsz = be32(&[0, 3]);
+        stsz.extend_from_slice(&be32(
+            &samples.iter().map(|s| s.len() as u32).collect::<Vec<_>>(),
+        ));
+        let text_tables = [
+            full_box(b"stts", 0, 0, &be32(&[3, 1, 60_000, 1, 30_500, 1, 29_500])),
+            full_box(b"stsc", 0, 0, &be32(&[1, 1, 3, 1])),
+            full_box(b"stsz", 0, 0, &stsz),
+            full_box(b"stco", 0, 0, &be32(&[1, mdat_offset + 8])),
+        ]
+        .concat();
+        mp4_box(
+            b"moov",
+            &[
+                mvhd(1000, 120_000, 3),
+                trak(
+                    1,
+                    b"soun",
+                    48000,
+                    &opus_stsd(),
+                    &empty_sample_tables(),
+                    &tref,
+                ),
+                trak(2, b"text", 1000, &text_stsd, &text_tables, &[]),
+                udta.clone(),
+            ]
+            .concat(),
+        )
+    };
+    let ftyp = ftyp(&[b"M4A ", b"isom"]);
+    let mdat_offset = (ftyp.len() + moov(0).len()) as u32;
+    [ftyp, moov(mdat_offset), mp4_box(b"mdat", &samples.concat())].concat()
+}
+
+/// `file` with `children` appended to its `moov`, which must be the last top level box.
+pub fn append_to_moov(file: &[u8], children: &[u8]) -> Vec<u8> {
+    let moov = file.windows(4).rposition(|w| w == b"moov").unwrap() - 4;
+    let size = u32::from_be_bytes(file[moov..moov + 4].try_into().unwrap()) as usize;
+    assert_eq!(moov + size, file.len());
+    let mut out = file.to_vec();
+    out[moov..moov + 4].copy_from_slice(&((size + children.len()) as u32).to_be_bytes());
+    out.extend_from_slice(children);
+    out
+}
+
+/// The signature and IHDR chunk of a `width` x `height` truecolour PNG.
+pub fn png_header(width: u32, height: u32) -> Vec<u8> {
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    png.extend_from_slice(&be32(&[13]));
+    png.extend_from_slice(b"IHDR");
+    png.extend_from_slice(&be32(&[width, height]));
+    png.extend_from_slice(&[8, 2, 0, 0, 0]);
+    png.extend_from_slice(&[0; 4]);
+    png
+}
+
+/// An `ilst` item holding one `data` atom of the given well-known type.
+pub fn ilst_item(atom: &[u8; 4], data_type: u32, payload: &[u8]) -> Vec<u8> {
+    let data = mp4_box(b"data", &[&be32(&[data_type, 0])[..], payload].concat());
+    mp4_box(atom, &data)
+}
+
+/// A `udta` with an iTunes `meta/ilst` holding text, track and disk numbers, a
+/// 300x200 PNG cover and a freeform iTunSMPB item.
+pub fn tagged_udta() -> Vec<u8> {
+    let mut hdlr = be32(&[0]);
+    hdlr.extend_from_slice(b"mdirappl");
+    hdlr.extend_from_slice(&[0; 9]);
+    let freeform = [
+        full_box(b"mean", 0, 0, b"com.apple.iTunes"),
+        full_box(b"name", 0, 0, b"iTunSMPB"),
+        mp4_box(
+            b"data",
+            &[&be32(&[1, 0])[..], b" 00000000 00000840"].concat(),
+        ),
+    ]
+    .concat();
+    let ilst = [
+        ilst_item(b"\xa9nam", 1, b"Episode 1"),
+        ilst_item(b"\xa9ART", 1, b"mpn"),
+        ilst_item(b"\xa9alb", 1, "Caf\u{e9} Sessions".as_bytes()),
+        ilst_item(b"\xa9day", 1, b"2026"),
+        ilst_item(b"\xa9too", 1, b"Lavf61.7.100"),
+        ilst_item(b"trkn", 0, &[0, 0, 0, 3, 0, 12, 0, 0]),
+        ilst_item(b"disk", 0, &[0, 0, 0, 1, 0, 2]),
+        ilst_item(b"covr", 14, &png_header(300, 200)),
+        ilst_item(b"desc", 1, b"Short description"),
+        ilst_item(b"ldes", 1, b"Long description"),
+        ilst_item(b"tvsh", 1, b"Show"),
+        mp4_box(b"----", &freeform),
+    ]
+    .concat();
+    let meta = full_box(
+        b"meta",
+        0,
+        0,
+        &[full_box(b"hdlr", 0, 0, &hdlr), mp4_box(b"ilst", &ilst)].concat(),
+    );
+    mp4_box(b"udta", &meta)
+}
+
+/// A QuickTime `meta` with Apple `mdta` keys for an iPhone recording: ISO 6709
+/// location with altitude, horizontal accuracy as a float, make, model, software
+/// and creation date.
+pub fn mdta_meta() -> Vec<u8> {
+    let mut hdlr = be32(&[0]);
+    hdlr.extend_from_slice(b"mdta");
+    hdlr.extend_from_slice(&[0; 13]);
+    let entries: [(&str, u32, Vec<u8>); 6] = [
+        (
+            "com.apple.quicktime.location.ISO6709",
+            1,
+            b"+37.3349-122.0090+018.000/".to_vec(),
+        ),
+        (
+            "com.apple.quicktime.location.accuracy.horizontal",
+            23,
+            4.75f32.to_be_bytes().to_vec(),
+        ),
+        ("com.apple.quicktime.make", 1, b"Apple".to_vec()),
+        ("com.apple.quicktime.model", 1, b"iPhone 15 Pro".to_vec()),
+        ("com.apple.quicktime.software", 1, b"17.4.1".to_vec()),
+        (
+            "com.apple.quicktime.creationdate",
+            1,
+            b"2026-05-01T10:15:30-0700".to_vec(),
+        ),
+    ];
+    let mut keys = be32(&[entries.len() as u32]);
+    let mut ilst = Vec::new();
+    for (index, (key, data_type, payload)) in entries.iter().enumerate() {
+        keys.extend_from_slice(&be32(&[key.len() as u32 + 8]));
+        keys.extend_from_slice(b"mdta");
+        keys.extend_from_slice(key.as_bytes());
+        ilst.extend_from_slice(&ilst_item(
+            &(index as u32 + 1).to_be_bytes(),
+            *data_type,
+            payload,
+        ));
+    }
+    mp4_box(
+        b"meta",
+        &[
+            full_box(b"hdlr", 0, 0, &hdlr),
+            full_box(b"keys", 0, 0, &keys),
+            mp4_box(b"ilst", &ilst),
+        ]
+        .concat(),
+    )
+}
+
+/// A GPMF KLV entry holding a string.
+fn gpmf_string(key: &[u8; 4], value: &str) -> Vec<u8> {
+    let mut klv = key.to_vec();
+    klv.extend_from_slice(&[b'c', 1]);
+    klv.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    klv.extend_from_slice(value.as_bytes());
+    klv.resize((klv.len() + 3) & !3, 0);
+    klv
+}
+
+/// A `udta` of a GoPro recording with a QuickTime `©xyz` location, a 3GPP `loci`
+/// for a different place, the `FIRM` firmware box and a GPMF device stream with
+/// the model and serial number.
+pub fn gopro_udta() -> Vec<u8> {
+    let xyz = b"+48.8584+002.2945+035.000/";
+    let mut loci = vec![0x15, 0xc7];
+    loci.extend_from_slice(b"Eiffel Tower\0");
+    loci.push(0);
+    for value in [2.2945f64, 48.8584, 35.0] {
+        loci.extend_from_slice(&((value * 65536.0).round() as i32).to_be_bytes());
+    }
+    loci.extend_from_slice(b"earth\0\0");
+    let devc_body = [
+        gpmf_string(b"MINF", "HERO12 Black"),
+        gpmf_string(b"CASN", "C3501324500001"),
+    ]
+    .concat();
+    let mut gpmf = b"DEVC".to_vec();
+    gpmf.extend_from_slice(&[0, 4]);
+    gpmf.extend_from_slice(&((devc_body.len() / 4) as u16).to_be_bytes());
+    gpmf.extend_from_slice(&devc_body);
+    let children = [
+        mp4_box(
+            b"\xa9xyz",
+            &[&(xyz.len() as u16).to_be_bytes()[..], &[0x15, 0xc7], xyz].concat(),
+        ),
+        full_box(b"loci", 0, 0, &loci),
+        mp4_box(b"FIRM", b"H23.01.02.32.00"),
+        mp4_box(b"GPMF", &gpmf),
+    ]
+    .concat();
+    mp4_box(b"udta", &children)
+}
+
+/// A segment type box listing `brands`, the first being the major brand.
+pub fn styp(brands: &[&[u8; 4]]) -> Vec<u8> {
+    let mut body = brands[0].to_vec();
+    body.extend_from_slice(&[0, 0, 0, 0]);
+    for brand in brands {
+        body.extend_from_slice(&brand[..]);
+    }
+    mp4_box(b"styp", &body)
+}
+
+/// moof and mdat of a fragment whose first sample is a sync sample.
+pub fn media_segment(fragment: &FragmentSpec) -> Vec<u8> {
+    let mfhd = full_box(b"mfhd", 0, 0, &be32(&[fragment.sequence_number]));
+    let tfhd = full_box(b"tfhd", 0, 0x02_0000, &be32(&[1]));
+    let tfdt = full_box(
+        b"tfdt",
+        1,
+        0,
+        &fragment.base_media_decode_time.to_be_bytes(),
+    );
+    let trun = |data_offset: u32| {
+        let mut body = be32(&[fragment.sample_sizes.len() as u32, data_offset, 0x0200_0000]);
+        body.extend_from_slice(&be32(&fragment.sample_sizes));
+        full_box(b"trun", 0, 0x205, &body)
+    };
+    let moof = |data_offset: u32| {
+        let traf = [tfhd.clone(), tfdt.clone(), trun(data_offset)].concat();
+        mp4_box(b"moof", &[mfhd.clone(), mp4_box(b"traf", &traf)].concat())
+    };
+    let moof_size = moof(0).len() as u32;
+    let total: u32 = fragment.sample_sizes.iter().sum();
+    [
+        moof(moof_size + 8),
+        mp4_box(b"mdat", &vec![0; total as usize]),
+    ]
+    .concat()
+}
+
+/// A complete fragmented file built from `init_segment` and `media_segment`.
+pub fn fragmented_mp4(brands: &[&[u8; 4]], fragments: &[FragmentSpec]) -> Vec<u8> {
+    let mut out = init_segment(brands);
+    for fragment in fragments {
+        out.extend(media_segment(fragment));
+    }
+    out
+}
+
+/// A fragmented file with a `sidx` after the init segment and an `mfra` at the
+/// end. Every reference uses `subsegment_duration` and every `tfra` entry is
+/// moved by `moof_offset_delta` bytes, so both can be made inconsistent.
+pub fn indexed_mp4(
+    fragments: &[FragmentSpec],
+    subsegment_duration: u32,
+    moof_offset_delta: u64,
+) -> Vec<u8> {
+    let init = init_segment(&[b"iso6"]);
+    let segments: Vec<Vec<u8>> = fragments.iter().map(media_segment).collect();
+    let mut sidx = be32(&[1, 30, 0, 0]);
+    sidx.extend_from_slice(&[0, 0]);
+    sidx.extend_from_slice(&(segments.len() as u16).to_be_bytes());
+    for segment in &segments {
+        sidx.extend_from_slice(&be32(&[
+            segment.len() as u32,
+            subsegment_duration,
+            0x9000_0000,
+        ]));
+    }
+    let sidx = full_box(b"sidx", 0, 0, &sidx);
+
+    let mut tfra = be32(&[1, 0, segments.len() as u32]);
+    let mut offset = (init.len() + sidx.len()) as u64;
+    for (fragment, segment) in fragments.iter().zip(&segments) {
+        tfra.extend_from_slice(&(fragment.base_media_decode_time as u32).to_be_bytes());
+        tfra.extend_from_slice(&((offset + moof_offset_delta) as u32).to_be_bytes());
+        tfra.extend_from_slice(&[1, 1, 1]);
+        offset += segment.len() as u64;
+    }
+    let tfra = full_box(b"tfra", 0, 0, &tfra);
+    let mfra_size = (8 + tfra.len() + 16) as u32;
+    let mfro = full_box(b"mfro", 0, 0, &be32(&[mfra_size]));
+    let mfra = mp4_box(b"mfra", &[tfra, mfro].concat());
+    [init, sidx, segments.concat(), mfra].concat()
+}
+
+/// Three one second fragments of 30 samples each.
+pub fn one_second_fragments() -> Vec<FragmentSpec> {
+    (0..3)
+        .map(|i| FragmentSpec {
+            sequence_number: i + 1,
+            base_media_decode_time: u64::from(i) * 30,
+            sample_sizes: (0..30).map(|s| 100 + s).collect(),
+        })
+        .collect()
+}
+
 /**
  * common testing setup
  *
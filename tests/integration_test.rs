@@ -70,13 +70,1195 @@ fn integ_cli_valid_stdout_media_track_audio() {
 
 #[test]
 fn integ_cli_invalid_stdout_media_track_audio() {
-    let predicate_fn = predicate::str::contains("audio");
+    let predicate_fn = predicate::str::contains("[media.track.audio");
     let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
     cmd.arg(common::TEST_BOKEH_AU_0T_VD_30F_854X480_MP4_FILE);
     let output = String::from_utf8(cmd.output().unwrap().stdout);
     assert!(!predicate_fn.eval(&output.unwrap()));
 }
 
+#[test]
+fn integ_cli_av_sync_within_tolerance() {
+    let predicate_fn = predicate::str::contains("within_tolerance = true");
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("--av-sync")
+        .arg(common::TEST_BOKEH_AU_2T_VD_30F_854X480_MP4_FILE);
+    let output = cmd.output().unwrap();
+    assert!(output.status.success());
+    assert!(predicate_fn.eval(&String::from_utf8(output.stdout).unwrap()));
+}
+
+#[test]
+fn integ_cli_av_sync_offset() {
+    let predicate_fn = predicate::str::contains("av_offset_ms = 0.000");
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("--av-sync")
+        .arg("--av-sync-tolerance")
+        .arg("0")
+        .arg(common::TEST_BOKEH_AU_2T_VD_30F_854X480_MP4_FILE);
+    let output = String::from_utf8(cmd.output().unwrap().stdout);
+    assert!(predicate_fn.eval(&output.unwrap()));
+}
+
+#[test]
+fn integ_cli_layout_chunks() {
+    let predicate_fn = predicate::str::contains("chunk_count = 3")
+        .and(predicate::str::contains("tracks_outside_mdat = []"));
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("--layout")
+        .arg(common::TEST_BOKEH_AU_2T_VD_30F_854X480_MP4_FILE);
+    let output = String::from_utf8(cmd.output().unwrap().stdout);
+    assert!(predicate_fn.eval(&output.unwrap()));
+}
+
+#[test]
+fn integ_cli_layout_duplicate_track_ids() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("duplicate-track-ids.mp4");
+    let mut data = std::fs::read(common::TEST_BOKEH_AU_2T_VD_30F_854X480_MP4_FILE).unwrap();
+    // give the second track the track_id of the first
+    let tkhd = data
+        .windows(4)
+        .enumerate()
+        .filter(|(_, w)| *w == b"tkhd")
+        .map(|(i, _)| i)
+        .nth(1)
+        .unwrap();
+    assert_eq!(data[tkhd + 4], 0);
+    data[tkhd + 16..tkhd + 20].copy_from_slice(&1u32.to_be_bytes());
+    std::fs::write(&file_path, data).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("--layout").arg(&file_path);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("chunk_count = 3"));
+}
+
+#[test]
+fn integ_cli_faststart_fields() {
+    let predicate_fn = predicate::str::contains("faststart = true")
+        .and(predicate::str::contains("moov_offset = 32"))
+        .and(predicate::str::contains("mdat_offset = 113665"));
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("--assert-faststart")
+        .arg(common::TEST_BOKEH_AU_2T_VD_30F_854X480_MP4_FILE);
+    let output = cmd.output().unwrap();
+    assert!(output.status.success());
+    assert!(predicate_fn.eval(&String::from_utf8(output.stdout).unwrap()));
+}
+
+#[test]
+fn integ_cli_assert_faststart_moov_at_end() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("moov-at-end.mp4");
+    common::write_moov_at_end(common::TEST_BOKEH_AU_2T_VD_30F_854X480_MP4_FILE, &file_path);
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("--assert-faststart").arg(&file_path);
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("faststart = false"));
+}
+
+#[test]
+fn integ_cli_assert_faststart_reports_av_sync() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("moov-at-end.mp4");
+    common::write_moov_at_end(common::TEST_BOKEH_AU_2T_VD_30F_854X480_MP4_FILE, &file_path);
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("--assert-faststart")
+        .arg("--av-sync")
+        .arg("--check")
+        .arg("cmaf")
+        .arg(&file_path);
+    cmd.assert()
+        .failure()
+        .stdout(
+            predicate::str::contains("faststart = false")
+                .and(predicate::str::contains("within_tolerance = true")),
+        )
+        .stderr(
+            predicate::str::contains("cmaf (").and(predicate::str::contains(
+                "faststart (moov is not before mdat)",
+            )),
+        );
+}
+
+#[test]
+fn integ_cli_faststart_rewrite() {
+    let dir = tempfile::tempdir().unwrap();
+    let moov_at_end = dir.path().join("moov-at-end.mp4");
+    let rewritten = dir.path().join("faststart.mp4");
+    common::write_moov_at_end(
+        common::TEST_BOKEH_AU_2T_VD_30F_854X480_MP4_FILE,
+        &moov_at_end,
+    );
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("faststart").arg(&moov_at_end).arg(&rewritten);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("verified_chunks = 3"));
+    assert_eq!(
+        std::fs::read(&rewritten).unwrap(),
+        std::fs::read(common::TEST_BOKEH_AU_2T_VD_30F_854X480_MP4_FILE).unwrap()
+    );
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("--assert-faststart").arg(&rewritten);
+    cmd.assert().success();
+}
+
+#[test]
+fn integ_cli_faststart_rewrite_keeps_trailing_bytes() {
+    let dir = tempfile::tempdir().unwrap();
+    let moov_at_end = dir.path().join("moov-at-end.mp4");
+    let rewritten = dir.path().join("faststart.mp4");
+    common::write_moov_at_end(
+        common::TEST_BOKEH_AU_2T_VD_30F_854X480_MP4_FILE,
+        &moov_at_end,
+    );
+    // trailing bytes too short for a box header
+    let trailing = [0, 0, 0];
+    let mut data = std::fs::read(&moov_at_end).unwrap();
+    data.extend_from_slice(&trailing);
+    std::fs::write(&moov_at_end, &data).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("faststart").arg(&moov_at_end).arg(&rewritten);
+    cmd.assert().success();
+    let output = std::fs::read(&rewritten).unwrap();
+    assert_eq!(output.len(), data.len());
+    assert!(output.ends_with(&trailing));
+}
+
+#[test]
+fn integ_cli_fragmented_statistics() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("fragmented.mp4");
+    let fragments = common::one_second_fragments();
+    std::fs::write(
+        &file_path,
+        common::fragmented_mp4(&[b"iso6", b"mp41"], &fragments),
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(&file_path);
+    cmd.assert().success().stdout(
+        predicate::str::contains("fragment_count = 3")
+            .and(predicate::str::contains("sample_count = 90"))
+            .and(predicate::str::contains("sync_sample_count = 3"))
+            .and(predicate::str::contains("duration = 3.000000"))
+            .and(predicate::str::contains("base_media_decode_time = 60")),
+    );
+}
+
+#[test]
+fn integ_cli_fragmented_trun_sample_count() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("fragmented.mp4");
+    let mut data = common::fragmented_mp4(&[b"iso6"], &common::one_second_fragments());
+    // a run without per sample fields claiming 2^32 - 1 samples
+    let trun = data.windows(4).position(|w| w == b"trun").unwrap();
+    data[trun + 4..trun + 12].copy_from_slice(&[0, 0, 0, 0x05, 0xff, 0xff, 0xff, 0xff]);
+    std::fs::write(&file_path, data).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(&file_path);
+    cmd.assert().success().stdout(
+        predicate::str::contains("[media.fragments]\nerror = ")
+            .and(predicate::str::contains("exceeds the fragment size")),
+    );
+}
+
+#[test]
+fn integ_cli_check_cmaf_conformant() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("cmaf.mp4");
+    let fragments = common::one_second_fragments();
+    std::fs::write(
+        &file_path,
+        common::fragmented_mp4(&[b"cmfc", b"iso6"], &fragments),
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("--check").arg("cmaf").arg(&file_path);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("conformant = true"));
+}
+
+#[test]
+fn integ_cli_check_cmaf_constant_sample_size() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("cmaf.mp4");
+    let mut data = common::fragmented_mp4(&[b"cmfc", b"iso6"], &common::one_second_fragments());
+    // an empty stsz with a non zero sample_size
+    let stsz = data.windows(4).position(|w| w == b"stsz").unwrap();
+    data[stsz + 8..stsz + 12].copy_from_slice(&100u32.to_be_bytes());
+    std::fs::write(&file_path, data).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("--check").arg("cmaf").arg(&file_path);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("conformant = true"));
+}
+
+#[test]
+fn integ_cli_check_cmaf_violations() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("not-cmaf.mp4");
+    let fragments = common::one_second_fragments();
+    let mut data = common::init_segment(&[b"iso6", b"mp41"]);
+    data.extend(common::styp(&[b"msdh", b"msix"]));
+    data.extend(common::media_segment(&fragments[0]));
+    std::fs::write(&file_path, data).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("--check").arg("cmaf").arg(&file_path);
+    cmd.assert().failure().stdout(
+        predicate::str::contains("violations = 2")
+            .and(predicate::str::contains("path = \"ftyp\""))
+            .and(predicate::str::contains("path = \"styp[1]\"")),
+    );
+}
+
+#[test]
+fn integ_cli_check_sidx_consistent() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("indexed.mp4");
+    let data = common::indexed_mp4(&common::one_second_fragments(), 30, 0);
+    std::fs::write(&file_path, data).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("--check").arg("sidx").arg(&file_path);
+    cmd.assert().success().stdout(
+        predicate::str::contains("[media.sidx]\n")
+            .and(predicate::str::contains("reference_count = 3"))
+            .and(predicate::str::contains("subsegment_duration = 30"))
+            .and(predicate::str::contains("[media.mfra.tfra]"))
+            .and(predicate::str::contains(
+                "[media.check.sidx]\nconformant = true",
+            )),
+    );
+}
+
+#[test]
+fn integ_cli_check_sidx_mismatch() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("bad-index.mp4");
+    let data = common::indexed_mp4(&common::one_second_fragments(), 25, 8);
+    std::fs::write(&file_path, data).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("--check").arg("sidx").arg(&file_path);
+    cmd.assert().failure().stdout(
+        predicate::str::contains("violations = 6")
+            .and(predicate::str::contains("path = \"sidx[1]/reference[2]\""))
+            .and(predicate::str::contains(
+                "does not match fragment duration 30",
+            ))
+            .and(predicate::str::contains("path = \"mfra/tfra[1]/entry[1]\"")),
+    );
+}
+
+#[test]
+fn integ_cli_sidx_offset_overflow() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("bad-sidx.mp4");
+    let mut sidx = vec![0, 0, 0, 52];
+    sidx.extend_from_slice(b"sidx");
+    sidx.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 30]);
+    sidx.extend_from_slice(&0u64.to_be_bytes());
+    sidx.extend_from_slice(&u64::MAX.to_be_bytes());
+    sidx.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 100, 0, 0, 0, 30, 0x90, 0, 0, 0]);
+    let data = [common::init_segment(&[b"iso6"]), sidx].concat();
+    std::fs::write(&file_path, data).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(&file_path);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("sidx reference offset overflows"));
+}
+
+fn write_segments(dir: &std::path::Path, numbers: &[usize]) -> Vec<std::path::PathBuf> {
+    let fragments = common::one_second_fragments();
+    std::fs::write(dir.join("init.mp4"), common::init_segment(&[b"iso6"])).unwrap();
+    numbers
+        .iter()
+        .map(|&n| {
+            let path = dir.join(format!("seg{}.m4s", n));
+            std::fs::write(&path, common::media_segment(&fragments[n - 1])).unwrap();
+            path
+        })
+        .collect()
+}
+
+#[test]
+fn integ_cli_init_segments_continuous() {
+    let dir = tempfile::tempdir().unwrap();
+    let segments = write_segments(dir.path(), &[1, 2, 3]);
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("--init")
+        .arg(dir.path().join("init.mp4"))
+        .args(&segments);
+    cmd.assert().success().stdout(
+        predicate::str::contains("[media.segment]\n")
+            .and(predicate::str::contains("base_media_decode_time = 60"))
+            .and(predicate::str::contains("continuous = true"))
+            .and(predicate::str::contains("segment_count = 3"))
+            .and(predicate::str::contains("duration = 3.000000"))
+            .and(predicate::str::contains("discontinuities = 0"))
+            .and(predicate::str::contains("sequence_gaps = 0")),
+    );
+}
+
+#[test]
+fn integ_cli_init_segments_gap() {
+    let dir = tempfile::tempdir().unwrap();
+    let segments = write_segments(dir.path(), &[1, 3]);
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("--init")
+        .arg(dir.path().join("init.mp4"))
+        .args(&segments);
+    cmd.assert().success().stdout(
+        predicate::str::contains("[media.segment.sequence_gap]\nexpected = 2\nfound = 3")
+            .and(predicate::str::contains(
+                "continuous = false\ndecode_time_gap = 30",
+            ))
+            .and(predicate::str::contains("discontinuities = 1"))
+            .and(predicate::str::contains("sequence_gaps = 1")),
+    );
+}
+
+#[test]
+fn integ_cli_init_conflicts_with_file_reports() {
+    let dir = tempfile::tempdir().unwrap();
+    let segments = write_segments(dir.path(), &[1, 2]);
+    let cover_dir = dir.path().to_string_lossy().to_string();
+    let chapters = dir
+        .path()
+        .join("chapters.vtt")
+        .to_string_lossy()
+        .to_string();
+    for args in [
+        vec!["--mime"],
+        vec!["--layout"],
+        vec!["--av-sync"],
+        vec!["--assert-faststart"],
+        vec!["--extract-cover", &cover_dir],
+        vec!["--export-chapters", &chapters],
+    ] {
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+        cmd.arg("--init")
+            .arg(dir.path().join("init.mp4"))
+            .args(&args)
+            .args(&segments);
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+}
+
+#[test]
+fn integ_cli_manifest_hls() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("fragmented.mp4");
+    let data = common::fragmented_mp4(&[b"iso6"], &common::one_second_fragments());
+    std::fs::write(&file_path, data).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("manifest").arg("--hls").arg(&file_path);
+    cmd.assert().success().stdout(
+        predicate::str::starts_with("#EXTM3U\n")
+            .and(predicate::str::contains("CODECS=\"avc1.4D401F\""))
+            .and(predicate::str::contains("#EXT-X-TARGETDURATION:1\n"))
+            .and(predicate::str::contains(
+                "#EXT-X-MAP:URI=\"fragmented.mp4\",BYTERANGE=\"694@0\"",
+            ))
+            .and(predicate::str::contains(
+                "#EXTINF:1.000000,\n#EXT-X-BYTERANGE:3655@4349\nfragmented.mp4\n",
+            ))
+            .and(predicate::str::ends_with("#EXT-X-ENDLIST\n")),
+    );
+}
+
+#[test]
+fn integ_cli_manifest_escapes_uri() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("a \"b\" & <c>.mp4");
+    let data = common::fragmented_mp4(&[b"iso6"], &common::one_second_fragments());
+    std::fs::write(&file_path, data).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("manifest").arg("--hls").arg(&file_path);
+    cmd.assert().success().stdout(
+        predicate::str::contains("#EXT-X-MAP:URI=\"a%20%22b%22%20&%20%3Cc%3E.mp4\"").and(
+            predicate::str::contains("\na%20%22b%22%20&%20%3Cc%3E.mp4\n"),
+        ),
+    );
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("manifest").arg("--dash").arg(&file_path);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "<BaseURL>a &quot;b&quot; &amp; &lt;c&gt;.mp4</BaseURL>",
+    ));
+}
+
+#[test]
+fn integ_cli_manifest_dash_sidx() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("indexed.mp4");
+    let data = common::indexed_mp4(&common::one_second_fragments(), 30, 0);
+    std::fs::write(&file_path, data).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("manifest").arg("--dash").arg(&file_path);
+    cmd.assert().success().stdout(
+        predicate::str::contains("isoff-on-demand:2011")
+            .and(predicate::str::contains(
+                "mediaPresentationDuration=\"PT3.000S\"",
+            ))
+            .and(predicate::str::contains("codecs=\"avc1.4D401F\""))
+            .and(predicate::str::contains(
+                "<SegmentBase indexRange=\"694-761\">",
+            ))
+            .and(predicate::str::contains(
+                "<Initialization range=\"0-693\"/>",
+            )),
+    );
+}
+
+#[test]
+fn integ_cli_manifest_skips_timecode_track() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("fragmented.mp4");
+    let mut data =
+        common::append_to_moov(&common::init_segment(&[b"iso6"]), &common::timecode_trak(2));
+    for fragment in common::one_second_fragments() {
+        data.extend(common::media_segment(&fragment));
+    }
+    std::fs::write(&file_path, data).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("manifest").arg("--hls").arg(&file_path);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("CODECS=\"avc1.4D401F\""));
+}
+
+#[test]
+fn integ_cli_manifest_requires_fragments() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("manifest")
+        .arg("--dash")
+        .arg("tests/files/test-bokeh-au-2t-vd-30f-854x480.mp4");
+    cmd.assert()
+        .failure()
+        .stderr("error = \"manifest requires a fragmented mp4 file\"\n");
+}
+
+#[test]
+fn integ_cli_codecs_strings() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("tests/files/test-bokeh-au-2t-vd-30f-854x480.mp4");
+    cmd.assert().success().stdout(
+        predicate::str::contains("codec_name = \"ES\"\ncodecs = \"mp4a.40.2\"").and(
+            predicate::str::contains("codec_name = \"AVC\"\ncodecs = \"avc1.4D401F\""),
+        ),
+    );
+}
+
+#[test]
+fn integ_cli_mime() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("--mime")
+        .arg("tests/files/test-bokeh-au-2t-vd-30f-854x480.mp4");
+    cmd.assert()
+        .success()
+        .stdout("video/mp4; codecs=\"mp4a.40.2,avc1.4D401F\"\n");
+}
+
+#[test]
+fn integ_cli_avc_parameter_sets() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("tests/files/test-bokeh-au-2t-vd-30f-854x480.mp4");
+    cmd.assert().success().stdout(
+        predicate::str::contains(
+            "[media.track.video.avc]\nconfiguration_version = 1\nprofile = \"Main\"",
+        )
+        .and(predicate::str::contains("level = \"3.1\""))
+        .and(predicate::str::contains("nal_length_size = 4"))
+        .and(predicate::str::contains(
+            "coded_width = 864\ncoded_height = 464\nwidth = 854\nheight = 450",
+        ))
+        .and(predicate::str::contains("colour_primaries = 6"))
+        .and(predicate::str::contains("entropy_coding = \"CAVLC\"")),
+    );
+}
+
+#[test]
+fn integ_cli_hevc_sample_entry() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("hevc.mp4");
+    std::fs::write(
+        &file_path,
+        common::init_segment_with_stsd(&[b"iso6"], &common::hvc1_stsd()),
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(&file_path);
+    cmd.assert().success().stdout(
+        predicate::str::contains(
+            "codec_name = \"HEVC\"\nfourcc = \"hvc1\"\ncodecs = \"hvc1.1.6.L123.90\"",
+        )
+        .and(predicate::str::contains(
+            "tier = \"Main\"\nprofile = \"Main\"",
+        ))
+        .and(predicate::str::contains("level = \"4.1\""))
+        .and(predicate::str::contains("nal_unit_type = \"SPS\""))
+        .and(predicate::str::contains(
+            "coded_width = 1920\ncoded_height = 1088\nwidth = 1920\nheight = 1080",
+        ))
+        .and(predicate::str::contains("max_num_reorder_pics = 2"))
+        .and(predicate::str::contains(
+            "colour_primaries = 9\ntransfer_characteristics = 16\nmatrix_coefficients = 9",
+        ))
+        .and(predicate::str::contains("frame_rate = 59.940")),
+    );
+}
+
+#[test]
+fn integ_cli_unsupported_video_sample_entry() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("unknown.mp4");
+    let stsd = common::visual_stsd(b"xvid", 640, 360, &[]);
+    std::fs::write(
+        &file_path,
+        common::init_segment_with_stsd(&[b"iso6"], &stsd),
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(&file_path);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "codec_name = \"Unsupported\"\nfourcc = \"xvid\"",
+    ));
+}
+
+#[test]
+fn integ_cli_av1_configuration() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("av1.mp4");
+    std::fs::write(
+        &file_path,
+        common::init_segment_with_stsd(&[b"iso6"], &common::av01_stsd()),
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(&file_path);
+    cmd.assert().success().stdout(
+        predicate::str::contains("codec_name = \"AV1\"\ncodecs = \"av01.0.08M.08\"")
+            .and(predicate::str::contains(
+                "[media.track.video.av1]\nseq_profile = 0\nseq_level_idx = 8\nlevel = \"4.0\"\ntier = \"Main\"",
+            ))
+            .and(predicate::str::contains("[media.track.video.av1.sequence_header]"))
+            .and(predicate::str::contains("max_frame_width = 1920\nmax_frame_height = 1080"))
+            .and(predicate::str::contains(
+                "colour_primaries = 1\ntransfer_characteristics = 1\nmatrix_coefficients = 1",
+            ))
+            .and(predicate::str::contains("enable_cdef = true"))
+            .and(predicate::str::contains("film_grain_params_present = false"))
+            .and(predicate::str::contains("warning").not()),
+    );
+}
+
+#[test]
+fn integ_cli_vp9_configuration() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("vp9.mp4");
+    std::fs::write(
+        &file_path,
+        common::init_segment_with_stsd(&[b"iso6"], &common::vp09_stsd()),
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(&file_path);
+    cmd.assert().success().stdout(
+        predicate::str::contains("codec_name = \"VPx\"\ncodecs = \"vp09.02.51.10\"").and(
+            predicate::str::contains(
+                "[media.track.video.vpx]\n\
+                 version = 1\n\
+                 profile = 2\n\
+                 level = 51\n\
+                 bit_depth = 10\n\
+                 chroma_subsampling = 1\n\
+                 chroma_subsampling_name = \"4:2:0 colocated\"\n\
+                 full_range = false\n\
+                 colour_primaries = 9\n\
+                 colour_primaries_name = \"BT.2020\"\n\
+                 transfer_characteristics = 16\n\
+                 transfer_characteristics_name = \"SMPTE ST 2084 (PQ)\"\n\
+                 matrix_coefficients = 9\n\
+                 matrix_coefficients_name = \"BT.2020 non-constant luminance\"\n\
+                 codec_init_data_size = 0\n",
+            ),
+        ),
+    );
+}
+
+#[test]
+fn integ_cli_aac_decoder_config() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(common::TEST_BOKEH_AU_2T_VD_30F_854X480_MP4_FILE);
+    cmd.assert().success().stdout(
+        predicate::str::contains(
+            "[media.track.audio.esds]\n\
+             object_type_indication = \"0x40\"\n\
+             stream_type = 5\n\
+             buffer_size_db = 6144\n\
+             max_bitrate = 128000\n\
+             avg_bitrate = 128000\n",
+        )
+        .and(predicate::str::contains(
+            "[media.track.audio.aac]\n\
+             audio_object_type = 2\n\
+             object_type_name = \"AAC-LC\"\n\
+             sampling_frequency_index = 3\n\
+             sampling_frequency = 48000\n\
+             channel_configuration = 2\n\
+             channels = 2\n\
+             channel_layout = \"stereo\"\n\
+             sbr_present = false\n\
+             ps_present = false\n\
+             frame_length = 1024\n",
+        )),
+    );
+}
+
+#[test]
+fn integ_cli_flac_stream_info() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("flac.mp4");
+    std::fs::write(&file_path, common::audio_init_segment(&common::flac_stsd())).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(&file_path);
+    cmd.assert().success().stdout(
+        predicate::str::contains("codec_name = \"FLAC\"")
+            .and(predicate::str::contains(
+                "sample_rate = 44100\nchannels = 2\nbits_per_sample = 16\ntotal_samples = 441000",
+            ))
+            .and(predicate::str::contains(
+                "md5 = \"000102030405060708090a0b0c0d0e0f\"",
+            )),
+    );
+}
+
+#[test]
+fn integ_cli_opus_header() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("opus.mp4");
+    std::fs::write(&file_path, common::audio_init_segment(&common::opus_stsd())).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(&file_path);
+    cmd.assert().success().stdout(
+        predicate::str::contains(
+            "output_channel_count = 6\npre_skip = 312\ninput_sample_rate = 48000",
+        )
+        .and(predicate::str::contains("output_gain_db = -1.000"))
+        .and(predicate::str::contains(
+            "stream_count = 4\ncoupled_count = 2\nchannel_mapping = [0, 4, 1, 2, 3, 5]",
+        )),
+    );
+}
+
+#[test]
+fn integ_cli_alac_magic_cookie() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("alac.mp4");
+    std::fs::write(&file_path, common::audio_init_segment(&common::alac_stsd())).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(&file_path);
+    cmd.assert().success().stdout(
+        predicate::str::contains("frame_length = 4096")
+            .and(predicate::str::contains("bit_depth = 24"))
+            .and(predicate::str::contains("channels = 2"))
+            .and(predicate::str::contains("max_frame_bytes = 13000"))
+            .and(predicate::str::contains("avg_bitrate = 2304000")),
+    );
+}
+
+#[test]
+fn integ_cli_ac3_sample_entry() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("ac3.mp4");
+    std::fs::write(&file_path, common::audio_init_segment(&common::ac3_stsd())).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(&file_path);
+    cmd.assert().success().stdout(
+        predicate::str::contains("codec_name = \"AC-3\"\nfourcc = \"ac-3\"\ncodecs = \"ac-3\"")
+            .and(predicate::str::contains(
+                "[media.track.audio.ac3]\n\
+                 bsid = 8\n\
+                 bsmod = 0\n\
+                 sample_rate = 48000\n\
+                 acmod = 7\n\
+                 audio_coding_mode = \"3/2\"\n\
+                 lfeon = true\n\
+                 channels = 6\n\
+                 bit_rate_code = 15\n\
+                 bitrate = 448000\n",
+            )),
+    );
+}
+
+#[test]
+fn integ_cli_eac3_joc() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("ec3.mp4");
+    std::fs::write(&file_path, common::audio_init_segment(&common::ec3_stsd())).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(&file_path);
+    cmd.assert().success().stdout(
+        predicate::str::contains(
+            "data_rate = 768000\n\
+             independent_substreams = 1\n\
+             dependent_substreams = 0\n\
+             joc = true\n\
+             complexity_index = 16\n",
+        )
+        .and(predicate::str::contains(
+            "[media.track.audio.eac3.substream]\nbsid = 16",
+        )),
+    );
+}
+
+#[test]
+fn integ_cli_ac4_codecs_string() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("ac4.mp4");
+    std::fs::write(&file_path, common::audio_init_segment(&common::ac4_stsd())).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("--mime").arg(&file_path);
+    cmd.assert()
+        .success()
+        .stdout("audio/mp4; codecs=\"ac-4.02.01.03\"\n");
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(&file_path);
+    cmd.assert().success().stdout(
+        predicate::str::contains("bitstream_version = 2")
+            .and(predicate::str::contains("frame_rate = \"25\""))
+            .and(predicate::str::contains("presentation_versions = [1]")),
+    );
+}
+
+#[test]
+fn integ_cli_unsupported_audio_sample_entry() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("mha1.mp4");
+    std::fs::write(
+        &file_path,
+        common::audio_init_segment(&common::audio_stsd(b"mha1", 2, 48000, &[])),
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(&file_path);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "codec_name = \"Unsupported\"\nfourcc = \"mha1\"",
+    ));
+}
+
+#[test]
+fn integ_cli_multiple_sample_descriptions() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("spliced.mp4");
+    std::fs::write(&file_path, common::spliced_mp4()).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(&file_path);
+    cmd.assert().success().stdout(
+        predicate::str::contains(
+            "[media.track.video.sample.description]\n\
+             index = 1\n\
+             fourcc = \"avc1\"\n\
+             data_reference_index = 1\n\
+             sample_count = 2\n\
+             sample_ranges = [[1, 2]]\n",
+        )
+        .and(predicate::str::contains(
+            "[media.track.video.sample.description]\n\
+             index = 2\n\
+             fourcc = \"hvc1\"\n\
+             data_reference_index = 1\n\
+             sample_count = 2\n\
+             sample_ranges = [[3, 4]]\n",
+        ))
+        .and(predicate::str::contains("codecs = \"avc1.4D401F\""))
+        .and(predicate::str::contains("codecs = \"hvc1.1.6.L123.90\"")),
+    );
+}
+
+#[test]
+fn integ_cli_hdr_colour_boxes() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("hdr.mp4");
+    std::fs::write(
+        &file_path,
+        common::init_segment_with_stsd(&[b"iso6"], &common::hdr_vp09_stsd()),
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(&file_path);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "[media.track.video.colour]\n\
+         colour_type = \"nclx\"\n\
+         colour_primaries = 9\n\
+         colour_primaries_name = \"BT.2020\"\n\
+         transfer_characteristics = 16\n\
+         transfer_characteristics_name = \"SMPTE ST 2084 (PQ)\"\n\
+         matrix_coefficients = 9\n\
+         matrix_coefficients_name = \"BT.2020 non-constant luminance\"\n\
+         full_range = false\n\
+         hdr_transfer = \"PQ\"\n\
+         mastering_display_green = [0.17000, 0.79700]\n\
+         mastering_display_blue = [0.13100, 0.04600]\n\
+         mastering_display_red = [0.70800, 0.29200]\n\
+         mastering_display_white_point = [0.31270, 0.32900]\n\
+         mastering_display_max_luminance = 1000.0000\n\
+         mastering_display_min_luminance = 0.0050\n\
+         max_cll = 1000\n\
+         max_fall = 400\n\
+         pixel_aspect_ratio = \"1:1\"\n\
+         clean_aperture_width = 3840.000\n\
+         clean_aperture_height = 2160.000\n\
+         clean_aperture_horizontal_offset = 0.000\n\
+         clean_aperture_vertical_offset = 0.000\n\
+         buffer_size_db = 0\n\
+         max_bitrate = 25000000\n\
+         avg_bitrate = 18000000\n",
+    ));
+}
+
+#[test]
+fn integ_cli_dolby_vision_profile_5() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("dvh1.mp4");
+    let stsd = common::hevc_stsd(b"dvh1", &common::dovi_config(b"dvcC", 5, 0));
+    std::fs::write(
+        &file_path,
+        common::init_segment_with_stsd(&[b"iso6"], &stsd),
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(&file_path);
+    cmd.assert().success().stdout(
+        predicate::str::contains("fourcc = \"dvh1\"\ncodecs = \"dvh1.05.06\"").and(
+            predicate::str::contains(
+                "[media.track.video.dolby_vision]\n\
+                 box = \"dvcC\"\n\
+                 dv_version = \"1.0\"\n\
+                 profile = 5\n\
+                 profile_name = \"5\"\n\
+                 level = 6\n\
+                 rpu_present = true\n\
+                 el_present = false\n\
+                 bl_present = true\n\
+                 bl_signal_compatibility_id = 0\n\
+                 bl_signal_compatibility = \"none\"\n",
+            ),
+        ),
+    );
+}
+
+#[test]
+fn integ_cli_dolby_vision_profile_8_signalling() {
+    let dir = tempfile::tempdir().unwrap();
+    let hdr10 = dir.path().join("dv81.mp4");
+    let stsd = common::hevc_stsd(b"hvc1", &common::dovi_config(b"dvvC", 8, 1));
+    std::fs::write(&hdr10, common::init_segment_with_stsd(&[b"iso6"], &stsd)).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(&hdr10);
+    cmd.assert().success().stdout(
+        predicate::str::contains("profile_name = \"8.1\"")
+            .and(predicate::str::contains(
+                "bl_signal_compatibility = \"HDR10\"",
+            ))
+            .and(predicate::str::contains("warning").not()),
+    );
+
+    // the PQ base layer does not match the HLG compatibility id
+    let hlg = dir.path().join("dv84.mp4");
+    let stsd = common::hevc_stsd(b"hvc1", &common::dovi_config(b"dvvC", 8, 4));
+    std::fs::write(&hlg, common::init_segment_with_stsd(&[b"iso6"], &stsd)).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(&hlg);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "warning = \"profile 8.4 base layer signals BT.2020 primaries with SMPTE ST 2084 (PQ) transfer, expected HLG\"",
+    ));
+}
+
+#[test]
+fn integ_cli_spatial_metadata() {
+    let dir = tempfile::tempdir().unwrap();
+    let spherical = dir.path().join("spherical.mp4");
+    std::fs::write(
+        &spherical,
+        common::init_segment_with_stsd(&[b"iso6"], &common::spherical_vp09_stsd()),
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(&spherical);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "[media.track.video.spatial]\n\
+         stereo_mode = 1\n\
+         stereo_mode_name = \"top-bottom\"\n\
+         metadata_source = \"mpn test\"\n\
+         pose_yaw = 90.000\n\
+         pose_pitch = 0.000\n\
+         pose_roll = 0.000\n\
+         projection = \"equirectangular\"\n\
+         bounds_top = 0.250000\n\
+         bounds_bottom = 0.250000\n\
+         bounds_left = 0.000000\n\
+         bounds_right = 0.000000\n",
+    ));
+
+    let stereo = dir.path().join("stereo.mp4");
+    std::fs::write(
+        &stereo,
+        common::init_segment_with_stsd(&[b"iso6"], &common::stereo_hvc1_stsd()),
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(&stereo);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "[media.track.video.spatial]\n\
+         has_left_eye = true\n\
+         has_right_eye = true\n\
+         eye_views_reversed = true\n\
+         has_additional_views = false\n\
+         hero_eye = \"left\"\n\
+         baseline_mm = 63.500\n\
+         disparity_adjustment = 0.0200\n\
+         projection_kind = \"rect\"\n\
+         horizontal_field_of_view = 65.000\n",
+    ));
+}
+
+#[test]
+fn integ_cli_itunes_tags() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("tagged.m4a");
+    let file = common::audio_init_segment(&common::opus_stsd());
+    std::fs::write(
+        &file_path,
+        common::append_to_moov(&file, &common::tagged_udta()),
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(&file_path);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "[media.tags]\n\
+         title = \"Episode 1\"\n\
+         artist = \"mpn\"\n\
+         album = \"Caf\u{e9} Sessions\"\n\
+         date = \"2026\"\n\
+         encoder = \"Lavf61.7.100\"\n\
+         track = [3, 12]\n\
+         disk = [1, 2]\n\
+         description = \"Short description\"\n\
+         long_description = \"Long description\"\n\
+         tv_show = \"Show\"\n\
+         [media.tags.cover]\n\
+         format = \"png\"\n\
+         size = 33\n\
+         width = 300\n\
+         height = 200\n\
+         [media.tags.freeform]\n\
+         mean = \"com.apple.iTunes\"\n\
+         name = \"iTunSMPB\"\n\
+         value = \" 00000000 00000840\"\n",
+    ));
+}
+
+#[test]
+fn integ_cli_extract_cover() {
+    let dir = tempfile::tempdir().unwrap();
+    let cover_dir = dir.path().join("covers");
+    let cover_path = cover_dir.join("cover-1.jpg");
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("--extract-cover")
+        .arg(&cover_dir)
+        .arg(common::TEST_BOKEH_AU_2T_VD_30F_854X480_MP4_FILE);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "[media.tags.cover]\n\
+         format = \"jpeg\"\n\
+         size = 111280\n\
+         width = 854\n\
+         height = 450\n\
+         path = {:?}\n",
+            cover_path.display().to_string()
+        )));
+    let cover = std::fs::read(&cover_path).unwrap();
+    assert_eq!(cover.len(), 111280);
+    assert!(cover.starts_with(&[0xff, 0xd8, 0xff]));
+}
+
+#[test]
+fn integ_cli_chapters() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("audiobook.m4b");
+    let vtt_path = dir.path().join("chapters.vtt");
+    std::fs::write(
+        &file_path,
+        common::chaptered_m4a(&["Opening", "Chapter 1", "Chapter 2"]),
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("--export-chapters").arg(&vtt_path).arg(&file_path);
+    cmd.assert().success().stdout(
+        predicate::str::contains(
+            "[media.chapters]\n\
+             source = \"chap\"\n\
+             track_id = 2\n\
+             count = 3\n\
+             [media.chapters.chapter]\n\
+             index = 1\n\
+             start = 0.000\n\
+             end = 60.000\n\
+             title = \"Opening\"\n",
+        )
+        .and(predicate::str::contains(
+            "[media.chapters]\n\
+             source = \"chpl\"\n\
+             count = 3\n",
+        ))
+        .and(predicate::str::contains(
+            "index = 3\n\
+             start = 90.500\n\
+             end = 120.000\n\
+             title = \"Chapter 2\"\n",
+        ))
+        .and(predicate::str::contains("warning").not()),
+    );
+    assert_eq!(
+        std::fs::read_to_string(&vtt_path).unwrap(),
+        "WEBVTT\n\n\
+         1\n00:00:00.000 --> 00:01:00.000\nOpening\n\n\
+         2\n00:01:00.000 --> 00:01:30.500\nChapter 1\n\n\
+         3\n00:01:30.500 --> 00:02:00.000\nChapter 2\n"
+    );
+}
+
+#[test]
+fn integ_cli_chapters_mismatch() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("audiobook.m4b");
+    let ffmetadata_path = dir.path().join("chapters.txt");
+    std::fs::write(&file_path, common::chaptered_m4a(&["Opening", "Chapter 1"])).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("--export-chapters")
+        .arg(&ffmetadata_path)
+        .arg(&file_path);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "warning = \"chapter track has 3 chapters but chpl has 2\"",
+    ));
+    let ffmetadata = std::fs::read_to_string(&ffmetadata_path).unwrap();
+    assert!(ffmetadata.starts_with(";FFMETADATA1\n"));
+    assert!(ffmetadata
+        .contains("[CHAPTER]\nTIMEBASE=1/1000\nSTART=60000\nEND=90500\ntitle=Chapter 1\n"));
+}
+
+#[test]
+fn integ_cli_export_chapters_without_chapters() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("--export-chapters")
+        .arg(dir.path().join("chapters.vtt"))
+        .arg(common::TEST_BOKEH_AU_2T_VD_30F_854X480_MP4_FILE);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("no chapters to export"));
+}
+
+#[test]
+fn integ_cli_chapters_truncated_chpl() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("truncated.m4a");
+    let mut data = common::chaptered_m4a(&["Opening", "Chapter 1", "Chapter 2"]);
+    // claim a fourth chpl entry that is not there
+    let chpl = data.windows(4).position(|w| w == b"chpl").unwrap();
+    data[chpl + 12] = 4;
+    std::fs::write(&file_path, data).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(&file_path);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("[media.chapters]\nerror = "));
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("--export-chapters")
+        .arg(dir.path().join("chapters.vtt"))
+        .arg(&file_path);
+    cmd.assert().failure();
+}
+
+#[test]
+fn integ_cli_capture_mdta() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("iphone.mov");
+    let file = common::init_segment_with_stsd(&[b"qt  "], &common::hvc1_stsd());
+    std::fs::write(
+        &file_path,
+        common::append_to_moov(&file, &common::mdta_meta()),
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(&file_path);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "[media.capture]\n\
+         make = \"Apple\"\n\
+         model = \"iPhone 15 Pro\"\n\
+         software = \"17.4.1\"\n\
+         creation_date = \"2026-05-01T10:15:30-0700\"\n\
+         location_iso6709 = \"+37.3349-122.0090+018.000/\"\n\
+         latitude = 37.334900\n\
+         longitude = -122.009000\n\
+         altitude = 18.000\n\
+         horizontal_accuracy = 4.750\n\
+         location_source = \"mdta\"\n",
+    ));
+}
+
+#[test]
+fn integ_cli_capture_gopro_udta() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("gopro.mp4");
+    let file = common::init_segment(&[b"mp41"]);
+    std::fs::write(
+        &file_path,
+        common::append_to_moov(&file, &common::gopro_udta()),
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg(&file_path);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "[media.capture]\n\
+         make = \"GoPro\"\n\
+         model = \"HERO12 Black\"\n\
+         firmware = \"H23.01.02.32.00\"\n\
+         serial_number = \"C3501324500001\"\n\
+         location_iso6709 = \"+48.8584+002.2945+035.000/\"\n\
+         location_name = \"Eiffel Tower\"\n\
+         latitude = 48.858400\n\
+         longitude = 2.294500\n\
+         altitude = 35.000\n\
+         location_source = \"\u{a9}xyz\"\n",
+    ));
+}
+
+#[test]
+fn integ_cli_mime_skips_chapter_track() {
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("audiobook.m4b");
+    std::fs::write(
+        &file_path,
+        common::chaptered_m4a(&["Opening", "Chapter 1", "Chapter 2"]),
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("--mime").arg(&file_path);
+    cmd.assert()
+        .success()
+        .stdout("audio/mp4; codecs=\"Opus\"\n");
+}
+
 // #[test]
 // fn integ_cli_invalid_file_path() {
 //     let mut cmd = std::process::Command::main_binary().unwrap();
@@ -86,7 +1268,7 @@ fn integ_cli_invalid_stdout_media_track_audio() {
 //         .stderr("error = \"No such file or directory (os error 2)\"\n");
 // }
 
-/// if need by, run with: cargo test -- --nocapture
+// if need by, run with: cargo test -- --nocapture
 // #[test]
 // fn integ_cli_valid_media_creation_time() {
 //     common::setup();